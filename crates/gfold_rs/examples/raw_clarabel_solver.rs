@@ -1,9 +1,10 @@
 #![allow(non_snake_case)]
 #![allow(clippy::too_many_lines)]
+// Index loops mirror the component-wise equations
+#![allow(clippy::needless_range_loop)]
 
 use clarabel::algebra::*;
 use clarabel::solver::*;
-use plotters::prelude::*;
 
 fn main() {
     // -------------------------------------------------------
@@ -0,0 +1,408 @@
+//! `gfold` command-line tool.
+//!
+//! Solve, plot, verify and sweep APDG scenarios stored as TOML or JSON
+//! [`Settings`] files.
+//!
+//! Exit codes:
+//! - `0` success
+//! - `1` any other error (I/O, bad scenario, solver failure)
+//! - `2` invalid command-line arguments
+//! - `3` a subproblem was infeasible
+//! - `4` the SC loop did not converge
+//! - `5` a solution failed verification
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use gfold_rs::{
+    io::{self, Format},
+    plotting,
+    trajectories::{APDGProblemSolver, Error, Integrator, Settings},
+};
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_INFEASIBLE: u8 = 3;
+const EXIT_NOT_CONVERGED: u8 = 4;
+const EXIT_VERIFY_FAILED: u8 = 5;
+
+#[derive(Parser, Debug)]
+#[command(name = "gfold", version, about = "Powered descent guidance solver")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Solve a scenario and write the solution
+    Solve {
+        /// Scenario file (.toml or .json)
+        scenario: PathBuf,
+        /// Output trajectory file (.toml or .json)
+        #[arg(short, long, default_value = "solution.json")]
+        output: PathBuf,
+        /// Also write the convergence history to a separate file
+        #[arg(long)]
+        convergence: Option<PathBuf>,
+    },
+    /// Render the standard charts for a saved solution
+    Plot {
+        /// Trajectory file written by `gfold solve`
+        solution: PathBuf,
+        /// Scenario used for limits and reference vectors, defaults if omitted
+        #[arg(short, long)]
+        scenario: Option<PathBuf>,
+        /// Directory the charts are written to
+        #[arg(short = 'd', long, default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// Check a saved solution against its constraints and the touchdown miss
+    /// of its propagated thrust profile, optionally also the propagation drift
+    Verify {
+        /// Trajectory file written by `gfold solve`
+        solution: PathBuf,
+        /// Scenario the solution was generated from, defaults if omitted
+        #[arg(short, long)]
+        scenario: Option<PathBuf>,
+        /// Relative tolerance for constraint violations
+        #[arg(short, long, default_value_t = 1e-4)]
+        tolerance: f64,
+        /// Maximum allowed touchdown miss of the propagated trajectory [m]
+        #[arg(long, default_value_t = 1.0)]
+        max_miss: f64,
+        /// Maximum allowed position drift of the propagated trajectory from
+        /// the solution nodes [m], not checked if omitted
+        #[arg(long)]
+        max_drift: Option<f64>,
+    },
+    /// Solve a scenario for a range of values of one parameter
+    Sweep {
+        /// Base scenario file (.toml or .json)
+        scenario: PathBuf,
+        /// Parameter to vary, e.g. `simulation_settings.m_0` or just `m_0`
+        #[arg(short, long)]
+        param: String,
+        /// Explicit values, parsed as JSON (e.g. `15000` or `[500,500,0]`)
+        #[arg(long, num_args = 1.., conflicts_with = "linspace")]
+        values: Vec<String>,
+        /// Evenly spaced scalar values: FROM TO COUNT
+        #[arg(long, num_args = 3, value_names = ["FROM", "TO", "COUNT"])]
+        linspace: Vec<f64>,
+        /// CSV summary output, printed to stdout if omitted. Solver progress
+        /// always goes to stderr
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Solve {
+            scenario,
+            output,
+            convergence,
+        } => solve(&scenario, &output, convergence.as_deref()),
+        Command::Plot {
+            solution,
+            scenario,
+            out_dir,
+        } => plot(&solution, scenario.as_deref(), &out_dir),
+        Command::Verify {
+            solution,
+            scenario,
+            tolerance,
+            max_miss,
+            max_drift,
+        } => verify(
+            &solution,
+            scenario.as_deref(),
+            tolerance,
+            max_miss,
+            max_drift,
+        ),
+        Command::Sweep {
+            scenario,
+            param,
+            values,
+            linspace,
+            output,
+        } => sweep(&scenario, &param, &values, &linspace, output.as_deref()),
+    };
+
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+type CliResult = Result<u8, Box<dyn std::error::Error>>;
+
+fn load_settings(scenario: Option<&Path>) -> Result<Settings, io::Error> {
+    match scenario {
+        Some(path) => io::load(path),
+        None => Ok(Settings::default()),
+    }
+}
+
+fn solver_exit_code(error: &Error) -> u8 {
+    if error.is_infeasible() {
+        EXIT_INFEASIBLE
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+fn solve(scenario: &Path, output: &Path, convergence: Option<&Path>) -> CliResult {
+    let settings: Settings = io::load(scenario)?;
+
+    let (solution, history) = match APDGProblemSolver::default().solve(&settings) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return Ok(solver_exit_code(&e));
+        }
+    };
+
+    io::save_solution(output, &solution, Some(&history))?;
+    if let Some(path) = convergence {
+        io::save(&history, path)?;
+    }
+
+    if history.converged {
+        Ok(0)
+    } else {
+        eprintln!(
+            "warning: SC loop did not converge in {} iterations",
+            history.len()
+        );
+        Ok(EXIT_NOT_CONVERGED)
+    }
+}
+
+fn plot(solution: &Path, scenario: Option<&Path>, out_dir: &Path) -> CliResult {
+    let settings = load_settings(scenario)?;
+    let file = io::load_trajectory_file(solution)?;
+    let sol = file.to_solution();
+    let sim = settings.simulation_settings();
+
+    fs::create_dir_all(out_dir)?;
+    let out = |name: &str| out_dir.join(name).to_string_lossy().into_owned();
+
+    plotting::plot_trajectory_3d(&out("trajectory_chart.png"), "U-E-N Trajectory", &sol)?;
+    plotting::plot_position_velocity_time(&out("pos_vel_chart.png"), &sol)?;
+    plotting::plot_thrust_time(&out("thrust_chart.png"), &sol, sim)?;
+    plotting::plot_mass_time(&out("mass_chart.png"), &sol, sim)?;
+    if let Some(hist) = &file.convergence {
+        plotting::plot_convergence(&out("convergence_chart.png"), hist)?;
+        plotting::plot_relaxation_convergence(&out("relaxation_convergence_chart.png"), hist)?;
+    }
+
+    Ok(0)
+}
+
+fn verify(
+    solution: &Path,
+    scenario: Option<&Path>,
+    tolerance: f64,
+    max_miss: f64,
+    max_drift: Option<f64>,
+) -> CliResult {
+    let settings = load_settings(scenario)?;
+    let sol = io::load_solution(solution)?;
+    let sim = settings.simulation_settings();
+
+    let violations = sol.check_constraints(sim, tolerance);
+    for violation in &violations {
+        println!("{violation}");
+    }
+
+    let report = sol.propagate(sim, Integrator::default());
+    println!(
+        "Propagation: max drift pos {:.3e} m, vel {:.3e} m/s, mass {:.3e} kg, touchdown miss {:.3e} m",
+        report.max_pos_drift(),
+        report.max_vel_drift(),
+        report.max_mass_drift(),
+        report.touchdown_miss
+    );
+
+    let drift_ok = max_drift.is_none_or(|max| report.max_pos_drift() <= max);
+    if violations.is_empty() && report.touchdown_miss <= max_miss && drift_ok {
+        println!("OK");
+        Ok(0)
+    } else {
+        println!("FAILED");
+        Ok(EXIT_VERIFY_FAILED)
+    }
+}
+
+fn sweep(
+    scenario: &Path,
+    param: &str,
+    values: &[String],
+    linspace: &[f64],
+    output: Option<&Path>,
+) -> CliResult {
+    let base: serde_json::Value =
+        Format::from_path(scenario)?.parse(&fs::read_to_string(scenario)?)?;
+
+    let values: Vec<serde_json::Value> = if linspace.len() == 3 {
+        linspace_values(linspace[0], linspace[1], linspace[2] as usize)
+    } else {
+        values
+            .iter()
+            .map(|v| serde_json::from_str(v).unwrap_or_else(|_| serde_json::json!(v)))
+            .collect()
+    };
+    if values.is_empty() {
+        return Err("no sweep values given, use --values or --linspace".into());
+    }
+
+    let mut csv = String::from("value,status,final_mass,propellant,time_of_flight,iterations\n");
+    for value in values {
+        let mut scenario_value = base.clone();
+        set_param(&mut scenario_value, param, value.clone())?;
+        let settings: Settings = serde_json::from_value(scenario_value)?;
+
+        let row = match APDGProblemSolver::default().solve(&settings) {
+            Ok((sol, hist)) => {
+                let m_f = sol.steps().last().map_or(f64::NAN, |s| s.m);
+                let status = if hist.converged {
+                    "ok"
+                } else {
+                    "not_converged"
+                };
+                format!(
+                    "{status},{m_f},{},{},{}",
+                    settings.simulation_settings().m_0 - m_f,
+                    sol.duration(),
+                    hist.len()
+                )
+            }
+            Err(e) if e.is_infeasible() => "infeasible,,,,".to_string(),
+            Err(_) => "error,,,,".to_string(),
+        };
+        csv.push_str(&format!("{},{row}\n", csv_field(&value)));
+    }
+
+    match output {
+        Some(path) => fs::write(path, csv)?,
+        None => print!("{csv}"),
+    }
+    Ok(0)
+}
+
+/// `count` evenly spaced values from `from` to `to`.
+///
+/// Integral values are written as JSON integers so integer parameters such
+/// as `N` or `n_sc` can be swept, float parameters accept them as well.
+fn linspace_values(from: f64, to: f64, count: usize) -> Vec<serde_json::Value> {
+    (0..count)
+        .map(|i| {
+            let frac = if count > 1 {
+                i as f64 / (count - 1) as f64
+            } else {
+                0.0
+            };
+            let value = from + (to - from) * frac;
+            if value.fract() == 0.0 && value.abs() < 2f64.powi(53) {
+                serde_json::json!(value as i64)
+            } else {
+                serde_json::json!(value)
+            }
+        })
+        .collect()
+}
+
+/// Quote a sweep value as a CSV field.
+///
+/// Strings are written without their JSON quotes, everything else as JSON so
+/// vectors survive. Embedded quotes are doubled.
+fn csv_field(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Set `param` in a scenario value, creating the section if needed.
+///
+/// A bare name is looked up in the simulation settings first, then the solver
+/// settings.
+fn set_param(
+    scenario: &mut serde_json::Value,
+    param: &str,
+    value: serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const SECTIONS: [&str; 2] = ["simulation_settings", "solver_settings"];
+
+    let (section, field) = match param.split_once('.') {
+        Some((section, field)) => (section.to_string(), field.to_string()),
+        None => {
+            // Probe which section accepts the field
+            let section = SECTIONS
+                .iter()
+                .find(|section| {
+                    let mut fields = serde_json::Map::new();
+                    fields.insert(param.to_string(), value.clone());
+                    let mut probe = serde_json::Map::new();
+                    probe.insert(section.to_string(), fields.into());
+                    serde_json::from_value::<Settings>(probe.into()).is_ok()
+                })
+                .ok_or_else(|| format!("unknown parameter: {param}"))?;
+            (section.to_string(), param.to_string())
+        }
+    };
+
+    let root = scenario.as_object_mut().ok_or("scenario must be a table")?;
+    let section = root
+        .entry(section)
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or("scenario section must be a table")?;
+    section.insert(field, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quotes_values() {
+        assert_eq!(csv_field(&serde_json::json!("Rk45")), "\"Rk45\"");
+        assert_eq!(
+            csv_field(&serde_json::json!("say \"hi\"")),
+            "\"say \"\"hi\"\"\""
+        );
+        assert_eq!(csv_field(&serde_json::json!([1.0, 2.5])), "\"[1.0,2.5]\"");
+        assert_eq!(csv_field(&serde_json::json!(3)), "\"3\"");
+    }
+
+    #[test]
+    fn test_linspace_sweeps_integer_parameters() {
+        let values = linspace_values(10.0, 20.0, 3);
+        assert_eq!(values, [10, 15, 20].map(|v| serde_json::json!(v)));
+        assert_eq!(linspace_values(0.0, 1.0, 3)[1], serde_json::json!(0.5));
+
+        let mut scenario = serde_json::json!({});
+        set_param(&mut scenario, "N", values[1].clone()).unwrap();
+        let settings: Settings = serde_json::from_value(scenario).unwrap();
+        assert_eq!(settings.solver_settings().N, 15);
+
+        // Float parameters take the integral values too
+        let mut scenario = serde_json::json!({});
+        set_param(&mut scenario, "m_0", values[0].clone()).unwrap();
+        let settings: Settings = serde_json::from_value(scenario).unwrap();
+        assert_eq!(settings.simulation_settings().m_0, 10.0);
+    }
+}
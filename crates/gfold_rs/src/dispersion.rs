@@ -0,0 +1,418 @@
+//! Monte Carlo dispersion analysis.
+//!
+//! A [`MonteCarlo`] run perturbs a nominal [`Settings`] according to a
+//! [`DispersionModel`], solves every sample in parallel and aggregates the
+//! outcomes in a [`DispersionReport`].
+//!
+//! All samples are drawn up front from a single seeded generator, so a run is
+//! reproducible for a given seed regardless of the number of threads.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use bon::Builder;
+use nalgebra::Vector3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+
+use crate::trajectories::{APDGProblemSolver, Error, Settings};
+
+/// Random perturbation added to a nominal value, in the units of the value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Perturbation {
+    /// Keep the nominal value
+    #[default]
+    None,
+    /// Zero-mean Gaussian with the given standard deviation
+    Normal(f64),
+    /// Uniform within plus or minus the given half width
+    Uniform(f64),
+}
+
+impl Perturbation {
+    /// Draw a perturbed value.
+    pub fn apply(&self, nominal: f64, rng: &mut impl Rng) -> f64 {
+        match *self {
+            Perturbation::None => nominal,
+            Perturbation::Normal(sigma) => {
+                // A non-finite or negative sigma is a usage error, fall back to nominal
+                Normal::new(0.0, sigma).map_or(nominal, |d| nominal + d.sample(rng))
+            }
+            Perturbation::Uniform(half_width) if half_width > 0.0 => {
+                nominal + rng.gen_range(-half_width..=half_width)
+            }
+            Perturbation::Uniform(_) => nominal,
+        }
+    }
+
+    /// Draw a perturbed vector, each component independently.
+    pub fn apply_vec(&self, nominal: &Vector3<f64>, rng: &mut impl Rng) -> Vector3<f64> {
+        nominal.map(|x| self.apply(x, rng))
+    }
+}
+
+/// Dispersions applied to the simulation parameters.
+#[derive(Debug, Clone, Default, Builder)]
+pub struct DispersionModel {
+    /// Initial position, per component
+    /// [m]
+    #[builder(default)]
+    pub r0: Perturbation,
+
+    /// Initial velocity, per component
+    /// [m/s]
+    #[builder(default)]
+    pub v0: Perturbation,
+
+    /// Initial mass
+    /// [kg]
+    #[builder(default)]
+    pub m_0: Perturbation,
+
+    /// Specific impulse
+    /// [s]
+    #[builder(default)]
+    pub i_sp: Perturbation,
+
+    /// Drag coefficient
+    #[builder(default)]
+    pub c_d: Perturbation,
+
+    /// Ambient fluid density
+    /// [kg/m^3]
+    #[builder(default)]
+    pub rho: Perturbation,
+
+    /// Minimum vacuum thrust
+    /// [N]
+    #[builder(default)]
+    pub t_min_vac: Perturbation,
+
+    /// Maximum vacuum thrust
+    /// [N]
+    #[builder(default)]
+    pub t_max_vac: Perturbation,
+}
+
+impl DispersionModel {
+    /// Draw a dispersed copy of `nominal`.
+    pub fn sample(&self, nominal: &Settings, rng: &mut impl Rng) -> Settings {
+        let mut sim = nominal.simulation_settings().clone();
+        sim.r0 = self.r0.apply_vec(&sim.r0, rng);
+        sim.v0 = self.v0.apply_vec(&sim.v0, rng);
+        sim.m_0 = self.m_0.apply(sim.m_0, rng);
+        sim.i_sp = self.i_sp.apply(sim.i_sp, rng);
+        sim.c_d = self.c_d.apply(sim.c_d, rng);
+        sim.rho = self.rho.apply(sim.rho, rng);
+        sim.t_min_vac = self.t_min_vac.apply(sim.t_min_vac, rng);
+        sim.t_max_vac = self.t_max_vac.apply(sim.t_max_vac, rng);
+
+        Settings::builder()
+            .simulation_settings(sim)
+            .solver_settings(nominal.solver_settings().clone())
+            .initial_guess(nominal.initial_guess().clone())
+            .constraints(nominal.constraints().to_vec())
+            .build()
+    }
+}
+
+/// Why a dispersed run failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureKind {
+    /// The sample was rejected by [`Settings::validate`]
+    InvalidSettings,
+    /// A convex subproblem was infeasible
+    Infeasible,
+    /// NaN or infinity in the solver output
+    Numeric,
+    /// Any other solver failure
+    Solver,
+}
+
+impl FailureKind {
+    /// Classify a solver error.
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::InvalidSettings(_) => FailureKind::InvalidSettings,
+            Error::NumericError(_) => FailureKind::Numeric,
+            Error::SCError(_, inner) => FailureKind::from_error(inner),
+            e if e.is_infeasible() => FailureKind::Infeasible,
+            _ => FailureKind::Solver,
+        }
+    }
+}
+
+/// Outcome of a single dispersed run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    /// The solver returned a trajectory
+    Solved {
+        /// Final mass [kg]
+        final_mass: f64,
+        /// Number of SC iterations
+        iterations: usize,
+        /// Whether the SC loop met its tolerance
+        converged: bool,
+    },
+    /// The solver failed
+    Failed(FailureKind),
+}
+
+/// A dispersed sample and its outcome.
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    /// Settings the run was solved with
+    pub settings: Settings,
+    /// What the solver returned
+    pub outcome: RunOutcome,
+}
+
+/// Sample statistics of a quantity over the runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    /// Number of values
+    pub count: usize,
+    /// Mean
+    pub mean: f64,
+    /// Sample standard deviation, zero for a single value
+    pub std_dev: f64,
+    /// Smallest value
+    pub min: f64,
+    /// Largest value
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarise a set of values, `None` if empty.
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = if count > 1 {
+            values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (count - 1) as f64
+        } else {
+            0.0
+        };
+
+        Some(Summary {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// Aggregated outcome of a [`MonteCarlo`] run.
+#[derive(Debug, Clone)]
+pub struct DispersionReport {
+    /// Every run, in sample order
+    pub runs: Vec<RunResult>,
+}
+
+impl DispersionReport {
+    /// Fraction of runs that converged.
+    pub fn success_rate(&self) -> f64 {
+        if self.runs.is_empty() {
+            return 0.0;
+        }
+        let converged = self
+            .runs
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r.outcome,
+                    RunOutcome::Solved {
+                        converged: true,
+                        ..
+                    }
+                )
+            })
+            .count();
+        converged as f64 / self.runs.len() as f64
+    }
+
+    /// Final mass over all runs that returned a trajectory.
+    pub fn final_mass(&self) -> Option<Summary> {
+        Summary::from_values(&self.solved().map(|(m, _)| m).collect::<Vec<_>>())
+    }
+
+    /// SC iteration count over all runs that returned a trajectory.
+    pub fn iterations(&self) -> Option<Summary> {
+        Summary::from_values(&self.solved().map(|(_, i)| i as f64).collect::<Vec<_>>())
+    }
+
+    /// Number of failed runs per category.
+    pub fn failures(&self) -> BTreeMap<FailureKind, usize> {
+        let mut failures = BTreeMap::new();
+        for run in &self.runs {
+            if let RunOutcome::Failed(kind) = run.outcome {
+                *failures.entry(kind).or_insert(0) += 1;
+            }
+        }
+        failures
+    }
+
+    fn solved(&self) -> impl Iterator<Item = (f64, usize)> + '_ {
+        self.runs.iter().filter_map(|r| match r.outcome {
+            RunOutcome::Solved {
+                final_mass,
+                iterations,
+                ..
+            } => Some((final_mass, iterations)),
+            RunOutcome::Failed(_) => None,
+        })
+    }
+}
+
+/// A Monte Carlo campaign around a nominal scenario.
+#[derive(Debug, Clone, Builder)]
+pub struct MonteCarlo {
+    /// Nominal scenario
+    nominal: Settings,
+
+    /// Dispersions applied to the nominal scenario
+    model: DispersionModel,
+
+    /// Number of dispersed runs
+    #[builder(default = 100)]
+    runs: usize,
+
+    /// Seed of the random generator
+    #[builder(default = 0)]
+    seed: u64,
+
+    /// Number of worker threads, defaults to the available parallelism
+    #[builder(default = thread::available_parallelism().map_or(1, |n| n.get()))]
+    threads: usize,
+}
+
+impl MonteCarlo {
+    /// Draw the dispersed settings for every run.
+    pub fn samples(&self) -> Vec<Settings> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        (0..self.runs)
+            .map(|_| self.model.sample(&self.nominal, &mut rng))
+            .collect()
+    }
+
+    /// Solve every sample and collect the outcomes.
+    pub fn run(&self) -> DispersionReport {
+        let samples = self.samples();
+        let next = AtomicUsize::new(0);
+        let outcomes = Mutex::new(vec![None; samples.len()]);
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.clamp(1, samples.len().max(1)) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(settings) = samples.get(i) else {
+                        break;
+                    };
+                    let outcome = solve_one(settings);
+                    outcomes.lock().expect("outcome lock poisoned")[i] = Some(outcome);
+                });
+            }
+        });
+
+        let runs = samples
+            .into_iter()
+            .zip(outcomes.into_inner().expect("outcome lock poisoned"))
+            .map(|(settings, outcome)| RunResult {
+                settings,
+                outcome: outcome.expect("every sample is solved"),
+            })
+            .collect();
+
+        DispersionReport { runs }
+    }
+}
+
+fn solve_one(settings: &Settings) -> RunOutcome {
+    match APDGProblemSolver::default().solve(settings) {
+        Ok((solution, history)) => RunOutcome::Solved {
+            final_mass: solution.steps().last().map_or(f64::NAN, |s| s.m),
+            iterations: history.len(),
+            converged: history.converged,
+        },
+        Err(e) => RunOutcome::Failed(FailureKind::from_error(&e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::SimulationParams;
+
+    #[test]
+    fn test_samples_are_reproducible() {
+        let model = DispersionModel::builder()
+            .r0(Perturbation::Normal(50.0))
+            .m_0(Perturbation::Uniform(200.0))
+            .build();
+        let campaign = |seed| {
+            MonteCarlo::builder()
+                .nominal(Settings::default())
+                .model(model.clone())
+                .runs(5)
+                .seed(seed)
+                .build()
+                .samples()
+        };
+
+        let (a, b, c) = (campaign(7), campaign(7), campaign(8));
+        let r0 = |s: &[Settings]| {
+            s.iter()
+                .map(|x| x.simulation_settings().r0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(r0(&a), r0(&b));
+        assert_ne!(r0(&a), r0(&c));
+        // Undispersed fields keep their nominal value
+        assert!(a
+            .iter()
+            .all(|s| s.simulation_settings().i_sp == SimulationParams::default().i_sp));
+    }
+
+    #[test]
+    fn test_failures_are_categorised() {
+        // Every sample has less propellant than dry mass and fails validation
+        let nominal = Settings::builder()
+            .simulation_settings(SimulationParams::builder().m_0(5_000.0).build())
+            .build();
+        let report = MonteCarlo::builder()
+            .nominal(nominal)
+            .model(
+                DispersionModel::builder()
+                    .m_0(Perturbation::Uniform(10.0))
+                    .build(),
+            )
+            .runs(8)
+            .threads(3)
+            .build()
+            .run();
+
+        assert_eq!(report.runs.len(), 8);
+        assert_eq!(report.success_rate(), 0.0);
+        assert!(report.final_mass().is_none());
+        assert_eq!(report.failures()[&FailureKind::InvalidSettings], 8);
+    }
+
+    #[test]
+    fn test_summary_statistics() {
+        let summary = Summary::from_values(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert!((summary.std_dev - (5.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,415 @@
+//! Tabular export of APDG solutions.
+//!
+//! A [`TrajectoryTable`] holds one named column per quantity and one row per
+//! node. It can be written as CSV, or as Parquet with the `parquet` feature,
+//! and read back from either.
+//!
+//! | Column              | Unit  |
+//! |---------------------|-------|
+//! | `time`              | s     |
+//! | `r_u` `r_e` `r_n`   | m     |
+//! | `v_u` `v_e` `v_n`   | m/s   |
+//! | `a_u` `a_e` `a_n`   | m/s^2 |
+//! | `m`                 | kg    |
+//! | `t_u` `t_e` `t_n`   | N     |
+//! | `gamma`             | N     |
+//! | `throttle`          | -     |
+//! | `tilt`              | deg   |
+//! | `azimuth`           | deg   |
+//! | `glide_slope`       | deg   |
+//! | `aR_u` `aR_e` `aR_n`| m/s^2 |
+
+use std::io::{BufRead, Write};
+
+use nalgebra::Vector3;
+use thiserror::Error;
+
+use crate::{
+    plotting::data_extraction::calculate_angles,
+    trajectories::{APDGSolution, APDGSolutionTimeStep, SimulationParams},
+};
+
+/// Column names in the order they are written.
+pub const COLUMNS: [&str; 22] = [
+    "time",
+    "r_u",
+    "r_e",
+    "r_n",
+    "v_u",
+    "v_e",
+    "v_n",
+    "a_u",
+    "a_e",
+    "a_n",
+    "m",
+    "t_u",
+    "t_e",
+    "t_n",
+    "gamma",
+    "throttle",
+    "tilt",
+    "azimuth",
+    "glide_slope",
+    "aR_u",
+    "aR_e",
+    "aR_n",
+];
+
+/// Errors from reading or writing tables.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Filesystem or stream error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Malformed CSV content.
+    #[error("Parse error on line {line}: {message}")]
+    Parse {
+        /// 1-based line number
+        line: usize,
+        /// What went wrong
+        message: String,
+    },
+
+    /// A column needed to rebuild a solution is not present.
+    #[error("Missing column: {0}")]
+    MissingColumn(String),
+
+    /// Parquet encoding or decoding error.
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+/// A solution laid out as named columns of equal length.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrajectoryTable {
+    columns: Vec<(String, Vec<f64>)>,
+}
+
+impl TrajectoryTable {
+    /// Tabulate a solution.
+    ///
+    /// `params` provides the up vector for the angle columns and the maximum
+    /// thrust for the throttle column.
+    pub fn from_solution(solution: &APDGSolution, params: &SimulationParams) -> Self {
+        let mut columns: Vec<(String, Vec<f64>)> = COLUMNS
+            .iter()
+            .map(|name| (name.to_string(), Vec::with_capacity(solution.num_steps())))
+            .collect();
+
+        let landing_site = params.target.state(params, solution.duration()).0;
+        for (k, s) in solution.steps().iter().enumerate() {
+            let (tilt, azimuth) = calculate_angles(&s.t, &params.e_hat_up);
+            let row = [
+                k as f64 * solution.dt(),
+                s.r[0],
+                s.r[1],
+                s.r[2],
+                s.v[0],
+                s.v[1],
+                s.v[2],
+                s.a[0],
+                s.a[1],
+                s.a[2],
+                s.m,
+                s.t[0],
+                s.t[1],
+                s.t[2],
+                s.gamma,
+                s.gamma / params.t_max_vac,
+                tilt,
+                azimuth,
+                glide_slope_angle(&(s.r - landing_site), &params.e_hat_up),
+                s.aR[0],
+                s.aR[1],
+                s.aR[2],
+            ];
+            for ((_, column), value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+
+        TrajectoryTable { columns }
+    }
+
+    /// Number of rows (nodes) in the table
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, c)| c.len())
+    }
+
+    /// Column names in order
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Look up a column by name
+    pub fn column(&self, name: &str) -> Option<&[f64]> {
+        self.columns
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c.as_slice())
+    }
+
+    /// Write the table as CSV with a header row.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let header: Vec<&str> = self.column_names().collect();
+        writeln!(writer, "{}", header.join(","))?;
+
+        for row in 0..self.num_rows() {
+            let values: Vec<String> = self
+                .columns
+                .iter()
+                .map(|(_, c)| c[row].to_string())
+                .collect();
+            writeln!(writer, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Read a table written by [`TrajectoryTable::write_csv`].
+    ///
+    /// Any set of numeric columns is accepted, so tables with extra or fewer
+    /// columns can still be loaded.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or(Error::Parse {
+            line: 1,
+            message: "missing header".to_string(),
+        })??;
+        let mut columns: Vec<(String, Vec<f64>)> = header
+            .split(',')
+            .map(|name| (name.trim().to_string(), Vec::new()))
+            .collect();
+
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let line_no = i + 2;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != columns.len() {
+                return Err(Error::Parse {
+                    line: line_no,
+                    message: format!("expected {} fields, found {}", columns.len(), fields.len()),
+                });
+            }
+            for ((name, column), field) in columns.iter_mut().zip(fields) {
+                let value = field.trim().parse::<f64>().map_err(|e| Error::Parse {
+                    line: line_no,
+                    message: format!("column {name}: {e}"),
+                })?;
+                column.push(value);
+            }
+        }
+
+        Ok(TrajectoryTable { columns })
+    }
+
+    /// Rebuild a solution from the state and control columns.
+    pub fn to_solution(&self) -> Result<APDGSolution, Error> {
+        let col = |name: &str| {
+            self.column(name)
+                .ok_or_else(|| Error::MissingColumn(name.to_string()))
+        };
+        let vec3 = |prefix: &str| {
+            Ok::<_, Error>([
+                col(&format!("{prefix}_u"))?,
+                col(&format!("{prefix}_e"))?,
+                col(&format!("{prefix}_n"))?,
+            ])
+        };
+        let at = |c: &[&[f64]; 3], k: usize| Vector3::new(c[0][k], c[1][k], c[2][k]);
+
+        let time = col("time")?;
+        let (r, v, a, t, a_r) = (vec3("r")?, vec3("v")?, vec3("a")?, vec3("t")?, vec3("aR")?);
+        let (m, gamma) = (col("m")?, col("gamma")?);
+
+        let steps = (0..self.num_rows())
+            .map(|k| {
+                APDGSolutionTimeStep::builder()
+                    .r(at(&r, k))
+                    .v(at(&v, k))
+                    .a(at(&a, k))
+                    .m(m[k])
+                    .t(at(&t, k))
+                    .gamma(gamma[k])
+                    .aR(at(&a_r, k))
+                    .build()
+            })
+            .collect();
+        let dt = if time.len() > 1 {
+            time[1] - time[0]
+        } else {
+            0.0
+        };
+
+        Ok(APDGSolution::builder().steps(steps).dt(dt).build())
+    }
+
+    /// Write the table as a Parquet file with one `DOUBLE` column per quantity.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
+        use parquet::{
+            data_type::DoubleType,
+            file::{properties::WriterProperties, writer::SerializedFileWriter},
+            schema::parser::parse_message_type,
+        };
+        use std::sync::Arc;
+
+        let fields: String = self
+            .column_names()
+            .map(|name| format!("REQUIRED DOUBLE {name};"))
+            .collect();
+        let schema = Arc::new(parse_message_type(&format!(
+            "message trajectory {{ {fields} }}"
+        ))?);
+        let props = Arc::new(WriterProperties::builder().build());
+
+        let file = std::fs::File::create(path)?;
+        let mut writer = SerializedFileWriter::new(file, schema, props)?;
+        let mut row_group = writer.next_row_group()?;
+        for (_, values) in &self.columns {
+            let mut column = row_group
+                .next_column()?
+                .expect("schema has one field per column");
+            column
+                .typed::<DoubleType>()
+                .write_batch(values, None, None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Read a table written by [`TrajectoryTable::write_parquet`].
+    #[cfg(feature = "parquet")]
+    pub fn read_parquet(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let reader = SerializedFileReader::new(std::fs::File::open(path)?)?;
+        let mut columns: Vec<(String, Vec<f64>)> = reader
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| (c.name().to_string(), Vec::new()))
+            .collect();
+
+        for row in reader.get_row_iter(None)? {
+            let row = row?;
+            for (i, (_, column)) in columns.iter_mut().enumerate() {
+                column.push(row.get_double(i)?);
+            }
+        }
+
+        Ok(TrajectoryTable { columns })
+    }
+}
+
+/// Angle between the line of sight to the landing site and the up vector [deg]
+fn glide_slope_angle(r: &Vector3<f64>, up_vector: &Vector3<f64>) -> f64 {
+    let r_norm = r.norm();
+    if r_norm < 1e-6 {
+        return 0.0;
+    }
+    (r.dot(up_vector) / r_norm)
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution() -> APDGSolution {
+        let steps = (0..4)
+            .map(|k| {
+                let k = k as f64;
+                APDGSolutionTimeStep::builder()
+                    .r(Vector3::new(100.0 - 30.0 * k, 10.0, 0.0))
+                    .v(Vector3::new(-30.0, 0.0, 0.0))
+                    .a(Vector3::new(0.5, 0.0, 0.0))
+                    .m(15_000.0 - 100.0 * k)
+                    .t(Vector3::new(150_000.0, 1_000.0 * k, 0.0))
+                    .gamma(150_000.0 + 10.0 * k)
+                    .aR(Vector3::new(1e-7, 0.0, 0.0))
+                    .build()
+            })
+            .collect();
+        APDGSolution::builder().steps(steps).dt(0.25).build()
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let params = SimulationParams::builder().build();
+        let table = TrajectoryTable::from_solution(&solution(), &params);
+
+        let mut buffer = Vec::new();
+        table.write_csv(&mut buffer).unwrap();
+        let loaded = TrajectoryTable::read_csv(buffer.as_slice()).unwrap();
+
+        assert_eq!(loaded, table);
+        assert_eq!(loaded.column_names().collect::<Vec<_>>(), COLUMNS);
+
+        let rebuilt = loaded.to_solution().unwrap();
+        assert_eq!(rebuilt.dt(), 0.25);
+        for (a, b) in rebuilt.steps().iter().zip(solution().steps()) {
+            assert_eq!(a.r, b.r);
+            assert_eq!(a.t, b.t);
+            assert_eq!(a.gamma, b.gamma);
+        }
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_round_trip() {
+        let params = SimulationParams::builder().build();
+        let table = TrajectoryTable::from_solution(&solution(), &params);
+
+        let path = std::env::temp_dir().join(format!(
+            "gfold_export_round_trip_{}.parquet",
+            std::process::id()
+        ));
+        table.write_parquet(&path).unwrap();
+        let loaded = TrajectoryTable::read_parquet(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.num_rows(), table.num_rows());
+        assert_eq!(loaded.column_names().collect::<Vec<_>>(), COLUMNS);
+        for name in COLUMNS {
+            assert_eq!(loaded.column(name), table.column(name), "column {name}");
+        }
+    }
+
+    #[test]
+    fn test_derived_columns() {
+        let params = SimulationParams::builder().build();
+        let table = TrajectoryTable::from_solution(&solution(), &params);
+
+        let throttle = table.column("throttle").unwrap();
+        assert!((throttle[0] - 150_000.0 / params.t_max_vac).abs() < 1e-12);
+
+        // First node thrust is along the up axis
+        assert!(table.column("tilt").unwrap()[0].abs() < 1e-9);
+        let expected_gs = (10.0_f64).atan2(100.0).to_degrees();
+        assert!((table.column("glide_slope").unwrap()[0] - expected_gs).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_read_csv_rejects_ragged_rows() {
+        let csv = "time,m\n0.0,1.0\n0.5\n";
+        assert!(matches!(
+            TrajectoryTable::read_csv(csv.as_bytes()),
+            Err(Error::Parse { line: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+//! Hoverslam (suicide burn) guidance.
+//!
+//! The vehicle falls with the engine off and ignites at the altitude where a
+//! burn at a fixed fraction of maximum thrust brings it to rest exactly at the
+//! ground. The classic estimate assumes constant mass and no drag:
+//!
+//! ```text
+//! stop_distance = v^2 / (2 * (T / m - g))
+//! ```
+//!
+//! Here the burn is integrated instead, so the vehicle getting lighter and
+//! drag helping to slow it down are both accounted for. Only the vertical
+//! channel is modelled.
+//!
+//! During the burn [`Hoverslam::throttle`] recomputes the throttle that stops
+//! the vehicle at the ground from the current state, which corrects for any
+//! error in the ignition point.
+
+use bon::Builder;
+use nalgebra::Vector3;
+
+use crate::trajectories::{APDGSolution, SimulationParams};
+
+/// Integration step for the burn
+/// [s]
+const BURN_DT: f64 = 0.01;
+
+/// Burns longer than this are treated as never stopping
+/// [s]
+const MAX_BURN_TIME: f64 = 600.0;
+
+/// Bisection iterations when searching for the ignition altitude.
+const IGNITION_ITERATIONS: usize = 60;
+
+/// Vehicle model for the hoverslam.
+#[derive(Debug, Clone, Builder)]
+pub struct Hoverslam {
+    /// Gravitational acceleration magnitude
+    /// [m/s^2]
+    pub g: f64,
+
+    /// Minimum thrust
+    /// [N]
+    pub t_min: f64,
+
+    /// Maximum thrust
+    /// [N]
+    pub t_max: f64,
+
+    /// Specific impulse
+    /// [s]
+    pub i_sp: f64,
+
+    /// Standard gravity
+    /// [m/s^2]
+    #[builder(default = 9.807)]
+    pub g_0: f64,
+
+    /// Mass flow lost to back pressure at the nozzle exit
+    /// [kg/s]
+    #[builder(default = 0.0)]
+    pub m_dot_bp: f64,
+
+    /// Dry mass, a burn that reaches it fails
+    /// [kg]
+    pub m_dry: f64,
+
+    /// Ambient fluid density
+    /// [kg/m^3]
+    #[builder(default = 0.0)]
+    pub rho: f64,
+
+    /// Reference area for drag
+    /// [m^2]
+    #[builder(default = 0.0)]
+    pub s_d: f64,
+
+    /// Drag coefficient
+    #[builder(default = 0.0)]
+    pub c_d: f64,
+}
+
+/// Outcome of a constant throttle burn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurnPrediction {
+    /// Altitude lost before coming to rest
+    /// [m]
+    pub distance: f64,
+    /// Burn duration
+    /// [s]
+    pub duration: f64,
+    /// Propellant used
+    /// [kg]
+    pub propellant: f64,
+}
+
+/// Propellant used by a hoverslam and an APDG solution from the same state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuelComparison {
+    /// Propellant used by the hoverslam
+    /// [kg]
+    pub hoverslam: f64,
+    /// Propellant used by the APDG solution
+    /// [kg]
+    pub apdg: f64,
+}
+
+impl FuelComparison {
+    /// Extra propellant used by the hoverslam, negative if it uses less
+    /// [kg]
+    pub fn excess(&self) -> f64 {
+        self.hoverslam - self.apdg
+    }
+}
+
+impl Hoverslam {
+    /// Take the vehicle model from the solver parameters.
+    pub fn from_params(params: &SimulationParams) -> Self {
+        Hoverslam::builder()
+            .g(params.g_vec.norm())
+            .t_min(params.t_min_vac)
+            .t_max(params.t_max_vac)
+            .i_sp(params.i_sp)
+            .g_0(params.g_0)
+            .m_dot_bp((params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0))
+            .m_dry(params.m_dry)
+            .rho(params.rho)
+            .s_d(params.s_d)
+            .c_d(params.c_d)
+            .build()
+    }
+
+    /// Stop distance with constant mass and no drag.
+    ///
+    /// `None` if the thrust cannot overcome gravity.
+    pub fn ideal_stop_distance(&self, speed: f64, m: f64, throttle: f64) -> Option<f64> {
+        let decel = throttle * self.t_max / m - self.g;
+        (decel > 0.0).then(|| speed.powi(2) / (2.0 * decel))
+    }
+
+    /// Integrate a burn at constant `throttle` from a downward `speed`.
+    ///
+    /// `None` if the vehicle does not come to rest before running out of
+    /// propellant.
+    pub fn burn(&self, speed: f64, m: f64, throttle: f64) -> Option<BurnPrediction> {
+        let thrust = throttle * self.t_max;
+        let m_dot = thrust / (self.i_sp * self.g_0) + self.m_dot_bp;
+        let k = self.drag_coeff();
+
+        // Downward acceleration, mass is linear in time so only speed is
+        // integrated and the distance follows from the RK4 speed samples
+        let accel = |s: f64, m: f64| self.g - (thrust + k * s * s.abs()) / m;
+
+        let (mut s, mut x, mut t) = (speed, 0.0, 0.0);
+        while s > 0.0 {
+            let m_t = m - m_dot * t;
+            let m_next = m_t - m_dot * BURN_DT;
+            if t > MAX_BURN_TIME || m_next <= self.m_dry {
+                return None;
+            }
+            let m_half = 0.5 * (m_t + m_next);
+
+            let s1 = s;
+            let k1 = accel(s1, m_t);
+            let s2 = s + 0.5 * BURN_DT * k1;
+            let k2 = accel(s2, m_half);
+            let s3 = s + 0.5 * BURN_DT * k2;
+            let k3 = accel(s3, m_half);
+            let s4 = s + BURN_DT * k3;
+            let k4 = accel(s4, m_next);
+
+            let s_next = s + BURN_DT / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+            let dx = BURN_DT / 6.0 * (s1 + 2.0 * s2 + 2.0 * s3 + s4);
+
+            // Interpolate the zero crossing within the last step
+            let frac = if s_next <= 0.0 { s / (s - s_next) } else { 1.0 };
+            x += frac * dx;
+            t += frac * BURN_DT;
+            s = s_next;
+        }
+
+        Some(BurnPrediction {
+            distance: x,
+            duration: t,
+            propellant: m_dot * t,
+        })
+    }
+
+    /// Downward speed after falling `distance` with the engine off.
+    ///
+    /// With quadratic drag the squared speed relaxes exponentially towards
+    /// the terminal velocity, so this is exact.
+    pub fn free_fall_speed(&self, speed: f64, m: f64, distance: f64) -> f64 {
+        let k = self.drag_coeff();
+        let e0 = speed.powi(2);
+        let e = if k > 0.0 {
+            let terminal = self.g * m / k;
+            terminal + (e0 - terminal) * (-2.0 * k * distance / m).exp()
+        } else {
+            e0 + 2.0 * self.g * distance
+        };
+        e.max(0.0).sqrt()
+    }
+
+    /// Altitude at which to ignite to stop at the ground.
+    ///
+    /// `vertical_speed` is positive up. An ascending vehicle is assumed to
+    /// coast to its drag free apex first, which ignites slightly early. `None`
+    /// if it is already too late or the burn cannot stop the vehicle at all.
+    pub fn ignition_altitude(
+        &self,
+        altitude: f64,
+        vertical_speed: f64,
+        m: f64,
+        throttle: f64,
+    ) -> Option<f64> {
+        let (altitude, speed) = if vertical_speed > 0.0 {
+            (altitude + vertical_speed.powi(2) / (2.0 * self.g), 0.0)
+        } else {
+            (altitude, -vertical_speed)
+        };
+
+        // Positive while igniting at h still stops above the ground
+        let margin = |h: f64| {
+            let s = self.free_fall_speed(speed, m, altitude - h);
+            self.burn(s, m, throttle)
+                .map_or(f64::NEG_INFINITY, |b| h - b.distance)
+        };
+        if margin(altitude) < 0.0 {
+            return None;
+        }
+
+        let (mut lo, mut hi) = (0.0, altitude);
+        for _ in 0..IGNITION_ITERATIONS {
+            let mid = 0.5 * (lo + hi);
+            if margin(mid) < 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(hi)
+    }
+
+    /// Throttle that brings the vehicle to rest at the ground.
+    ///
+    /// Assumes a constant deceleration from the current state, clamped to
+    /// the throttle range of the engine.
+    pub fn throttle(&self, altitude: f64, vertical_speed: f64, m: f64) -> f64 {
+        let min_throttle = self.t_min / self.t_max;
+        let hover = m * self.g;
+        let thrust = if vertical_speed >= 0.0 || altitude <= 0.0 {
+            hover
+        } else {
+            let decel = vertical_speed.powi(2) / (2.0 * altitude);
+            m * (decel + self.g) - self.drag_coeff() * vertical_speed.powi(2)
+        };
+        (thrust / self.t_max).clamp(min_throttle, 1.0)
+    }
+
+    /// Propellant a hoverslam would use from the first node of `solution`.
+    ///
+    /// Altitude is taken relative to the last node. The APDG trajectory
+    /// starts with the engine already lit, so the comparison is only fair
+    /// for solutions that start well above the hoverslam ignition point.
+    /// `None` if the hoverslam is not possible from that state.
+    pub fn compare_fuel(
+        &self,
+        solution: &APDGSolution,
+        e_hat_up: &Vector3<f64>,
+        throttle: f64,
+    ) -> Option<FuelComparison> {
+        let (first, last) = (solution.steps().first()?, solution.steps().last()?);
+        let altitude = e_hat_up.dot(&(first.r - last.r));
+        let vertical_speed = e_hat_up.dot(&first.v);
+
+        let ignition = self.ignition_altitude(altitude, vertical_speed, first.m, throttle)?;
+        let (apex, speed) = if vertical_speed > 0.0 {
+            (altitude + vertical_speed.powi(2) / (2.0 * self.g), 0.0)
+        } else {
+            (altitude, -vertical_speed)
+        };
+        let ignition_speed = self.free_fall_speed(speed, first.m, apex - ignition);
+        let burn = self.burn(ignition_speed, first.m, throttle)?;
+
+        Some(FuelComparison {
+            hoverslam: burn.propellant,
+            apdg: first.m - last.m,
+        })
+    }
+
+    /// Drag force per squared speed
+    /// [kg/m]
+    fn drag_coeff(&self) -> f64 {
+        0.5 * self.rho * self.s_d * self.c_d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vehicle with negligible mass flow and no drag
+    fn ideal() -> Hoverslam {
+        Hoverslam::builder()
+            .g(9.807)
+            .t_min(50_000.0)
+            .t_max(250_000.0)
+            .i_sp(1e12)
+            .m_dry(1_000.0)
+            .build()
+    }
+
+    #[test]
+    fn test_burn_matches_ideal_stop_distance() {
+        let h = ideal();
+        let burn = h.burn(60.0, 15_000.0, 0.8).unwrap();
+        let expected = h.ideal_stop_distance(60.0, 15_000.0, 0.8).unwrap();
+
+        assert!((burn.distance - expected).abs() / expected < 1e-4);
+        assert!(burn.propellant < 1e-6);
+    }
+
+    #[test]
+    fn test_ignition_altitude_without_drag() {
+        let h = ideal();
+        let (altitude, speed, m, throttle) = (1_000.0, 40.0, 15_000.0, 0.8);
+        let ignition = h.ignition_altitude(altitude, -speed, m, throttle).unwrap();
+
+        // Energy balance: speed^2 + 2 g (h - h_i) = 2 (T / m - g) h_i
+        let accel = throttle * h.t_max / m;
+        let expected = (speed.powi(2) + 2.0 * h.g * altitude) / (2.0 * accel);
+        assert!((ignition - expected).abs() / expected < 1e-3);
+
+        // Too late once below the stop distance
+        assert!(h.ignition_altitude(5.0, -speed, m, throttle).is_none());
+    }
+
+    #[test]
+    fn test_drag_and_mass_loss_shorten_the_burn() {
+        let params = SimulationParams::default();
+        let real = Hoverslam::from_params(&params);
+        let burn = real.burn(60.0, params.m_0, 0.8).unwrap();
+        let ideal_distance = real.ideal_stop_distance(60.0, params.m_0, 0.8).unwrap();
+
+        assert!(burn.distance < ideal_distance);
+        assert!(burn.propellant > 0.0);
+        // Throttle stays within the engine limits
+        let throttle = real.throttle(burn.distance, -60.0, params.m_0);
+        assert!((params.t_min_vac / params.t_max_vac..=1.0).contains(&throttle));
+    }
+}
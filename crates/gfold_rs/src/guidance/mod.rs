@@ -0,0 +1,32 @@
+//! Closed-form feedback guidance laws.
+//!
+//! These are cheap enough to evaluate every control cycle and act as a
+//! fallback when the convex solver cannot produce a trajectory in time.
+
+use nalgebra::Vector3;
+
+// Zero-effort-miss / zero-effort-velocity law
+pub mod zem_zev;
+
+// Constant throttle landing burn
+pub mod hoverslam;
+
+/// Current state of the vehicle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VehicleState {
+    /// Position [m]
+    pub r: Vector3<f64>,
+    /// Velocity [m/s]
+    pub v: Vector3<f64>,
+    /// Mass [kg]
+    pub m: f64,
+}
+
+/// Desired state at the end of the manoeuvre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    /// Position [m]
+    pub r: Vector3<f64>,
+    /// Velocity [m/s]
+    pub v: Vector3<f64>,
+}
@@ -0,0 +1,301 @@
+//! Zero-effort-miss / zero-effort-velocity (ZEM/ZEV) guidance.
+//!
+//! For a double integrator in uniform gravity the control that reaches a
+//! target position and velocity in `t_go` with minimum `∫ ||a||² dt` is
+//!
+//! ```text
+//! a = 6 * ZEM / t_go^2 - 2 * ZEV / t_go
+//! ZEM = r_t - (r + v * t_go + 1/2 * g * t_go^2)
+//! ZEV = v_t - (v + g * t_go)
+//! ```
+//!
+//! Re-evaluating it every cycle gives a feedback law. Drag and mass
+//! depletion are not modelled, both are absorbed by the feedback.
+//!
+//! The time-to-go is free: [`ZemZev::optimal_t_go`] minimises
+//! `Γ * t_go + 1/2 ∫ ||a||² dt` as in D'Souza (1997), where `Γ` trades time
+//! against control effort.
+
+use bon::Builder;
+use nalgebra::Vector3;
+
+use super::{Target, VehicleState};
+use crate::trajectories::SimulationParams;
+
+/// Number of coarse samples before refining the optimal time-to-go.
+const T_GO_SCAN_POINTS: usize = 64;
+
+/// Golden section iterations when refining the optimal time-to-go.
+const T_GO_REFINE_ITERATIONS: usize = 60;
+
+/// ZEM/ZEV guidance law with thrust magnitude and tilt limits.
+#[derive(Debug, Clone, Builder)]
+pub struct ZemZev {
+    /// Gravity vector
+    /// [m/s^2]
+    pub g_vec: Vector3<f64>,
+
+    /// Up pointing unit vector
+    pub e_hat_up: Vector3<f64>,
+
+    /// Minimum thrust
+    /// [N]
+    pub t_min: f64,
+
+    /// Maximum thrust
+    /// [N]
+    pub t_max: f64,
+
+    /// Maximum tilt of the thrust vector from vertical
+    /// [deg]
+    pub theta_max: f64,
+
+    /// Weight on time of flight against control effort (Γ)
+    /// [m^2/s^4]
+    #[builder(default = 1.0)]
+    pub time_weight: f64,
+
+    /// Time-to-go is never taken below this, the law is singular at zero
+    /// [s]
+    #[builder(default = 0.1)]
+    pub min_t_go: f64,
+
+    /// Upper bound of the time-to-go search
+    /// [s]
+    #[builder(default = 300.0)]
+    pub max_t_go: f64,
+}
+
+/// Output of [`ZemZev::command`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZemZevCommand {
+    /// Thrust acceleration after saturation
+    /// [m/s^2]
+    pub acceleration: Vector3<f64>,
+    /// Thrust vector after saturation
+    /// [N]
+    pub thrust: Vector3<f64>,
+    /// Time-to-go the command was computed for
+    /// [s]
+    pub t_go: f64,
+    /// Whether the unconstrained command had to be limited
+    pub saturated: bool,
+}
+
+impl ZemZev {
+    /// Take gravity and vehicle limits from the solver parameters.
+    pub fn from_params(params: &SimulationParams) -> Self {
+        ZemZev::builder()
+            .g_vec(params.g_vec)
+            .e_hat_up(params.e_hat_up)
+            .t_min(params.t_min_vac)
+            .t_max(params.t_max_vac)
+            .theta_max(params.theta_max)
+            .build()
+    }
+
+    /// Zero-effort miss and velocity after `t_go` seconds.
+    pub fn zem_zev(
+        &self,
+        state: &VehicleState,
+        target: &Target,
+        t_go: f64,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let zem = target.r - (state.r + state.v * t_go + 0.5 * self.g_vec * t_go.powi(2));
+        let zev = target.v - (state.v + self.g_vec * t_go);
+        (zem, zev)
+    }
+
+    /// Unconstrained thrust acceleration for a given time-to-go.
+    pub fn acceleration(&self, state: &VehicleState, target: &Target, t_go: f64) -> Vector3<f64> {
+        let t_go = t_go.max(self.min_t_go);
+        let (zem, zev) = self.zem_zev(state, target, t_go);
+        6.0 * zem / t_go.powi(2) - 2.0 * zev / t_go
+    }
+
+    /// Cost `Γ * t_go + 1/2 ∫ ||a||² dt` of reaching the target in `t_go`.
+    pub fn cost(&self, state: &VehicleState, target: &Target, t_go: f64) -> f64 {
+        let (zem, zev) = self.zem_zev(state, target, t_go);
+        // a(t) = c0 + c1 * t along the open-loop optimal trajectory
+        let c0 = 6.0 * zem / t_go.powi(2) - 2.0 * zev / t_go;
+        let c1 = 6.0 * zev / t_go.powi(2) - 12.0 * zem / t_go.powi(3);
+        let energy = c0.norm_squared() * t_go
+            + c0.dot(&c1) * t_go.powi(2)
+            + c1.norm_squared() * t_go.powi(3) / 3.0;
+        self.time_weight * t_go + 0.5 * energy
+    }
+
+    /// Time-to-go minimising [`ZemZev::cost`] within `[min_t_go, max_t_go]`.
+    ///
+    /// The cost is scanned on a logarithmic grid and the best interval is
+    /// refined by golden section search.
+    pub fn optimal_t_go(&self, state: &VehicleState, target: &Target) -> f64 {
+        let (lo, hi) = (self.min_t_go, self.max_t_go.max(self.min_t_go));
+        let ratio = (hi / lo).powf(1.0 / (T_GO_SCAN_POINTS - 1) as f64);
+        let grid: Vec<f64> = (0..T_GO_SCAN_POINTS)
+            .map(|i| lo * ratio.powi(i as i32))
+            .collect();
+        let cost = |t: f64| self.cost(state, target, t);
+
+        let best = (0..grid.len())
+            .min_by(|&i, &j| cost(grid[i]).total_cmp(&cost(grid[j])))
+            .unwrap_or(0);
+        let mut a = grid[best.saturating_sub(1)];
+        let mut b = grid[(best + 1).min(grid.len() - 1)];
+
+        let inv_phi = (5.0_f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..T_GO_REFINE_ITERATIONS {
+            let c = b - inv_phi * (b - a);
+            let d = a + inv_phi * (b - a);
+            if cost(c) < cost(d) {
+                b = d;
+            } else {
+                a = c;
+            }
+        }
+        0.5 * (a + b)
+    }
+
+    /// Saturated thrust command.
+    ///
+    /// `t_go` is computed with [`ZemZev::optimal_t_go`] if not given. The
+    /// direction is first rotated into the tilt cone, keeping its magnitude,
+    /// then the magnitude is clamped to the thrust limits.
+    pub fn command(
+        &self,
+        state: &VehicleState,
+        target: &Target,
+        t_go: Option<f64>,
+    ) -> ZemZevCommand {
+        let t_go = t_go
+            .unwrap_or_else(|| self.optimal_t_go(state, target))
+            .max(self.min_t_go);
+        let desired = self.acceleration(state, target, t_go);
+
+        let (tilted, tilt_limited) = self.limit_tilt(desired);
+        let mut thrust = state.m * tilted;
+        let magnitude = thrust.norm();
+        let magnitude_limited = magnitude < self.t_min || magnitude > self.t_max;
+        if magnitude <= f64::EPSILON {
+            thrust = self.e_hat_up * self.t_min;
+        } else if magnitude_limited {
+            thrust *= magnitude.clamp(self.t_min, self.t_max) / magnitude;
+        }
+
+        ZemZevCommand {
+            acceleration: thrust / state.m,
+            thrust,
+            t_go,
+            saturated: tilt_limited || magnitude_limited,
+        }
+    }
+
+    /// Rotate `a` onto the edge of the tilt cone if it lies outside.
+    ///
+    /// A command pointing straight down has no preferred direction on the
+    /// cone, it is dropped so the magnitude limit falls back to minimum
+    /// thrust straight up.
+    fn limit_tilt(&self, a: Vector3<f64>) -> (Vector3<f64>, bool) {
+        let norm = a.norm();
+        let theta = self.theta_max.to_radians();
+        let up = self.e_hat_up.dot(&a);
+        if norm <= f64::EPSILON || up >= norm * theta.cos() {
+            return (a, false);
+        }
+
+        let horizontal = a - up * self.e_hat_up;
+        let h_norm = horizontal.norm();
+        if h_norm <= f64::EPSILON * norm {
+            return (Vector3::zeros(), true);
+        }
+
+        let rotated = norm * (theta.cos() * self.e_hat_up + theta.sin() * horizontal / h_norm);
+        (rotated, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guidance() -> ZemZev {
+        ZemZev::from_params(&SimulationParams::default())
+    }
+
+    #[test]
+    fn test_closed_loop_reaches_target() {
+        let law = ZemZev::builder()
+            .g_vec([-9.807, 0.0, 0.0].into())
+            .e_hat_up([1.0, 0.0, 0.0].into())
+            .t_min(0.0)
+            .t_max(f64::INFINITY)
+            .theta_max(180.0)
+            .build();
+        let target = Target {
+            r: Vector3::zeros(),
+            v: Vector3::zeros(),
+        };
+        let mut state = VehicleState {
+            r: [500.0, 200.0, -100.0].into(),
+            v: [-40.0, 5.0, 10.0].into(),
+            m: 1.0,
+        };
+
+        let dt = 0.01;
+        let mut t_go = 20.0;
+        while t_go > 2.0 * dt {
+            let a = law.command(&state, &target, Some(t_go)).acceleration + law.g_vec;
+            state.r += state.v * dt + 0.5 * a * dt.powi(2);
+            state.v += a * dt;
+            t_go -= dt;
+        }
+
+        assert!(state.r.norm() < 0.1, "miss {}", state.r.norm());
+        assert!(state.v.norm() < 0.5, "velocity {}", state.v.norm());
+    }
+
+    #[test]
+    fn test_optimal_t_go_is_a_minimum() {
+        let law = guidance();
+        let state = VehicleState {
+            r: [500.0, 500.0, 0.0].into(),
+            v: [-50.0, 0.0, 50.0].into(),
+            m: 15_000.0,
+        };
+        let target = Target {
+            r: Vector3::zeros(),
+            v: Vector3::zeros(),
+        };
+
+        let t = law.optimal_t_go(&state, &target);
+        let j = law.cost(&state, &target, t);
+        assert!(t > law.min_t_go && t < law.max_t_go);
+        assert!(j <= law.cost(&state, &target, 0.95 * t));
+        assert!(j <= law.cost(&state, &target, 1.05 * t));
+    }
+
+    #[test]
+    fn test_command_respects_tilt_and_thrust_limits() {
+        let law = guidance();
+        // Far off to the side, so the unconstrained command is nearly horizontal
+        let state = VehicleState {
+            r: [100.0, 2000.0, 0.0].into(),
+            v: Vector3::zeros(),
+            m: 15_000.0,
+        };
+        let target = Target {
+            r: Vector3::zeros(),
+            v: Vector3::zeros(),
+        };
+
+        let cmd = law.command(&state, &target, Some(5.0));
+        let tilt = (law.e_hat_up.dot(&cmd.thrust) / cmd.thrust.norm())
+            .acos()
+            .to_degrees();
+
+        assert!(cmd.saturated);
+        assert!(tilt <= law.theta_max + 1e-9);
+        assert!(cmd.thrust.norm() <= law.t_max * (1.0 + 1e-12));
+        assert!(cmd.thrust.norm() >= law.t_min * (1.0 - 1e-12));
+    }
+}
@@ -0,0 +1,393 @@
+//! Loading and saving of scenarios and solutions.
+//!
+//! Config files are picked up by extension (`.toml` or `.json`). Any field
+//! left out of a [`Settings`], [`SimulationParams`] or [`AlgorithmParams`] file
+//! takes the same default as the corresponding builder, so a scenario only
+//! needs to list what it changes:
+//!
+//! ```toml
+//! [simulation_settings]
+//! r0 = [1500.0, 200.0, 0.0]
+//! v0 = [-80.0, 0.0, 10.0]
+//!
+//! [solver_settings]
+//! N = 40
+//! ```
+//!
+//! Solutions are written through [`TrajectoryFile`], a versioned layout that
+//! does not depend on the in-memory representation and is intended to be read
+//! by other tools.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nalgebra::Vector3;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::trajectories::{
+    APDGSolution, APDGSolutionTimeStep, ConstraintDuals, ConstraintGroup, ConvergenceHistory,
+};
+
+/// Version of the [`TrajectoryFile`] layout written by this crate.
+pub const TRAJECTORY_FORMAT_VERSION: u32 = 1;
+
+/// Errors from reading or writing files.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Filesystem error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Malformed TOML.
+    #[error("TOML parse error: {0}")]
+    TomlDe(#[from] toml::de::Error),
+
+    /// Value could not be written as TOML.
+    #[error("TOML write error: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+
+    /// Malformed JSON or value could not be written as JSON.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// File extension is not one of the supported formats.
+    #[error("Unsupported file format: {0}")]
+    UnknownFormat(PathBuf),
+
+    /// Trajectory file was written by an incompatible version.
+    #[error("Unsupported trajectory format version {found} (supported: {supported})")]
+    UnsupportedVersion {
+        /// Version found in the file
+        found: u32,
+        /// Version this crate reads
+        supported: u32,
+    },
+}
+
+/// Supported on-disk formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// TOML, preferred for hand written config files
+    Toml,
+    /// JSON, preferred for machine generated files
+    Json,
+}
+
+impl Format {
+    /// Pick a format from the file extension.
+    pub fn from_path(path: &Path) -> Result<Format, Error> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Ok(Format::Toml),
+            Some("json") => Ok(Format::Json),
+            _ => Err(Error::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Parse a value from a string in this format.
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, Error> {
+        match self {
+            Format::Toml => Ok(toml::from_str(contents)?),
+            Format::Json => Ok(serde_json::from_str(contents)?),
+        }
+    }
+
+    /// Write a value to a string in this format.
+    pub fn write<T: Serialize>(self, value: &T) -> Result<String, Error> {
+        match self {
+            Format::Toml => Ok(toml::to_string_pretty(value)?),
+            Format::Json => Ok(serde_json::to_string_pretty(value)?),
+        }
+    }
+}
+
+/// Load any serialisable value, e.g. [`Settings`] or [`RocketConfig`].
+///
+/// [`Settings`]: crate::trajectories::Settings
+/// [`RocketConfig`]: crate::rocket_config::RocketConfig
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Error> {
+    let path = path.as_ref();
+    let format = Format::from_path(path)?;
+    format.parse(&fs::read_to_string(path)?)
+}
+
+/// Save any serialisable value, the format follows the file extension.
+pub fn save<T: Serialize>(value: &T, path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    let format = Format::from_path(path)?;
+    fs::write(path, format.write(value)?)?;
+    Ok(())
+}
+
+/// A single node of a [`TrajectoryFile`].
+///
+/// Vectors are stored as `[U, E, N]` arrays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectoryNode {
+    /// Time since the start of the trajectory [s]
+    pub time: f64,
+    /// Position [m]
+    pub r: [f64; 3],
+    /// Velocity [m/s]
+    pub v: [f64; 3],
+    /// Acceleration [m/s^2]
+    pub a: [f64; 3],
+    /// Mass [kg]
+    pub m: f64,
+    /// Thrust [N]
+    pub thrust: [f64; 3],
+    /// Thrust magnitude [N]
+    pub gamma: f64,
+    /// Acceleration relaxation term [m/s^2]
+    pub a_r: [f64; 3],
+}
+
+/// Stable on-disk layout of a solution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectoryFile {
+    /// Layout version, see [`TRAJECTORY_FORMAT_VERSION`]
+    pub format_version: u32,
+    /// Name and version of the program that wrote the file
+    pub generator: String,
+    /// Time between nodes [s]
+    pub dt: f64,
+    /// Nodes of the trajectory
+    pub nodes: Vec<TrajectoryNode>,
+    /// Convergence history of the solve, if it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub convergence: Option<ConvergenceHistory>,
+    /// Dual variables of the solve, see [`APDGSolution::duals`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duals: Option<ConstraintDuals>,
+    /// Constraint groups the solve enforced, see
+    /// [`APDGSolution::constraint_groups`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint_groups: Option<Vec<ConstraintGroup>>,
+}
+
+impl TrajectoryFile {
+    /// Wrap a solution in the on-disk layout.
+    pub fn new(solution: &APDGSolution, convergence: Option<&ConvergenceHistory>) -> Self {
+        let nodes = solution
+            .steps()
+            .iter()
+            .enumerate()
+            .map(|(k, s)| TrajectoryNode {
+                time: k as f64 * solution.dt(),
+                r: s.r.into(),
+                v: s.v.into(),
+                a: s.a.into(),
+                m: s.m,
+                thrust: s.t.into(),
+                gamma: s.gamma,
+                a_r: s.aR.into(),
+            })
+            .collect();
+
+        TrajectoryFile {
+            format_version: TRAJECTORY_FORMAT_VERSION,
+            generator: format!("gfold-rs {}", crate::VERSION),
+            dt: solution.dt(),
+            nodes,
+            convergence: convergence.cloned(),
+            duals: solution.duals().cloned(),
+            constraint_groups: solution.constraint_groups().map(<[_]>::to_vec),
+        }
+    }
+
+    /// Convert back into a solution.
+    pub fn to_solution(&self) -> APDGSolution {
+        let steps = self
+            .nodes
+            .iter()
+            .map(|n| {
+                APDGSolutionTimeStep::builder()
+                    .r(Vector3::from(n.r))
+                    .v(Vector3::from(n.v))
+                    .a(Vector3::from(n.a))
+                    .m(n.m)
+                    .t(Vector3::from(n.thrust))
+                    .gamma(n.gamma)
+                    .aR(Vector3::from(n.a_r))
+                    .build()
+            })
+            .collect();
+
+        APDGSolution::builder()
+            .steps(steps)
+            .dt(self.dt)
+            .maybe_duals(self.duals.clone())
+            .maybe_constraint_groups(self.constraint_groups.clone())
+            .build()
+    }
+}
+
+/// Save a solution, and optionally its convergence history, as a [`TrajectoryFile`].
+pub fn save_solution(
+    path: impl AsRef<Path>,
+    solution: &APDGSolution,
+    convergence: Option<&ConvergenceHistory>,
+) -> Result<(), Error> {
+    save(&TrajectoryFile::new(solution, convergence), path)
+}
+
+/// Load a [`TrajectoryFile`], checking that its layout version is supported.
+pub fn load_trajectory_file(path: impl AsRef<Path>) -> Result<TrajectoryFile, Error> {
+    let file: TrajectoryFile = load(path)?;
+    if file.format_version != TRAJECTORY_FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            found: file.format_version,
+            supported: TRAJECTORY_FORMAT_VERSION,
+        });
+    }
+    Ok(file)
+}
+
+/// Load a solution saved with [`save_solution`].
+pub fn load_solution(path: impl AsRef<Path>) -> Result<APDGSolution, Error> {
+    Ok(load_trajectory_file(path)?.to_solution())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::{
+        AlgorithmParams, ConstraintFamily, ConstraintViolation, Settings, SimulationParams,
+    };
+
+    #[test]
+    fn test_partial_settings_use_builder_defaults() {
+        let settings: Settings = Format::Toml
+            .parse(
+                r#"
+                [simulation_settings]
+                m_0 = 16000.0
+
+                [solver_settings]
+                tf_guess = 20.0
+                N = 40
+                "#,
+            )
+            .unwrap();
+
+        let default_sim = SimulationParams::builder().build();
+        assert_eq!(settings.simulation_settings().m_0, 16000.0);
+        assert_eq!(settings.simulation_settings().m_dry, default_sim.m_dry);
+        assert_eq!(settings.simulation_settings().r0, default_sim.r0);
+
+        // dt follows the overridden horizon, as with the builder
+        let expected = AlgorithmParams::builder().tf_guess(20.0).N(40).build();
+        assert_eq!(settings.solver_settings().dt, expected.dt);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let result: Result<SimulationParams, _> = Format::Toml.parse("m_zero = 1.0");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_trajectory_file_round_trip() {
+        let steps = (0..3)
+            .map(|k| {
+                APDGSolutionTimeStep::builder()
+                    .r(Vector3::new(10.0 - k as f64, 1.0, 2.0))
+                    .v(Vector3::new(-1.0, 0.0, 0.5))
+                    .a(Vector3::new(0.1, 0.2, 0.3))
+                    .m(1000.0 - k as f64)
+                    .t(Vector3::new(5000.0, 0.0, 0.0))
+                    .gamma(5000.0)
+                    .aR(Vector3::zeros())
+                    .build()
+            })
+            .collect();
+        let solution = APDGSolution::builder().steps(steps).dt(0.5).build();
+
+        for format in [Format::Toml, Format::Json] {
+            let text = format.write(&TrajectoryFile::new(&solution, None)).unwrap();
+            let file: TrajectoryFile = format.parse(&text).unwrap();
+            let loaded = file.to_solution();
+
+            assert_eq!(loaded.num_steps(), solution.num_steps());
+            assert_eq!(loaded.dt(), solution.dt());
+            for (a, b) in loaded.steps().iter().zip(solution.steps()) {
+                assert_eq!(a.r, b.r);
+                assert_eq!(a.m, b.m);
+                assert_eq!(a.t, b.t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trajectory_file_keeps_duals_and_disabled_groups() {
+        // Thrust far outside the 15 degree default tilt limit
+        let steps = (0..3)
+            .map(|k| {
+                APDGSolutionTimeStep::builder()
+                    .r(Vector3::new(10.0 - k as f64, 0.0, 0.0))
+                    .v(Vector3::new(-1.0, 0.0, 0.0))
+                    .a(Vector3::zeros())
+                    .m(15_000.0)
+                    .t(Vector3::new(100_000.0, 100_000.0, 0.0))
+                    .gamma(100_000.0_f64.hypot(100_000.0))
+                    .aR(Vector3::zeros())
+                    .build()
+            })
+            .collect();
+        let groups: Vec<_> = ConstraintGroup::ALL
+            .into_iter()
+            .filter(|g| *g != ConstraintGroup::Tilt)
+            .collect();
+        let duals: ConstraintDuals = serde_json::from_value(serde_json::json!({
+            "w_mf": 1.0,
+            "thrust_min": [0.0, 0.0, 0.0],
+            "thrust_max": [0.0, 0.5, 0.0],
+            "tilt": [0.0, 0.0, 0.0],
+            "glide_slope": [0.0, 0.0, 0.0],
+            "mass_floor": [0.0, 0.0, 0.0],
+            "thrust_rate_min": [0.0, 0.0],
+            "thrust_rate_max": [0.0, 0.0],
+            "boundary": {
+                "m0": 1.0,
+                "r0": [0.0, 0.0, 0.0],
+                "v0": [0.0, 0.0, 0.0],
+                "rf": [0.0, 0.0, 0.0],
+                "vf": [0.0, 0.0, 0.0],
+            },
+        }))
+        .unwrap();
+        let solution = APDGSolution::builder()
+            .steps(steps)
+            .dt(0.5)
+            .duals(duals.clone())
+            .constraint_groups(groups.clone())
+            .build();
+
+        let params = SimulationParams::builder().build();
+        let is_tilt = |v: &ConstraintViolation| v.constraint == "tilt";
+        let all_groups = APDGSolution::builder()
+            .steps(solution.steps().to_vec())
+            .dt(0.5)
+            .build();
+        assert!(all_groups
+            .check_constraints(&params, 1e-4)
+            .iter()
+            .any(is_tilt));
+
+        for format in [Format::Toml, Format::Json] {
+            let text = format.write(&TrajectoryFile::new(&solution, None)).unwrap();
+            let loaded = format.parse::<TrajectoryFile>(&text).unwrap().to_solution();
+
+            assert_eq!(loaded.constraint_groups(), Some(groups.as_slice()));
+            assert_eq!(loaded.duals(), Some(&duals));
+            assert_eq!(
+                loaded.duals().unwrap().family(ConstraintFamily::ThrustMax),
+                [0.0, 0.5, 0.0]
+            );
+            // The disabled tilt limit is not checked after loading
+            assert!(!loaded.check_constraints(&params, 1e-4).iter().any(is_tilt));
+        }
+    }
+}
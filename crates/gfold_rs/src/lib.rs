@@ -1,3 +1,9 @@
+//! Powered descent guidance for rocket landings.
+//!
+//! Fuel-optimal trajectories are generated with the G-FOLD successive
+//! convexification algorithm in [`trajectories`], alongside closed-form
+//! guidance laws, dispersion analysis, plotting and export helpers.
+
 #![allow(confusable_idents)]
 #![allow(unused)]
 #![warn(missing_docs)]
@@ -12,8 +18,19 @@ mod utils;
 
 pub mod trajectories;
 
+pub mod guidance;
+
+pub mod dispersion;
+
 pub mod plotting;
 
+pub mod export;
+
 pub mod state;
 
 pub mod rocket_config;
+
+pub mod units;
+
+#[cfg(feature = "serde")]
+pub mod io;
@@ -1,20 +1,13 @@
+//! Column data of a solution for plotting.
+
 use crate::trajectories::{APDGSolution, SimulationParams};
 use nalgebra::Vector3;
 
-pub fn get_trajectory_3d_data(
-    solution: &APDGSolution,
-) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<Vector3<f64>>) {
-    let time = time_vector(solution);
-    let pos_u: Vec<f64> = solution.steps().iter().map(|s| s.r[0]).collect();
-    let pos_e: Vec<f64> = solution.steps().iter().map(|s| s.r[1]).collect();
-    let pos_n: Vec<f64> = solution.steps().iter().map(|s| s.r[2]).collect();
-    let thrust_vectors: Vec<Vector3<f64>> = solution.steps().iter().map(|s| s.t).collect();
-    (time, pos_u, pos_e, pos_n, thrust_vectors)
-}
+/// Time, up, east and north position and thrust vectors.
+type TrajectoryData = (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<Vector3<f64>>);
 
-pub fn get_pos_vel_time_data(
-    solution: &APDGSolution,
-) -> (
+/// Time, then up, east and north position and velocity.
+type PosVelData = (
     Vec<f64>,
     Vec<f64>,
     Vec<f64>,
@@ -22,7 +15,23 @@ pub fn get_pos_vel_time_data(
     Vec<f64>,
     Vec<f64>,
     Vec<f64>,
-) {
+);
+
+/// Time, thrust magnitude, thrust rate, tilt, azimuth and mass.
+type ThrustMassData = (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>);
+
+/// Time, position components and thrust vectors of every node.
+pub fn get_trajectory_3d_data(solution: &APDGSolution) -> TrajectoryData {
+    let time = time_vector(solution);
+    let pos_u: Vec<f64> = solution.steps().iter().map(|s| s.r[0]).collect();
+    let pos_e: Vec<f64> = solution.steps().iter().map(|s| s.r[1]).collect();
+    let pos_n: Vec<f64> = solution.steps().iter().map(|s| s.r[2]).collect();
+    let thrust_vectors: Vec<Vector3<f64>> = solution.steps().iter().map(|s| s.t).collect();
+    (time, pos_u, pos_e, pos_n, thrust_vectors)
+}
+
+/// Time, position and velocity components of every node.
+pub fn get_pos_vel_time_data(solution: &APDGSolution) -> PosVelData {
     let time = time_vector(solution);
     let pos_u: Vec<f64> = solution.steps().iter().map(|s| s.r[0]).collect();
     let pos_e: Vec<f64> = solution.steps().iter().map(|s| s.r[1]).collect();
@@ -34,22 +43,24 @@ pub fn get_pos_vel_time_data(
 }
 
 /// Calculates the tilt and azimuth angle in degrees
-fn calculate_angles(thrust_vector: &Vector3<f64>, up_vector: &Vector3<f64>) -> (f64, f64) {
+pub(crate) fn calculate_angles(
+    thrust_vector: &Vector3<f64>,
+    up_vector: &Vector3<f64>,
+) -> (f64, f64) {
     let thrust_norm = thrust_vector.norm();
     if thrust_norm < 1e-6 {
         return (0.0, 0.0);
     }
     let normalized_thrust = thrust_vector / thrust_norm;
-    let cos_tilt = normalized_thrust.dot(up_vector).max(-1.0).min(1.0);
+    let cos_tilt = normalized_thrust.dot(up_vector).clamp(-1.0, 1.0);
     let tilt_angle_deg = cos_tilt.acos().to_degrees();
     let azimuth_deg = thrust_vector[2].atan2(thrust_vector[1]).to_degrees();
     (tilt_angle_deg, azimuth_deg)
 }
 
-pub fn get_thrust_mass_data(
-    solution: &APDGSolution,
-    params: &SimulationParams,
-) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>) {
+/// Time, thrust magnitude, thrust rate, thrust angles and mass of every
+/// node. The thrust rate has one entry less than the nodes.
+pub fn get_thrust_mass_data(solution: &APDGSolution, params: &SimulationParams) -> ThrustMassData {
     let time = time_vector(solution);
     let thrust_mag: Vec<f64> = solution.steps().iter().map(|s| s.gamma).collect();
     let mass: Vec<f64> = solution.steps().iter().map(|s| s.m).collect();
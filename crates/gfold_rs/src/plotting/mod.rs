@@ -1,8 +1,11 @@
+//! Trajectory and convergence plots rendered to bitmap files.
+
 use bon::builder;
 use nalgebra::Vector3;
 use plotters::coord::types::RangedCoordf64;
 use plotters::coord::Shift;
 use plotters::prelude::*;
+use uom::si::angle::degree;
 
 use crate::trajectories::{APDGSolution, ConvergenceHistory, SimulationParams};
 
@@ -17,6 +20,7 @@ fn scale(values: &[f64], factor: f64) -> Vec<f64> {
     values.iter().map(|v| v * factor).collect()
 }
 
+/// Plot the 3D flight path with thrust vectors to `output`.
 pub fn plot_trajectory_3d(
     output: &str,
     title: &str,
@@ -75,6 +79,7 @@ pub fn plot_trajectory_3d(
     Ok(())
 }
 
+/// Plot the position and velocity components over time to `output`.
 pub fn plot_position_velocity_time(
     output: &str,
     solution: &APDGSolution,
@@ -149,6 +154,8 @@ pub fn plot_position_velocity_time(
     Ok(())
 }
 
+/// Plot thrust magnitude, thrust rate and thrust angles over time to
+/// `output`, with the limits from `sim`.
 pub fn plot_thrust_time(
     output: &str,
     solution: &APDGSolution,
@@ -208,7 +215,7 @@ pub fn plot_thrust_time(
         .caption("Tilt (deg)")
         .col(&RED)
         .lb(0.0)
-        .ub(sim.theta_max)
+        .ub(sim.theta_max().get::<degree>())
         .x_label("Time (s)")
         .y_label("Tilt Angle (deg)")
         .call()?;
@@ -227,6 +234,7 @@ pub fn plot_thrust_time(
     Ok(())
 }
 
+/// Plot the mass over time to `output`.
 pub fn plot_mass_time(
     output: &str,
     solution: &APDGSolution,
@@ -273,14 +281,14 @@ fn min_max(v: &[f64]) -> Option<(f64, f64)> {
 
 fn build_chart<DB: DrawingBackend>(
     area: &DrawingArea<DB, Shift>,
-) -> Result<ChartBuilder<DB>, Box<dyn std::error::Error>> {
+) -> Result<ChartBuilder<'_, '_, DB>, Box<dyn std::error::Error>> {
     let mut chart_builder = ChartBuilder::on(area);
 
     Ok(chart_builder)
 }
 
 #[builder]
-fn single_time_series<DB: DrawingBackend, 'a>(
+fn single_time_series<'a, DB: DrawingBackend>(
     mut chart_builder: ChartBuilder<'a, '_, DB>,
     t: &[f64],
     y: &[f64],
@@ -407,6 +415,7 @@ where
     Ok(())
 }
 
+/// Plot the SC convergence history to `output`.
 pub fn plot_convergence(
     output: &str,
     hist: &ConvergenceHistory,
@@ -460,6 +469,7 @@ pub fn plot_convergence(
     Ok(())
 }
 
+/// Plot the virtual acceleration over the SC iterations to `output`.
 pub fn plot_relaxation_convergence(
     output: &str,
     hist: &ConvergenceHistory,
@@ -478,10 +488,10 @@ pub fn plot_relaxation_convergence(
     const LOG_EPSILON: f64 = 1e-10; // Prevent a log10(0) error
 
     // Log Max ||aR||
-    let max_aR = hist.aR.iter().fold(0.0, |max, &v| v.abs().max(max));
+    let max_a_r = hist.aR.iter().fold(0.0, |max, &v| v.abs().max(max));
 
     let iters: Vec<f64> = (1..=hist.len()).map(|i| i as f64).collect();
-    let (min, max) = (0.0, max_aR);
+    let (min, max) = (0.0, max_a_r);
 
     single_time_series()
         .chart_builder(build_chart(&root)?)
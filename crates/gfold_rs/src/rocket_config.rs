@@ -1,38 +1,60 @@
+//! Vehicle configuration independent of the initial state.
+
 use bon::Builder;
 use nalgebra::Vector3;
 
 use crate::trajectories::SimulationParams;
 
+/// Mass, engine and aerodynamic properties of a vehicle.
 #[derive(Builder, Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 pub struct RocketConfig {
     // Mass
-    pub m_dry: f64,  // [kg]
-    pub m_fuel: f64, // [kg]
+    /// Dry mass [kg]
+    pub m_dry: f64,
+    /// Propellant mass [kg]
+    pub m_fuel: f64,
 
     // Thrust
-    pub t_min_vac: f64, // [N]
-    pub t_max_vac: f64, // [N]
-    pub tdot_min: f64,  // [N s⁻¹]
-    pub tdot_max: f64,  // [N s⁻¹]
+    /// Minimum vacuum thrust [N]
+    pub t_min_vac: f64,
+    /// Maximum vacuum thrust [N]
+    pub t_max_vac: f64,
+    /// Minimum thrust rate [N s⁻¹]
+    pub tdot_min: f64,
+    /// Maximum thrust rate [N s⁻¹]
+    pub tdot_max: f64,
 
     // Thrust vector
-    pub i_sp: f64,     // [s]
-    pub a_nozzle: f64, // [m²]
+    /// Specific impulse [s]
+    pub i_sp: f64,
+    /// Nozzle exit area [m²]
+    pub a_nozzle: f64,
 
     // Degrees of freedom
-    pub theta_max: f64, // [deg]
+    /// Maximum tilt angle [deg]
+    pub theta_max: f64,
 
     // Drag
-    pub s_d: f64, // [m²]
-    pub c_d: f64, // [-]
+    /// Reference area for drag [m²]
+    pub s_d: f64,
+    /// Drag coefficient [-]
+    pub c_d: f64,
 }
 
 impl RocketConfig {
+    /// Initial total mass (dry mass + propellant) [kg]
     #[inline]
     pub fn m_0(&self) -> f64 {
         self.m_dry + self.m_fuel
     }
 
+    /// Simulation parameters for this vehicle starting fully fuelled at `r0`
+    /// with velocity `v0`, everything else at its default.
     pub fn to_sim_params(&self, r0: Vector3<f64>, v0: Vector3<f64>) -> SimulationParams {
         SimulationParams::builder()
             .r0(r0)
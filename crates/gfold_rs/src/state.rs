@@ -1,3 +1,5 @@
+//! Vehicle state.
+
 use nalgebra::Vector3;
 
 struct RocketState {
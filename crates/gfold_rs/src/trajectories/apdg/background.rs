@@ -0,0 +1,289 @@
+//! Solving on a background thread.
+//!
+//! [`APDGProblemSolver::spawn`] and [`APDGProblemSolver::spawn_phases`] run
+//! the solve on its own thread and return a [`SolveHandle`] to poll
+//! progress, cancel, or wait for the result. A
+//! wall-clock budget can be given, once it runs out the best SC iterate so
+//! far is returned with [`ConvergenceHistory::truncated`] set. Iterates are
+//! ranked by the penalty part of the Problem 5 objective, the weighted
+//! relaxation slack `||kappa_aR||` plus the trust region slacks.
+//!
+//! Cancellation and the deadline are checked between convex subproblems, a
+//! subproblem that has started is always finished.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{
+    phases::{self, MultiPhaseSolution, Phase},
+    APDGProblemSolver, APDGSolution, AlgorithmParams, Error, Reference, Settings,
+};
+use crate::trajectories::ConvergenceHistory;
+
+/// Progress reported by a background solve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolveProgress {
+    /// The initial guess is available
+    InitialGuess,
+    /// An SC iteration finished
+    Iteration {
+        /// Iteration number, starting at 1
+        iteration: usize,
+        /// Maximum number of iterations
+        n_sc: usize,
+        /// Largest relative change from the previous iterate
+        max_relative: f64,
+    },
+}
+
+/// Handle to a solve running on a background thread, `T` is the solution
+/// type.
+#[derive(Debug)]
+pub struct SolveHandle<T = APDGSolution> {
+    cancel: Arc<AtomicBool>,
+    progress: mpsc::Receiver<SolveProgress>,
+    thread: JoinHandle<Result<(T, ConvergenceHistory), Error>>,
+}
+
+impl<T> SolveHandle<T> {
+    /// Ask the solve to stop, [`SolveHandle::join`] then returns
+    /// [`Error::Cancelled`].
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Progress reported since the last call, without blocking.
+    pub fn progress(&self) -> impl Iterator<Item = SolveProgress> + '_ {
+        self.progress.try_iter()
+    }
+
+    /// Whether the solve has finished and [`SolveHandle::join`] will not
+    /// block.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the solve to finish.
+    pub fn join(self) -> Result<(T, ConvergenceHistory), Error> {
+        self.thread
+            .join()
+            .unwrap_or_else(|_| Err(Error::SolverError("solver thread panicked".to_string())))
+    }
+}
+
+impl APDGProblemSolver {
+    /// Solve on a background thread.
+    ///
+    /// With a `budget`, the solve stops at the first check after it has
+    /// elapsed and returns the best iterate so far, marked as truncated.
+    pub fn spawn(&self, settings: Settings, budget: Option<Duration>) -> SolveHandle {
+        spawn(budget, move |control| {
+            settings.validate()?;
+            super::_solve(&settings, control)
+        })
+    }
+
+    /// Solve a multi-phase trajectory on a background thread, see
+    /// [`APDGProblemSolver::spawn`].
+    pub fn spawn_phases(
+        &self,
+        settings: Settings,
+        phases: Vec<Phase>,
+        budget: Option<Duration>,
+    ) -> SolveHandle<MultiPhaseSolution> {
+        spawn(budget, move |control| {
+            settings.validate()?;
+            phases::validate_phases(&settings, &phases)?;
+            phases::_solve_phases(&settings, &phases, control)
+        })
+    }
+}
+
+/// Run `solve` on its own thread with a control wired to the handle.
+fn spawn<T: Send + 'static>(
+    budget: Option<Duration>,
+    solve: impl FnOnce(&SolveControl) -> Result<(T, ConvergenceHistory), Error> + Send + 'static,
+) -> SolveHandle<T> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let control = SolveControl {
+        cancel: Some(cancel.clone()),
+        deadline: budget.map(|b| Instant::now() + b),
+        progress: Some(tx),
+    };
+
+    let thread = thread::spawn(move || solve(&control));
+
+    SolveHandle {
+        cancel,
+        progress: rx,
+        thread,
+    }
+}
+
+/// Why a solve should stop early.
+pub(super) enum Stop {
+    /// Cancelled through the handle
+    Cancelled,
+    /// Wall-clock budget used up
+    Deadline,
+}
+
+/// Cancellation, deadline and progress reporting for the SC loop.
+#[derive(Default)]
+pub(super) struct SolveControl {
+    cancel: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+    progress: Option<mpsc::Sender<SolveProgress>>,
+}
+
+impl SolveControl {
+    /// Check whether the solve should stop before the next subproblem.
+    pub(super) fn should_stop(&self) -> Option<Stop> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+        {
+            Some(Stop::Cancelled)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(Stop::Deadline)
+        } else {
+            None
+        }
+    }
+
+    /// Report progress, a dropped handle is not an error.
+    pub(super) fn report(&self, progress: SolveProgress) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(progress);
+        }
+    }
+}
+
+/// Best SC iterate seen so far, returned when the deadline is hit.
+pub(super) struct BestIterate<T> {
+    best: Option<(f64, T)>,
+}
+
+impl<T> Default for BestIterate<T> {
+    fn default() -> Self {
+        BestIterate { best: None }
+    }
+}
+
+impl<T: Reference> BestIterate<T> {
+    /// Keep `solution` if its merit is lower than the best so far.
+    ///
+    /// The merit is the penalty part of the Problem 5 objective evaluated at
+    /// `solution`, with the trust region slacks measured from `previous`,
+    /// summed over the segments of a multi-phase trajectory.
+    pub(super) fn offer(&mut self, algo: &AlgorithmParams, previous: &T, solution: &T) {
+        let merit = solution
+            .segments()
+            .zip(previous.segments())
+            .map(|(s, p)| merit(algo, p, s))
+            .sum::<f64>();
+        if self.best.as_ref().is_none_or(|(best, _)| merit < *best) {
+            self.best = Some((merit, solution.clone()));
+        }
+    }
+
+    /// The best iterate, or `latest` if none was offered.
+    pub(super) fn into_solution(self, latest: T) -> T {
+        self.best.map_or(latest, |(_, solution)| solution)
+    }
+}
+
+/// `w_kappa_aR * ||kappa_aR|| + w_eta_T * ||eta_T|| + w_eta_dt * eta_dt` with
+/// the slacks at their tightest values.
+fn merit(algo: &AlgorithmParams, previous: &APDGSolution, solution: &APDGSolution) -> f64 {
+    let kappa_aR = solution
+        .steps
+        .iter()
+        .map(|s| s.aR.norm_squared())
+        .sum::<f64>()
+        .sqrt();
+    let eta_T = solution
+        .steps
+        .iter()
+        .zip(&previous.steps)
+        .map(|(s, p)| (s.t - p.t).norm_squared())
+        .sum::<f64>()
+        .sqrt();
+    let eta_dt = (solution.dt - previous.dt).abs();
+
+    algo.w_kappa_aR * kappa_aR + algo.w_eta_T * eta_T + algo.w_eta_dt * eta_dt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::{APDGSolutionTimeStep, SimulationParams};
+    use nalgebra::Vector3;
+
+    fn iterate(aR: f64) -> APDGSolution {
+        let step = APDGSolutionTimeStep {
+            r: Vector3::new(100.0, 0.0, 0.0),
+            v: Vector3::zeros(),
+            a: Vector3::zeros(),
+            m: 1000.0,
+            t: Vector3::new(10_000.0, 0.0, 0.0),
+            gamma: 10_000.0,
+            aR: Vector3::new(aR, 0.0, 0.0),
+        };
+        APDGSolution {
+            steps: vec![step; 4],
+            dt: 1.0,
+            duals: None,
+            constraint_groups: None,
+        }
+    }
+
+    #[test]
+    fn test_best_iterate_is_kept_over_the_latest() {
+        let algo = AlgorithmParams::default();
+        let guess = iterate(1.0);
+        let (good, worse) = (iterate(1e-4), iterate(0.5));
+
+        let mut best = BestIterate::default();
+        best.offer(&algo, &guess, &good);
+        best.offer(&algo, &good, &worse);
+
+        let returned = best.into_solution(worse.clone());
+        assert_eq!(returned.steps[0].aR, good.steps[0].aR);
+    }
+
+    #[test]
+    fn test_latest_is_returned_without_iterates() {
+        let latest = iterate(0.25);
+        let returned = BestIterate::default().into_solution(latest.clone());
+        assert_eq!(returned.steps[0].aR, latest.steps[0].aR);
+    }
+
+    #[test]
+    fn test_cancelled_before_start() {
+        let control = SolveControl {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..Default::default()
+        };
+        let result = super::super::_solve(&Settings::default(), &control);
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_invalid_settings_are_reported_through_the_handle() {
+        let settings = Settings::builder()
+            .simulation_settings(SimulationParams::builder().m_0(1.0).build())
+            .build();
+        let handle = APDGProblemSolver::default().spawn(settings, None);
+
+        assert!(matches!(handle.join(), Err(Error::InvalidSettings(_))));
+    }
+}
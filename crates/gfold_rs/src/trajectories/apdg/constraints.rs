@@ -0,0 +1,176 @@
+//! User-defined constraints.
+//!
+//! Mission-specific restrictions, such as keeping out of a no-fly zone or
+//! capping the descent rate below some altitude, can be added without
+//! touching the solver. Implement [`TrajectoryConstraint`] and add it to
+//! [`Settings`](super::Settings), it is then applied to both the initial guess
+//! (Problem 4, or the log-mass guess) and every successive convexification
+//! step (Problem 5).
+//!
+//! Constraints are built from the per-node decision variables with the
+//! `good_lp` macros, e.g. `constraint!(node.v[0] >= -20.0)` or
+//! `soc_constraint!(norm2(node.t[1], node.t[2]) <= node.gamma * 0.5)`. Only
+//! linear and second-order cone constraints can be expressed. Anything
+//! nonlinear has to be linearised about the reference trajectory, which is
+//! only available in Problem 5.
+
+use std::fmt;
+
+use good_lp::{Constraint, Expression, Variable};
+use nalgebra::Vector3;
+
+use super::{
+    models::{AlgorithmParams, SimulationParams},
+    APDGSolution,
+};
+
+/// Decision variables of a single node.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeVariables {
+    /// Position [m]
+    pub r: Vector3<Variable>,
+    /// Velocity [m/s]
+    pub v: Vector3<Variable>,
+    /// Acceleration [m/s^2]
+    pub a: Vector3<Variable>,
+    /// Mass [kg]
+    pub m: Variable,
+    /// Thrust [N]
+    pub t: Vector3<Variable>,
+    /// Thrust magnitude [N]
+    pub gamma: Variable,
+}
+
+/// The problem a [`TrajectoryConstraint`] is being added to.
+#[derive(Debug, Clone)]
+pub struct ConstraintContext<'a> {
+    /// Decision variables of every node
+    pub nodes: &'a [NodeVariables],
+    /// Time step [s]
+    ///
+    /// Fixed in Problem 4 and a decision variable in Problem 5.
+    pub dt: Expression,
+    /// Trajectory Problem 5 is linearised about, `None` in Problem 4
+    pub reference: Option<&'a APDGSolution>,
+    /// Simulation parameters
+    pub params: &'a SimulationParams,
+    /// Algorithm parameters
+    pub algo: &'a AlgorithmParams,
+}
+
+/// A constraint added to every convex subproblem.
+///
+/// Constraints are shared between threads by background solves and Monte
+/// Carlo runs, so implementations must be `Send + Sync`.
+pub trait TrajectoryConstraint: fmt::Debug + Send + Sync {
+    /// Name identifying the constraint
+    fn name(&self) -> &str;
+
+    /// Linear or second-order cone constraints to add to the problem.
+    fn constraints(&self, ctx: &ConstraintContext<'_>) -> Vec<Constraint>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use good_lp::{constraint, variables, ProblemVariables};
+
+    use super::*;
+    use crate::trajectories::{apdg::guess, APDGProblemSolver, Settings};
+
+    /// Keep the vertical velocity above a floor at every node
+    #[derive(Debug)]
+    struct DescentRate(f64);
+
+    impl TrajectoryConstraint for DescentRate {
+        fn name(&self) -> &str {
+            "descent rate"
+        }
+
+        fn constraints(&self, ctx: &ConstraintContext<'_>) -> Vec<Constraint> {
+            ctx.nodes
+                .iter()
+                .map(|node| constraint!(node.v[0] >= -self.0))
+                .collect()
+        }
+    }
+
+    fn vector(vars: &mut ProblemVariables) -> Vector3<Variable> {
+        Vector3::from_fn(|_, _| vars.add_variable())
+    }
+
+    #[test]
+    fn test_constraints_are_built_per_node() {
+        let mut vars = variables!();
+        let nodes: Vec<_> = (0..4)
+            .map(|_| NodeVariables {
+                r: vector(&mut vars),
+                v: vector(&mut vars),
+                a: vector(&mut vars),
+                m: vars.add_variable(),
+                t: vector(&mut vars),
+                gamma: vars.add_variable(),
+            })
+            .collect();
+        let descent_rate: Arc<dyn TrajectoryConstraint> = Arc::new(DescentRate(20.0));
+        let settings = Settings::builder().constraints(vec![descent_rate]).build();
+
+        let ctx = ConstraintContext {
+            nodes: &nodes,
+            dt: Expression::from(1.0),
+            reference: None,
+            params: settings.simulation_settings(),
+            algo: settings.solver_settings(),
+        };
+        let built: Vec<_> = settings
+            .constraints()
+            .iter()
+            .flat_map(|c| c.constraints(&ctx))
+            .collect();
+
+        assert_eq!(built.len(), nodes.len());
+        assert_eq!(settings.constraints()[0].name(), "descent rate");
+    }
+
+    #[test]
+    fn test_descent_rate_holds_in_guess_and_solution() {
+        let scenario = |constraints: Vec<Arc<dyn TrajectoryConstraint>>| {
+            Settings::builder()
+                .simulation_settings(
+                    SimulationParams::builder()
+                        .r0(Vector3::new(500.0, 100.0, 0.0))
+                        .v0(Vector3::new(-30.0, 0.0, 0.0))
+                        .build(),
+                )
+                .solver_settings(AlgorithmParams::builder().N(20).build())
+                .constraints(constraints)
+                .build()
+        };
+        let guess = |settings: &Settings| {
+            guess::problem::APDGProblem::new(
+                settings.simulation_settings().clone(),
+                settings.solver_settings().clone(),
+                settings.constraints().to_vec(),
+            )
+            .solve()
+            .unwrap()
+        };
+        let max_descent_rate = |solution: &APDGSolution| {
+            solution
+                .steps()
+                .iter()
+                .map(|s| -s.v[0])
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+
+        // Left to itself the vehicle comes down faster than 40 m/s
+        assert!(max_descent_rate(&guess(&scenario(Vec::new()))) > 45.0);
+
+        let settings = scenario(vec![Arc::new(DescentRate(40.0))]);
+        let (solution, _) = APDGProblemSolver::default().solve(&settings).unwrap();
+        for solution in [guess(&settings), solution] {
+            assert!(max_descent_rate(&solution) <= 40.0 + 1e-3);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Dual variables of the final successive convexification solve.
+//!
+//! Clarabel solves `min c^T x` subject to `A x + s = b` with `s` in a cone,
+//! and its dual variables `z` satisfy `dp*/db = -z`. Every constraint is
+//! stored with its slack `s` measuring how far it is from being violated, so
+//! relaxing a constraint by `δ` lowers the optimal cost by `z * δ`.
+//!
+//! The Problem 5 cost is `-w_mf * m[kf]` plus trust region and relaxation
+//! penalties that vanish once the SC loop has converged. Dividing a dual by
+//! `w_mf` therefore gives the final mass gained per unit of relaxation, to
+//! first order. The duals belong to the last convex subproblem, so they are
+//! only meaningful for converged solutions.
+//!
+//! With `w_mf = 0` the final mass is not part of the cost, as in a minimum
+//! time landing, and there is nothing to convert the duals into. The final
+//! mass sensitivities are then `None` and only the raw duals, in units of
+//! cost, are available.
+
+use nalgebra::Vector3;
+use uom::si::angle::radian;
+
+use super::{models::SimulationParams, APDGSolution};
+
+/// Path constraints with one dual per node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstraintFamily {
+    /// `Γ[k] >= T_min`, relaxed in [N]
+    ThrustMin,
+    /// `Γ[k] <= T_max`, relaxed in [N]
+    ThrustMax,
+    /// `Γ[k] cos(θ_max) <= e_u^T T[k]`, relaxed in [N]
+    Tilt,
+    /// `||r[k] - c[k]|| <= sec(γ_gs) e_u^T (r[k] - c[k])` with `c[k]` the
+    /// cone vertex, relaxed in [m]
+    GlideSlope,
+    /// `m[k] >= m_dry`, relaxed in [kg]
+    MassFloor,
+    /// `Γ[k+1] - Γ[k] >= Tdot_min dt`, relaxed in [N]
+    ThrustRateMin,
+    /// `Γ[k+1] - Γ[k] <= Tdot_max dt`, relaxed in [N]
+    ThrustRateMax,
+}
+
+impl ConstraintFamily {
+    /// Every family, in the order they are reported.
+    pub const ALL: [ConstraintFamily; 7] = [
+        ConstraintFamily::ThrustMin,
+        ConstraintFamily::ThrustMax,
+        ConstraintFamily::Tilt,
+        ConstraintFamily::GlideSlope,
+        ConstraintFamily::MassFloor,
+        ConstraintFamily::ThrustRateMin,
+        ConstraintFamily::ThrustRateMax,
+    ];
+
+    /// Human readable name of the family.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConstraintFamily::ThrustMin => "minimum thrust",
+            ConstraintFamily::ThrustMax => "maximum thrust",
+            ConstraintFamily::Tilt => "tilt",
+            ConstraintFamily::GlideSlope => "glide slope",
+            ConstraintFamily::MassFloor => "dry mass",
+            ConstraintFamily::ThrustRateMin => "minimum thrust rate",
+            ConstraintFamily::ThrustRateMax => "maximum thrust rate",
+        }
+    }
+}
+
+/// Duals of the boundary conditions.
+///
+/// These are equality constraints, so the sign matters: a positive value
+/// means increasing the boundary value increases the final mass.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundaryDuals {
+    /// Initial mass `m[0] = m_0`
+    pub m0: f64,
+    /// Initial position `r[0] = r_0`
+    pub r0: Vector3<f64>,
+    /// Initial velocity `v[0] = v_0`
+    pub v0: Vector3<f64>,
+    /// Final position `r[kf] = r_f`
+    pub rf: Vector3<f64>,
+    /// Final velocity `v[kf] = v_f`
+    pub vf: Vector3<f64>,
+}
+
+/// A constraint with a non-negligible dual.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveConstraint {
+    /// Constraint family
+    pub family: ConstraintFamily,
+    /// Node index, for thrust rates the step from `node` to `node + 1`
+    pub node: usize,
+    /// Dual variable, in cost per unit of relaxation
+    pub dual: f64,
+    /// Final mass gained per unit of relaxation [kg], `None` if the cost
+    /// does not weigh the final mass
+    pub marginal_final_mass: Option<f64>,
+}
+
+/// Dual variables of a Problem 5 solve, per constraint family and node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstraintDuals {
+    /// Weight on final mass in the cost the duals belong to
+    pub(super) w_mf: f64,
+    pub(super) thrust_min: Vec<f64>,
+    pub(super) thrust_max: Vec<f64>,
+    pub(super) tilt: Vec<f64>,
+    pub(super) glide_slope: Vec<f64>,
+    pub(super) mass_floor: Vec<f64>,
+    pub(super) thrust_rate_min: Vec<f64>,
+    pub(super) thrust_rate_max: Vec<f64>,
+    pub(super) boundary: BoundaryDuals,
+}
+
+impl ConstraintDuals {
+    /// Convert a change in cost into final mass [kg].
+    ///
+    /// `None` if the final mass has no weight in the cost.
+    fn to_final_mass(&self, cost: f64) -> Option<f64> {
+        (self.w_mf > 0.0).then(|| cost / self.w_mf)
+    }
+
+    /// Duals of one family, indexed by node.
+    pub fn family(&self, family: ConstraintFamily) -> &[f64] {
+        match family {
+            ConstraintFamily::ThrustMin => &self.thrust_min,
+            ConstraintFamily::ThrustMax => &self.thrust_max,
+            ConstraintFamily::Tilt => &self.tilt,
+            ConstraintFamily::GlideSlope => &self.glide_slope,
+            ConstraintFamily::MassFloor => &self.mass_floor,
+            ConstraintFamily::ThrustRateMin => &self.thrust_rate_min,
+            ConstraintFamily::ThrustRateMax => &self.thrust_rate_max,
+        }
+    }
+
+    /// Duals of the boundary conditions.
+    pub fn boundary(&self) -> &BoundaryDuals {
+        &self.boundary
+    }
+
+    /// Final mass gained per unit of relaxation of one constraint [kg]
+    ///
+    /// `None` if the node is out of range or `w_mf = 0`.
+    pub fn marginal_final_mass(&self, family: ConstraintFamily, node: usize) -> Option<f64> {
+        self.family(family)
+            .get(node)
+            .and_then(|&z| self.to_final_mass(z))
+    }
+
+    /// Constraints whose dual exceeds `tolerance`.
+    ///
+    /// By complementary slackness only constraints that hold with equality
+    /// have a non-zero dual. An interior point solver leaves small positive
+    /// values on the others, so the tolerance should sit well above the
+    /// solver tolerance.
+    pub fn active(&self, tolerance: f64) -> Vec<ActiveConstraint> {
+        ConstraintFamily::ALL
+            .iter()
+            .flat_map(|&family| {
+                self.family(family)
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, &z)| z > tolerance)
+                    .map(move |(node, &dual)| ActiveConstraint {
+                        family,
+                        node,
+                        dual,
+                        marginal_final_mass: self.to_final_mass(dual),
+                    })
+            })
+            .collect()
+    }
+
+    /// Final mass gained by relaxing every node of a family by one unit [kg]
+    ///
+    /// `None` if `w_mf = 0`.
+    pub fn total_marginal_final_mass(&self, family: ConstraintFamily) -> Option<f64> {
+        self.to_final_mass(self.family(family).iter().sum())
+    }
+}
+
+impl APDGSolution {
+    /// Dual variables of the solve that produced this solution.
+    ///
+    /// Only set for solutions from a successive convexification iteration.
+    pub fn duals(&self) -> Option<&ConstraintDuals> {
+        self.duals.as_ref()
+    }
+
+    /// Final mass gained per degree of extra tilt allowance [kg/deg]
+    ///
+    /// Raising `θ_max` by `dθ` relaxes the tilt constraint at node `k` by
+    /// `Γ[k] sin(θ_max) dθ`. `None` without duals or with `w_mf = 0`.
+    pub fn final_mass_per_degree_tilt(&self, params: &SimulationParams) -> Option<f64> {
+        let duals = self.duals()?;
+        let sin_th = params.theta_max().get::<radian>().sin();
+        let gain: f64 = duals
+            .tilt
+            .iter()
+            .zip(&self.steps)
+            .map(|(z, step)| z * step.gamma * sin_th)
+            .sum();
+        duals.to_final_mass(gain.to_radians())
+    }
+
+    /// Final mass gained per degree of extra glide slope allowance [kg/deg]
+    ///
+    /// Raising `γ_gs` by `dγ` relaxes the glide slope cone at node `k` by
+    /// `sec(γ_gs) tan(γ_gs) e_u^T (r[k] - c[k]) dγ` with `c[k]` the cone
+    /// vertex. `None` without duals or with `w_mf = 0`.
+    pub fn final_mass_per_degree_glide_slope(&self, params: &SimulationParams) -> Option<f64> {
+        let duals = self.duals()?;
+        let gamma_gs = params.gamma_gs().get::<radian>();
+        let scale = gamma_gs.tan() / gamma_gs.cos();
+        let n = self.steps.len();
+        let tf = self.duration();
+        let gain: f64 = duals
+            .glide_slope
+            .iter()
+            .zip(&self.steps)
+            .enumerate()
+            .map(|(k, (z, step))| {
+                let vertex = params.mission.glide_slope_vertex(params, k, n, tf);
+                z * scale * params.e_hat_up.dot(&(step.r - vertex))
+            })
+            .sum();
+        duals.to_final_mass(gain.to_radians())
+    }
+
+    /// Final mass gained per newton of extra maximum thrust [kg/N]
+    ///
+    /// `None` without duals or with `w_mf = 0`.
+    pub fn final_mass_per_newton_max_thrust(&self) -> Option<f64> {
+        self.duals()?
+            .total_marginal_final_mass(ConstraintFamily::ThrustMax)
+    }
+
+    /// Final mass gained per newton of lower minimum thrust [kg/N]
+    ///
+    /// `None` without duals or with `w_mf = 0`.
+    pub fn final_mass_per_newton_min_thrust(&self) -> Option<f64> {
+        self.duals()?
+            .total_marginal_final_mass(ConstraintFamily::ThrustMin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::APDGSolutionTimeStep;
+
+    fn duals() -> ConstraintDuals {
+        ConstraintDuals {
+            w_mf: 2.0,
+            thrust_min: vec![1e-9, 0.0, 0.0],
+            thrust_max: vec![4.0, 0.0, 0.0],
+            tilt: vec![0.0, 1.0, 0.0],
+            glide_slope: vec![0.0; 3],
+            mass_floor: vec![0.0; 3],
+            thrust_rate_min: vec![0.0; 2],
+            thrust_rate_max: vec![0.0, 3.0],
+            boundary: BoundaryDuals {
+                m0: 2.0,
+                r0: Vector3::zeros(),
+                v0: Vector3::zeros(),
+                rf: Vector3::zeros(),
+                vf: Vector3::zeros(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_active_constraints() {
+        let active = duals().active(1e-6);
+        let found: Vec<_> = active.iter().map(|a| (a.family, a.node)).collect();
+
+        assert_eq!(
+            found,
+            [
+                (ConstraintFamily::ThrustMax, 0),
+                (ConstraintFamily::Tilt, 1),
+                (ConstraintFamily::ThrustRateMax, 1),
+            ]
+        );
+        assert_eq!(active[0].marginal_final_mass, Some(2.0));
+    }
+
+    #[test]
+    fn test_tilt_sensitivity_scales_with_thrust() {
+        let params = SimulationParams::default();
+        let step = APDGSolutionTimeStep {
+            r: Vector3::zeros(),
+            v: Vector3::zeros(),
+            a: Vector3::zeros(),
+            m: 1.0,
+            t: Vector3::zeros(),
+            gamma: 1000.0,
+            aR: Vector3::zeros(),
+        };
+        let solution = APDGSolution {
+            steps: vec![step; 3],
+            dt: 1.0,
+            duals: Some(duals()),
+            constraint_groups: None,
+        };
+
+        let sin_th = params.theta_max().get::<radian>().sin();
+        let expected = 1000.0 * sin_th * 1.0_f64.to_radians() / 2.0;
+        let gain = solution.final_mass_per_degree_tilt(&params).unwrap();
+        assert!((gain - expected).abs() < 1e-12);
+        assert_eq!(solution.final_mass_per_newton_max_thrust(), Some(2.0));
+    }
+
+    #[test]
+    fn test_no_final_mass_sensitivity_without_final_mass_weight() {
+        let duals = ConstraintDuals {
+            w_mf: 0.0,
+            ..duals()
+        };
+        let step = APDGSolutionTimeStep {
+            r: Vector3::zeros(),
+            v: Vector3::zeros(),
+            a: Vector3::zeros(),
+            m: 1.0,
+            t: Vector3::zeros(),
+            gamma: 1000.0,
+            aR: Vector3::zeros(),
+        };
+        let solution = APDGSolution {
+            steps: vec![step; 3],
+            dt: 1.0,
+            duals: Some(duals.clone()),
+            constraint_groups: None,
+        };
+        let params = SimulationParams::default();
+
+        assert_eq!(
+            duals.marginal_final_mass(ConstraintFamily::ThrustMax, 0),
+            None
+        );
+        assert_eq!(
+            duals.total_marginal_final_mass(ConstraintFamily::Tilt),
+            None
+        );
+        assert_eq!(duals.active(1e-6)[0].marginal_final_mass, None);
+        assert_eq!(solution.final_mass_per_degree_tilt(&params), None);
+        assert_eq!(solution.final_mass_per_degree_glide_slope(&params), None);
+        assert_eq!(solution.final_mass_per_newton_max_thrust(), None);
+    }
+}
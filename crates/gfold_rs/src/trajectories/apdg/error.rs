@@ -1,6 +1,8 @@
 use good_lp::{ResolutionError, SolverModel};
 use thiserror::Error;
 
+use super::validation::ValidationErrors;
+
 /// Error codes returnable from APDG solver.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -8,6 +10,10 @@ pub enum Error {
     #[error("Solver error: {0}")]
     SolverError(String),
 
+    /// A convex subproblem has no feasible point.
+    #[error("Problem is infeasible")]
+    Infeasible,
+
     /// SC loop did not converge.
     #[error("SC loop did not converge after {0} iterations")]
     SCNotConverged(usize),
@@ -23,4 +29,49 @@ pub enum Error {
     /// Numeric error.
     #[error("Numeric error: {0}")]
     NumericError(String),
+
+    /// Solve was cancelled through its handle.
+    #[error("Solve was cancelled")]
+    Cancelled,
+
+    /// Wall-clock budget ran out before an initial guess was found.
+    #[error("Deadline exceeded before a solution was found")]
+    DeadlineExceeded,
+
+    /// Re-planning horizon is shorter than the minimum.
+    #[error("Planning horizon has expired")]
+    HorizonExpired,
+
+    /// The final solution is not physically realisable.
+    #[error("Relaxation is not tight: relative thrust gap {thrust_gap:.3e}, relative aR {aR:.3e}")]
+    LooseRelaxation {
+        /// Largest relative gap `(Γ - ||T||) / Γ`
+        thrust_gap: f64,
+        /// Largest `||a_R||` relative to the thrust acceleration
+        aR: f64,
+    },
+
+    /// Settings failed validation.
+    #[error("Invalid settings:\n{0}")]
+    InvalidSettings(ValidationErrors),
+}
+
+impl Error {
+    /// Whether the failure was caused by an infeasible subproblem.
+    pub fn is_infeasible(&self) -> bool {
+        match self {
+            Error::Infeasible => true,
+            Error::SCError(_, inner) => inner.is_infeasible(),
+            _ => false,
+        }
+    }
+}
+
+impl From<ResolutionError> for Error {
+    fn from(e: ResolutionError) -> Self {
+        match e {
+            ResolutionError::Infeasible => Error::Infeasible,
+            e => Error::SolverError(format!("{:?}", e)),
+        }
+    }
 }
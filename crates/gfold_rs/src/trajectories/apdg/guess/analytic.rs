@@ -0,0 +1,188 @@
+//! Closed-form initial guesses.
+//!
+//! These skip the Problem 4 solve and build a reference trajectory directly
+//! on the `N` node grid with spacing `dt`. They only need to be good enough
+//! for the linearisation in Problem 5, so no path constraints are enforced.
+//! The thrust is whatever the kinematics demand, including drag, and the mass
+//! follows from the trapezoidal mass flow used by the solver.
+
+use nalgebra::Vector3;
+
+use crate::trajectories::{
+    apdg::models::{AlgorithmParams, SimulationParams},
+    APDGSolution, APDGSolutionTimeStep,
+};
+
+/// Minimum-energy trajectory, the acceleration is linear in time.
+///
+/// This is the ZEM/ZEV solution of the double integrator
+///
+/// ```text
+/// a_c(t) = c0 + c1 * t
+/// c0 = 6 * ZEM / tf^2 - 2 * ZEV / tf
+/// c1 = 6 * ZEV / tf^2 - 12 * ZEM / tf^3
+/// ```
+///
+/// with the zero-effort miss and velocity taken at ignition.
+pub(in crate::trajectories::apdg) fn polynomial(
+    params: &SimulationParams,
+    algo: &AlgorithmParams,
+) -> APDGSolution {
+    let tf = (algo.N - 1) as f64 * algo.dt;
+    let g = params.g_vec;
+    let (r_target, v_target) = params.target.state(params, tf);
+
+    let zem = r_target - (params.r0 + params.v0 * tf + 0.5 * g * tf.powi(2));
+    let zev = v_target - (params.v0 + g * tf);
+    let c0 = 6.0 * zem / tf.powi(2) - 2.0 * zev / tf;
+    let c1 = 6.0 * zev / tf.powi(2) - 12.0 * zem / tf.powi(3);
+
+    let kinematics = (0..algo.N)
+        .map(|k| {
+            let t = k as f64 * algo.dt;
+            let r = params.r0 + params.v0 * t + 0.5 * (g + c0) * t.powi(2) + c1 * t.powi(3) / 6.0;
+            let v = params.v0 + (g + c0) * t + 0.5 * c1 * t.powi(2);
+            let a = g + c0 + c1 * t;
+            (r, v, a)
+        })
+        .collect();
+
+    from_kinematics(params, algo.dt, kinematics)
+}
+
+/// Position and velocity interpolated linearly between the boundary
+/// conditions, with the constant acceleration that takes `v0` to `vf`.
+pub(in crate::trajectories::apdg) fn straight_line(
+    params: &SimulationParams,
+    algo: &AlgorithmParams,
+) -> APDGSolution {
+    let tf = (algo.N - 1) as f64 * algo.dt;
+    let (r_target, v_target) = params.target.state(params, tf);
+    let a = (v_target - params.v0) / tf;
+
+    let kinematics = (0..algo.N)
+        .map(|k| {
+            let s = k as f64 / (algo.N - 1) as f64;
+            let r = params.r0 + (r_target - params.r0) * s;
+            let v = params.v0 + (v_target - params.v0) * s;
+            (r, v, a)
+        })
+        .collect();
+
+    from_kinematics(params, algo.dt, kinematics)
+}
+
+/// Fit a user supplied solution to the `N` node grid.
+///
+/// The time of flight of the supplied solution is kept, only the node
+/// spacing changes.
+pub(in crate::trajectories::apdg) fn resample(
+    solution: &APDGSolution,
+    algo: &AlgorithmParams,
+) -> APDGSolution {
+    if solution.num_steps() == algo.N {
+        return solution.clone();
+    }
+
+    let dt = solution.duration() / (algo.N - 1) as f64;
+    let steps = (0..algo.N)
+        .filter_map(|k| solution.sample(k as f64 * dt))
+        .collect();
+
+    APDGSolution {
+        steps,
+        dt,
+        duals: None,
+        constraint_groups: None,
+    }
+}
+
+/// Recover thrust and mass from the kinematics at every node.
+///
+/// `T = m * (a - g) - D` with the mass integrated by the same trapezoidal
+/// rule as the dynamics constraints. `m[k+1]` appears on both sides through
+/// the thrust magnitude, so each step takes a few fixed-point iterations.
+/// The mass flow over one step is a small fraction of the mass, which makes
+/// the iteration contract quickly.
+fn from_kinematics(
+    params: &SimulationParams,
+    dt: f64,
+    kinematics: Vec<(Vector3<f64>, Vector3<f64>, Vector3<f64>)>,
+) -> APDGSolution {
+    const MASS_ITERATIONS: usize = 5;
+
+    let alpha = 1.0 / (params.i_sp * params.g_0);
+    let m_dot_bp = (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0);
+    let drag_coeff = 0.5 * params.rho * params.s_d * params.c_d;
+
+    let mut steps: Vec<APDGSolutionTimeStep> = Vec::with_capacity(kinematics.len());
+    for (r, v, a) in kinematics {
+        let specific = a - params.g_vec;
+        let drag = -drag_coeff * v.norm() * v;
+        let thrust = |m: f64| m * specific - drag;
+
+        let m = match steps.last() {
+            None => params.m_0,
+            Some(prev) => {
+                let mut m = prev.m;
+                for _ in 0..MASS_ITERATIONS {
+                    let gamma = thrust(m).norm();
+                    m = prev.m - 0.5 * dt * (alpha * (prev.gamma + gamma) + 2.0 * m_dot_bp);
+                }
+                // A guess that runs out of propellant is still a usable reference
+                m.max(params.m_dry)
+            }
+        };
+
+        let t = thrust(m);
+        steps.push(APDGSolutionTimeStep {
+            r,
+            v,
+            a,
+            m,
+            t,
+            gamma: t.norm(),
+            aR: Vector3::zeros(),
+        });
+    }
+
+    APDGSolution {
+        steps,
+        dt,
+        duals: None,
+        constraint_groups: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polynomial_meets_boundary_conditions() {
+        let params = SimulationParams::default();
+        let algo = AlgorithmParams::default();
+        let guess = polynomial(&params, &algo);
+
+        let (first, last) = (&guess.steps[0], guess.steps.last().unwrap());
+        assert_eq!(guess.num_steps(), algo.N);
+        assert!((first.r - params.r0).norm() < 1e-9);
+        assert!((last.r - params.rf).norm() < 1e-9);
+        assert!((last.v - params.vf).norm() < 1e-9);
+        assert_eq!(first.m, params.m_0);
+        // Mass only decreases
+        assert!(guess.steps.windows(2).all(|w| w[1].m < w[0].m));
+    }
+
+    #[test]
+    fn test_resample_keeps_time_of_flight() {
+        let params = SimulationParams::default();
+        let guess = straight_line(&params, &AlgorithmParams::builder().N(20).build());
+        let algo = AlgorithmParams::builder().N(35).build();
+        let resampled = resample(&guess, &algo);
+
+        assert_eq!(resampled.num_steps(), 35);
+        assert!((resampled.duration() - guess.duration()).abs() < 1e-9);
+        assert!((resampled.steps.last().unwrap().r - params.rf).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,454 @@
+//! Log-mass formulation of the initial guess.
+//!
+//! This is the change of variables used by the original G-FOLD papers
+//!
+//! ```text
+//! z = ln m,    u = T / m,    σ = Γ / m
+//! ```
+//!
+//! under which the mass dynamics and thrust direction constraints become
+//! linear. The thrust bounds `T_min <= Γ <= T_max` turn into
+//! `T_min e^-z <= σ <= T_max e^-z`, which are made convex with a second-order
+//! Taylor expansion of `e^-z` about the maximum thrust mass profile `z0`:
+//!
+//! ```text
+//! T_min e^-z0 [1 - (z - z0) + (z - z0)^2 / 2] <= σ <= T_max e^-z0 [1 - (z - z0)]
+//! ```
+//!
+//! Both sides are conservative, and with `z0 <= z` bounded from the mass flow
+//! limits the relaxation `||u|| <= σ` is lossless. Without drag (`rho = 0`)
+//! and back pressure (`p_amb = 0`) the dynamics are exact, so a single solve
+//! gives an optimal trajectory of the discretised problem. Otherwise both are
+//! evaluated along the same assumed mass and speed profiles as Problem 4.
+//!
+//! The thrust rate limits are applied to `σ` scaled by the assumed mass
+//! profile, so they are only approximate. The cost maximises `z[N-1]`, scaled
+//! by the assumed final mass so that `w_mf` keeps its meaning to first order.
+//!
+//! User constraints see the same node variables as in Problem 4. Position,
+//! velocity and acceleration are exact, mass is `e^z` linearised about the
+//! assumed mass profile `mu[k]`, and thrust is `mu[k] u` with magnitude
+//! `mu[k] σ`:
+//!
+//! ```text
+//! m[k] = mu[k] (1 + z[k] - ln mu[k]),    T[k] = mu[k] u[k],    Γ[k] = mu[k] σ[k]
+//! ```
+
+use good_lp::{
+    clarabel, constraint, soc_constraint, variables, Expression, ProblemVariables, Solution,
+    SolutionStatus, SolverModel, Variable,
+};
+use nalgebra::Vector3;
+use uom::si::angle::radian;
+
+use std::sync::Arc;
+
+use crate::trajectories::{
+    apdg::{
+        constraints::{ConstraintContext, NodeVariables, TrajectoryConstraint},
+        mission,
+        models::{AlgorithmParams, ConstraintGroup, SimulationParams},
+    },
+    APDGSolution, APDGSolutionTimeStep,
+};
+
+use super::{problem::pre_compute, Error};
+
+// Decision variables of a single node
+struct LogMassVariables {
+    /// Position [m]
+    r: Vector3<Variable>,
+    /// Velocity [m/s]
+    v: Vector3<Variable>,
+    /// Acceleration [m/s^2]
+    a: Vector3<Variable>,
+    /// Log of mass [ln kg]
+    z: Variable,
+    /// Thrust acceleration [m/s^2]
+    u: Vector3<Variable>,
+    /// Thrust acceleration magnitude [m/s^2]
+    sigma: Variable,
+    /// Acceleration relaxation term [m/s^2]
+    aR: Vector3<Variable>,
+    /// Relaxation slack []
+    kappa_aR: Variable,
+}
+
+impl LogMassVariables {
+    fn vector(vars: &mut ProblemVariables) -> Vector3<Variable> {
+        Vector3::from_fn(|_, _| vars.add_variable())
+    }
+
+    fn new(vars: &mut ProblemVariables) -> Self {
+        LogMassVariables {
+            r: Self::vector(vars),
+            v: Self::vector(vars),
+            a: Self::vector(vars),
+            z: vars.add_variable(),
+            u: Self::vector(vars),
+            sigma: vars.add_variable(),
+            aR: Self::vector(vars),
+            kappa_aR: vars.add_variable(),
+        }
+    }
+}
+
+/// Bounds on `z[k]` implied by the thrust limits.
+///
+/// The lower bound burns at `T_max` from ignition and is the expansion point
+/// `z0` of the thrust bounds, the upper bound burns at `T_min`. Both are
+/// floored at the dry mass.
+pub(super) fn mass_bounds(params: &SimulationParams, algo: &AlgorithmParams) -> Vec<(f64, f64)> {
+    let alpha = 1.0 / (params.i_sp * params.g_0);
+    let m_dot_bp = (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0);
+    let mass = |thrust: f64, t: f64| {
+        (params.m_0 - (alpha * thrust + m_dot_bp) * t)
+            .max(params.m_dry)
+            .ln()
+    };
+
+    (0..algo.N)
+        .map(|k| {
+            let t = k as f64 * algo.dt;
+            (mass(params.t_max_vac, t), mass(params.t_min_vac, t))
+        })
+        .collect()
+}
+
+/// Solve the initial guess in the log-mass variables.
+pub(in crate::trajectories::apdg) fn solve(
+    params: &SimulationParams,
+    algo: &AlgorithmParams,
+    constraints: &[Arc<dyn TrajectoryConstraint>],
+) -> Result<APDGSolution, Error> {
+    let N = algo.N;
+    let dt = algo.dt;
+    let alpha = 1.0 / (params.i_sp * params.g_0);
+    let m_dot_bp = (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0);
+    let drag_coeff = 0.5 * params.rho * params.s_d * params.c_d;
+    let (mu, s) = pre_compute(params, algo);
+    let bounds = mass_bounds(params, algo);
+
+    let mut vars = variables!();
+    let steps: Vec<LogMassVariables> = (0..N).map(|_| LogMassVariables::new(&mut vars)).collect();
+    let norm_kappa_aR = vars.add_variable();
+    // Mass and thrust in Problem 4 units, only needed by user constraints
+    let nodes: Vec<NodeVariables> = if constraints.is_empty() {
+        Vec::new()
+    } else {
+        steps
+            .iter()
+            .map(|step| NodeVariables {
+                r: step.r,
+                v: step.v,
+                a: step.a,
+                m: vars.add_variable(),
+                t: LogMassVariables::vector(&mut vars),
+                gamma: vars.add_variable(),
+            })
+            .collect()
+    };
+
+    // Minimize: -w_mf * mu[N-1] * z[N-1] + w_kappa_aR * ||kappa_aR||
+    let objective = -algo.w_mf * mu[N - 1] * steps[N - 1].z + algo.w_kappa_aR * norm_kappa_aR;
+    let mut model = vars.minimise(objective).using(clarabel);
+
+    let kappa_aR: Vec<Variable> = steps.iter().map(|s| s.kappa_aR).collect();
+    model.add_constraint(constraint!(norm_kappa_aR >= 0.0));
+    model.add_constraint(soc_constraint!(norm2_vec(kappa_aR) <= norm_kappa_aR));
+
+    // Initial conditions, the initial thrust fixes sigma[0] since m[0] is known
+    let sigma_0 = params.gamma_0_vac / params.m_0;
+    let fixed_thrust = params.mission.fixes_initial_thrust();
+    model.add_constraint(constraint!(steps[0].z == params.m_0.ln()));
+    if fixed_thrust {
+        model.add_constraint(constraint!(steps[0].sigma == sigma_0));
+    }
+    for i in 0..3 {
+        model.add_constraint(constraint!(steps[0].r[i] == params.r0[i]));
+        model.add_constraint(constraint!(steps[0].v[i] == params.v0[i]));
+        if fixed_thrust {
+            model.add_constraint(constraint!(steps[0].u[i] == sigma_0 * params.n_hat0[i]));
+        }
+    }
+
+    // Final conditions relative to the target at the fixed time of flight
+    let tf = (N - 1) as f64 * dt;
+    let (r_target, v_target) = params.target.state(params, tf);
+    let last = &steps[N - 1];
+    for i in 0..3 {
+        if algo.is_enabled(ConstraintGroup::FinalPosition) {
+            model.add_constraint(constraint!(last.r[i] == r_target[i]));
+        }
+        if algo.is_enabled(ConstraintGroup::FinalVelocity) {
+            model.add_constraint(constraint!(last.v[i] == v_target[i]));
+        }
+        if algo.is_enabled(ConstraintGroup::FinalThrustDirection) {
+            model.add_constraint(constraint!(last.u[i] == last.sigma * params.n_hatf[i]));
+        }
+    }
+
+    // Dynamics
+    // Back pressure flow is m_dot_bp * e^-z, linearised about ln(mu[k])
+    let inv_mass = |k: usize| -> Expression {
+        (1.0 / mu[k]) * (Expression::from(1.0 + mu[k].ln()) - steps[k].z)
+    };
+    for k in 0..N - 1 {
+        let (now, next) = (&steps[k], &steps[k + 1]);
+
+        // z[k+1] = z[k] - [alpha/2 * (sigma[k] + sigma[k+1]) + m_dot_bp/2 * (1/m[k] + 1/m[k+1])] * dt
+        model.add_constraint(constraint!(
+            next.z
+                == now.z
+                    - alpha / 2.0 * (now.sigma + next.sigma) * dt
+                    - m_dot_bp / 2.0 * (inv_mass(k) + inv_mass(k + 1)) * dt
+        ));
+
+        for i in 0..3 {
+            // r[k+1] = r[k] + v[k] * dt + 1/3 * (a[k] + 1/2*a[k+1]) * dt^2
+            model.add_constraint(constraint!(
+                next.r[i]
+                    == now.r[i]
+                        + now.v[i] * dt
+                        + (1.0 / 3.0) * (now.a[i] + 0.5 * next.a[i]) * dt.powi(2)
+            ));
+            // v[k+1] = v[k] + 1/2 * (a[k] + a[k+1]) * dt
+            model.add_constraint(constraint!(
+                next.v[i] == now.v[i] + 0.5 * (now.a[i] + next.a[i]) * dt
+            ));
+        }
+    }
+    // a[k] = u[k] - 1/2 * rho * S_D * C_D * s[k] / mu[k] * v[k] + a_R[k] + g
+    for (k, step) in steps.iter().enumerate() {
+        for i in 0..3 {
+            model.add_constraint(constraint!(
+                step.a[i]
+                    == step.u[i] - drag_coeff * s[k] / mu[k] * step.v[i]
+                        + step.aR[i]
+                        + params.g_vec[i]
+            ));
+        }
+    }
+
+    // State constraints
+    let cos_th = params.theta_max().get::<radian>().cos();
+    let sec_gs = 1.0 / params.gamma_gs().get::<radian>().cos();
+    let up = params.e_hat_up;
+    for (k, step) in steps.iter().enumerate() {
+        // z[k] >= ln(m_dry)
+        if algo.is_enabled(ConstraintGroup::MassFloor) {
+            model.add_constraint(constraint!(step.z >= params.m_dry.ln()));
+        }
+
+        // ||r[k] - c[k]|| cos(gamma_gs) <= e_u^T * (r[k] - c[k])
+        if algo.is_enabled(ConstraintGroup::GlideSlope) {
+            let vertex = params.mission.glide_slope_vertex(params, k, N, tf);
+            let height = up.x * step.r[0] + up.y * step.r[1] + up.z * step.r[2] - up.dot(&vertex);
+            let offset: Vec<Expression> = (0..3).map(|i| step.r[i] - vertex[i]).collect();
+            model.add_constraint(soc_constraint!(norm2_vec(offset) <= sec_gs * height));
+        }
+
+        // ||u[k]|| <= sigma[k]
+        if algo.is_enabled(ConstraintGroup::ThrustMagnitude) {
+            model.add_constraint(soc_constraint!(
+                norm2(step.u[0], step.u[1], step.u[2]) <= step.sigma
+            ));
+        }
+
+        // Taylor-bounded thrust limits about z0[k]
+        if algo.is_enabled(ConstraintGroup::ThrustBounds) {
+            let (z0, z_max) = bounds[k];
+            let mu_1 = params.t_min_vac * (-z0).exp();
+            let mu_2 = params.t_max_vac * (-z0).exp();
+            let dz = Expression::from(step.z) - z0;
+            let one_minus_dz = Expression::from(1.0 + z0) - step.z;
+
+            model.add_constraint(constraint!(step.z >= z0));
+            model.add_constraint(constraint!(step.z <= z_max));
+            // sigma[k] <= mu_2 * (1 - dz)
+            model.add_constraint(constraint!(step.sigma <= mu_2 * one_minus_dz.clone()));
+            // mu_1 * (1 - dz + dz^2/2) <= sigma[k], as dz^2 <= y with
+            // y = 2 * (sigma[k] - mu_1 * (1 - dz)) / mu_1
+            if mu_1 > 0.0 {
+                let y = (2.0 / mu_1) * (step.sigma - mu_1 * one_minus_dz);
+                let sides = vec![2.0 * dz, y.clone() - 1.0];
+                model.add_constraint(soc_constraint!(norm2_vec(sides) <= y + 1.0));
+            } else {
+                model.add_constraint(constraint!(step.sigma >= 0.0));
+            }
+        }
+
+        // sigma[k] * cos(theta_max) <= e_u^T * u[k]
+        if algo.is_enabled(ConstraintGroup::Tilt) {
+            let up_dot_u = up.x * step.u[0] + up.y * step.u[1] + up.z * step.u[2];
+            model.add_constraint(constraint!(up_dot_u >= cos_th * step.sigma));
+        }
+
+        // ||a_R[k]|| <= kappa_aR[k], or a_R[k] = 0 without the relaxation
+        if algo.is_enabled(ConstraintGroup::Relaxation) {
+            model.add_constraint(soc_constraint!(
+                norm2(step.aR[0], step.aR[1], step.aR[2]) <= step.kappa_aR
+            ));
+        } else {
+            for aR in step.aR.iter() {
+                model.add_constraint(constraint!(*aR == 0.0));
+            }
+        }
+    }
+
+    // Hop altitude constraints
+    let r: Vec<_> = steps.iter().map(|s| s.r).collect();
+    let v: Vec<_> = steps.iter().map(|s| s.v).collect();
+    for constraint in mission::altitude_constraints(params, &r, &v) {
+        model.add_constraint(constraint);
+    }
+
+    // User-defined constraints on the Problem 4 node variables
+    if !nodes.is_empty() {
+        for (k, (step, node)) in steps.iter().zip(&nodes).enumerate() {
+            // m[k] = mu[k] * (1 + z[k] - ln(mu[k])), T[k] = mu[k] * u[k], Gamma[k] = mu[k] * sigma[k]
+            model.add_constraint(constraint!(
+                node.m == mu[k] * (Expression::from(1.0 - mu[k].ln()) + step.z)
+            ));
+            model.add_constraint(constraint!(node.gamma == mu[k] * step.sigma));
+            for i in 0..3 {
+                model.add_constraint(constraint!(node.t[i] == mu[k] * step.u[i]));
+            }
+        }
+        let ctx = ConstraintContext {
+            nodes: &nodes,
+            dt: Expression::from(dt),
+            reference: None,
+            params,
+            algo,
+        };
+        for constraint in constraints {
+            for c in constraint.constraints(&ctx) {
+                model.add_constraint(c);
+            }
+        }
+    }
+
+    // Tdot_min*dt <= Gamma[k+1] - Gamma[k] <= Tdot_max*dt, with Gamma ~ mu * sigma
+    if algo.is_enabled(ConstraintGroup::ThrustRate) {
+        for k in 0..N - 1 {
+            let change = mu[k + 1] * steps[k + 1].sigma - mu[k] * steps[k].sigma;
+            model.add_constraint(constraint!(change.clone() >= params.tdot_min * dt));
+            model.add_constraint(constraint!(change <= params.tdot_max * dt));
+        }
+    }
+
+    let solution = model.solve()?;
+    match solution.status() {
+        SolutionStatus::Optimal => {
+            let value = |v: &Vector3<Variable>| v.map(|x| solution.value(x));
+            let steps = steps
+                .iter()
+                .map(|step| {
+                    let m = solution.value(step.z).exp();
+                    APDGSolutionTimeStep {
+                        r: value(&step.r),
+                        v: value(&step.v),
+                        a: value(&step.a),
+                        m,
+                        t: value(&step.u) * m,
+                        gamma: solution.value(step.sigma) * m,
+                        aR: value(&step.aR),
+                    }
+                })
+                .collect();
+            Ok(APDGSolution {
+                steps,
+                dt,
+                duals: None,
+                constraint_groups: Some(algo.enabled_constraints()),
+            })
+        }
+        _ => Err(Error::SolverError(format!(
+            "Solver did not find an optimal solution. Status: {:?}",
+            solution.status()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use good_lp::Constraint;
+
+    use super::*;
+
+    /// Keep the vertical velocity above a floor at every node
+    #[derive(Debug)]
+    struct DescentRate(f64);
+
+    impl TrajectoryConstraint for DescentRate {
+        fn name(&self) -> &str {
+            "descent rate"
+        }
+
+        fn constraints(&self, ctx: &ConstraintContext<'_>) -> Vec<Constraint> {
+            ctx.nodes
+                .iter()
+                .map(|node| constraint!(node.v[0] >= -self.0))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_mass_bounds_bracket_the_burn() {
+        let params = SimulationParams::default();
+        let algo = AlgorithmParams::default();
+        let bounds = mass_bounds(&params, &algo);
+
+        assert_eq!(bounds.len(), algo.N);
+        assert!((bounds[0].0 - params.m_0.ln()).abs() < 1e-12);
+        for pair in bounds.windows(2) {
+            let ((lower, upper), (next_lower, next_upper)) = (pair[0], pair[1]);
+            assert!(lower <= upper);
+            assert!(next_lower <= lower && next_upper <= upper);
+            assert!(next_lower >= params.m_dry.ln());
+        }
+    }
+
+    #[test]
+    fn test_lossless_without_drag_and_back_pressure() {
+        // Feasible at the fixed time of flight, so the relaxation is unused
+        let params = SimulationParams::builder()
+            .rho(0.0)
+            .p_amb(0.0)
+            .r0(Vector3::new(500.0, 100.0, 0.0))
+            .v0(Vector3::new(-30.0, 0.0, 0.0))
+            .build();
+        let algo = AlgorithmParams::builder().tf_guess(20.0).N(20).build();
+        let solution = solve(&params, &algo, &[]).unwrap();
+
+        for (k, step) in solution.steps().iter().enumerate() {
+            // ||u|| = sigma is the same as ||T|| = Gamma
+            let gap = (step.t.norm() - step.gamma).abs() / step.gamma;
+            assert!(gap < 1e-6, "node {k}: ||u|| and sigma differ by {gap:e}");
+            assert!(step.aR.norm() < 1e-5, "node {k}: aR = {:e}", step.aR.norm());
+        }
+    }
+
+    #[test]
+    fn test_user_constraints_are_applied() {
+        let params = SimulationParams::builder()
+            .r0(Vector3::new(500.0, 100.0, 0.0))
+            .v0(Vector3::new(-30.0, 0.0, 0.0))
+            .build();
+        let algo = AlgorithmParams::builder().N(20).build();
+        let max_descent_rate = |solution: &APDGSolution| {
+            solution
+                .steps()
+                .iter()
+                .map(|s| -s.v[0])
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+
+        let free = solve(&params, &algo, &[]).unwrap();
+        assert!(max_descent_rate(&free) > 40.0 + 1.0);
+
+        let limited = solve(&params, &algo, &[Arc::new(DescentRate(40.0))]).unwrap();
+        assert!(max_descent_rate(&limited) <= 40.0 + 1e-3);
+    }
+}
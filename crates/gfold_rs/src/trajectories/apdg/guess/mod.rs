@@ -1,4 +1,10 @@
 // In the paper we start with an initial guess of the trajectory using a simplifed model.
 pub(super) mod problem;
 
+// Closed-form alternatives to Problem 4
+pub(super) mod analytic;
+
+// G-FOLD log-mass formulation of Problem 4
+pub(super) mod log_mass;
+
 use super::error::Error;
@@ -1,11 +1,18 @@
 use good_lp::{
-    clarabel, constraint, soc_constraint, variable, variables, Constraint, Expression,
-    ProblemVariables, Solution, SolutionStatus, SolverModel, Variable,
+    clarabel, constraint, soc_constraint, solvers::clarabel::ClarabelProblem, variable, variables,
+    Constraint, Expression, ProblemVariables, Solution, SolutionStatus, SolverModel, Variable,
 };
 use nalgebra::Vector3;
+use std::sync::Arc;
+use uom::si::angle::radian;
 
 use crate::trajectories::{
-    apdg::models::{AlgorithmParams, SimulationParams},
+    apdg::{
+        constraints::{ConstraintContext, NodeVariables, TrajectoryConstraint},
+        mission,
+        models::{AlgorithmParams, ConstraintGroup, SimulationParams},
+        objective, target,
+    },
     APDGSolution, APDGSolutionTimeStep,
 };
 
@@ -30,6 +37,7 @@ use super::Error;
 pub struct APDGProblem {
     sim_params: SimulationParams,
     algo_params: AlgorithmParams,
+    constraints: Vec<Arc<dyn TrajectoryConstraint>>,
 }
 
 // Store all decision variables for a single time step
@@ -53,6 +61,19 @@ pub struct TimeStepVariables {
     kappa_aR: Variable,
 }
 
+impl TimeStepVariables {
+    fn node(&self) -> NodeVariables {
+        NodeVariables {
+            r: self.r,
+            v: self.v,
+            a: self.a,
+            m: self.m,
+            t: self.t,
+            gamma: self.gamma,
+        }
+    }
+}
+
 // Store all decision variables for all time steps
 // Basically the global state
 struct DecisionVariables {
@@ -98,10 +119,15 @@ impl DecisionVariables {
 }
 
 impl APDGProblem {
-    pub fn new(sim_params: SimulationParams, algo_params: AlgorithmParams) -> APDGProblem {
+    pub fn new(
+        sim_params: SimulationParams,
+        algo_params: AlgorithmParams,
+        constraints: Vec<Arc<dyn TrajectoryConstraint>>,
+    ) -> APDGProblem {
         APDGProblem {
             sim_params,
             algo_params,
+            constraints,
         }
     }
 
@@ -150,10 +176,17 @@ impl APDGProblem {
             &self.algo_params,
         );
 
+        // Add user-defined constraints
+        add_custom_constraints(
+            &mut model,
+            &decision_vars,
+            &self.sim_params,
+            &self.algo_params,
+            &self.constraints,
+        );
+
         // Run the solver
-        let solution = model
-            .solve()
-            .map_err(|e| Error::SolverError(format!("{:?}", e)))?;
+        let solution = model.solve()?;
 
         match solution.status() {
             SolutionStatus::Optimal => {
@@ -205,6 +238,8 @@ impl APDGProblem {
                 Ok(APDGSolution {
                     steps: steps_solution,
                     dt,
+                    duals: None,
+                    constraint_groups: Some(self.algo_params.enabled_constraints()),
                 })
             }
             _ => Err(Error::SolverError(format!(
@@ -219,7 +254,7 @@ impl APDGProblem {
 fn setup_problem(
     params: &SimulationParams,
     algo: &AlgorithmParams,
-) -> (DecisionVariables, impl SolverModel) {
+) -> (DecisionVariables, ClarabelProblem) {
     let N = algo.N;
 
     let mut vars = variables!();
@@ -228,12 +263,32 @@ fn setup_problem(
 
     let norm_kappa_aR_var = vars.add_variable();
 
+    let nodes: Vec<NodeVariables> = decision_variables
+        .steps
+        .iter()
+        .map(TimeStepVariables::node)
+        .collect();
+    let tf = (N - 1) as f64 * algo.dt;
+    let (landing_site, _) = target::final_state(params, Expression::from(tf), tf);
+    let (cost_terms, cost_constraints) = objective::cost_terms(
+        &mut vars,
+        &algo.cost_terms,
+        &nodes,
+        Expression::from(algo.dt),
+        &landing_site,
+        params,
+    );
+
     let mut objective = Expression::default();
-    // Minimize: -w_mf * m[N-1] + w_kappa_aR * ||kappa_aR||
+    // Minimize: -w_mf * m[N-1] + w_kappa_aR * ||kappa_aR|| + cost terms
     objective += -algo.w_mf * decision_variables.steps[N - 1].m;
     objective += algo.w_kappa_aR * norm_kappa_aR_var;
+    objective += cost_terms;
 
     let mut model = vars.minimise(objective).using(clarabel);
+    for constraint in cost_constraints {
+        model.add_constraint(constraint);
+    }
 
     // Add SOC constraint: ||kappa_aR|| <= norm_kappa_aR
     let kappa_aR_vars: Vec<Variable> = decision_variables
@@ -270,6 +325,11 @@ fn add_initial_constraints(
         model.add_constraint(constraint!(*var == v0));
     }
 
+    // A hop lifts off with whatever thrust is best
+    if !params.mission.fixes_initial_thrust() {
+        return;
+    }
+
     // T[0] = Gamma_0 * n_hat0
     // n_hat0 is the initial normal vector
     for (i, var) in vars.steps[0].t.iter().enumerate() {
@@ -288,27 +348,51 @@ fn add_final_constraints(
     algo: &AlgorithmParams,
 ) {
     let k_end = algo.N - 1;
-
-    // Final position r[N-1] = rf
-    for (var, &rf_val) in vars.steps[k_end].r.iter().zip(params.rf.iter()) {
-        model.add_constraint(constraint!(*var == rf_val));
+    // Target state at the fixed time of flight
+    let tf = k_end as f64 * algo.dt;
+    let (r_target, v_target) = target::final_state(params, Expression::from(tf), tf);
+
+    // Final position r[N-1] = r_T(tf), only the altitude when minimising the
+    // landing error
+    if algo.is_enabled(ConstraintGroup::FinalPosition) {
+        if objective::frees_landing_position(&algo.cost_terms) {
+            let r_end = vars.steps[k_end].r;
+            let up = params.e_hat_up;
+            model.add_constraint(constraint!(
+                up.x * r_end[0] + up.y * r_end[1] + up.z * r_end[2]
+                    == up.x * r_target[0].clone()
+                        + up.y * r_target[1].clone()
+                        + up.z * r_target[2].clone()
+            ));
+        } else {
+            for (var, r_T) in vars.steps[k_end].r.iter().zip(r_target) {
+                model.add_constraint(constraint!(*var == r_T));
+            }
+        }
     }
 
-    // Final velocity v[N-1] = vf
-    for (var, &vf_val) in vars.steps[k_end].v.iter().zip(params.vf.iter()) {
-        model.add_constraint(constraint!(*var == vf_val));
+    // Final velocity v[N-1] = v_T(tf)
+    if algo.is_enabled(ConstraintGroup::FinalVelocity) {
+        for (var, v_T) in vars.steps[k_end].v.iter().zip(v_target) {
+            model.add_constraint(constraint!(*var == v_T));
+        }
     }
 
     // Final thrust direction
     // T[N-1] = Gamma[N-1] * n_hatf
-    for (i, tf) in vars.steps[k_end].t.iter().enumerate() {
-        model.add_constraint(constraint!(
-            *tf == vars.steps[k_end].gamma * params.n_hatf[i]
-        ));
+    if algo.is_enabled(ConstraintGroup::FinalThrustDirection) {
+        for (i, tf) in vars.steps[k_end].t.iter().enumerate() {
+            model.add_constraint(constraint!(
+                *tf == vars.steps[k_end].gamma * params.n_hatf[i]
+            ));
+        }
     }
 }
 
-fn pre_compute(params: &SimulationParams, settings: &AlgorithmParams) -> (Vec<f64>, Vec<f64>) {
+pub(super) fn pre_compute(
+    params: &SimulationParams,
+    settings: &AlgorithmParams,
+) -> (Vec<f64>, Vec<f64>) {
     // Pre-computed values for Problem 4
     // mu[k] = ((k_n - k)/k_n)*m_0 + (k/k_n)*m_dry
     let mu_fn = |k: usize| {
@@ -417,65 +501,107 @@ fn add_state_constraints(
     settings: &AlgorithmParams,
 ) {
     let N = settings.N;
+    let tf = (N - 1) as f64 * settings.dt;
 
     // Add SOC constraints
-    for k in 0..N {
-        // Mass lowerbound constraint
-        // m[k] >= m_dry
-        model.add_constraint(constraint!(vars.steps[k].m >= params.m_dry));
+    if settings.is_enabled(ConstraintGroup::MassFloor) {
+        for k in 0..N {
+            // Mass lowerbound constraint
+            // m[k] >= m_dry
+            model.add_constraint(constraint!(vars.steps[k].m >= params.m_dry));
+        }
     }
 
-    // Glide-slope constraint
-    // ||r[k]|| cos(gamma_gs) <= e_u^T * r[k]
-    let sec_gs = 1.0 / f64::to_radians(params.gamma_gs).cos();
-    for k in 0..N {
-        let t_expr = sec_gs
-            * (params.e_hat_up.x * vars.steps[k].r[0]
-                + params.e_hat_up.y * vars.steps[k].r[1]
-                + params.e_hat_up.z * vars.steps[k].r[2]);
-        // Use the expression directly
-        model.add_constraint(soc_constraint!(
-            norm2(vars.steps[k].r[0], vars.steps[k].r[1], vars.steps[k].r[2]) <= t_expr
-        ));
+    // Glide-slope constraint about the cone vertex c[k]
+    // ||r[k] - c[k]|| cos(gamma_gs) <= e_u^T * (r[k] - c[k])
+    if settings.is_enabled(ConstraintGroup::GlideSlope) {
+        let sec_gs = 1.0 / params.gamma_gs().get::<radian>().cos();
+        for k in 0..N {
+            let vertex = params.mission.glide_slope_vertex(params, k, N, tf);
+            let offset: Vec<Expression> = (0..3).map(|i| vars.steps[k].r[i] - vertex[i]).collect();
+            let t_expr = sec_gs
+                * (params.e_hat_up.x * offset[0].clone()
+                    + params.e_hat_up.y * offset[1].clone()
+                    + params.e_hat_up.z * offset[2].clone());
+            model.add_constraint(soc_constraint!(norm2_vec(offset) <= t_expr));
+        }
+    }
+
+    // Hop altitude constraints
+    let r: Vec<_> = vars.steps.iter().map(|s| s.r).collect();
+    let v: Vec<_> = vars.steps.iter().map(|s| s.v).collect();
+    for constraint in mission::altitude_constraints(params, &r, &v) {
+        model.add_constraint(constraint);
     }
 
     // Thrust (Equation 70)
     // ||T[k]|| <= Gamma[k]
-    for k in 0..N {
-        model.add_constraint(soc_constraint!(
-            norm2(vars.steps[k].t[0], vars.steps[k].t[1], vars.steps[k].t[2])
-                <= vars.steps[k].gamma
-        ));
+    if settings.is_enabled(ConstraintGroup::ThrustMagnitude) {
+        for k in 0..N {
+            model.add_constraint(soc_constraint!(
+                norm2(vars.steps[k].t[0], vars.steps[k].t[1], vars.steps[k].t[2])
+                    <= vars.steps[k].gamma
+            ));
+        }
     }
 
     // Max/Min thrust (Equation 71)
     // T_min <= Gamma[k] <= T_max
-    for k in 0..N {
-        model.add_constraint(constraint!(vars.steps[k].gamma >= params.t_min_vac));
-        model.add_constraint(constraint!(vars.steps[k].gamma <= params.t_max_vac));
+    if settings.is_enabled(ConstraintGroup::ThrustBounds) {
+        for k in 0..N {
+            model.add_constraint(constraint!(vars.steps[k].gamma >= params.t_min_vac));
+            model.add_constraint(constraint!(vars.steps[k].gamma <= params.t_max_vac));
+        }
     }
 
     // Tilt constraint (Equation 72):
     // Gamma[k] * cos(theta_max) <= e^T T[k].
     // e_hat_up dot T[k] - Gamma[k]*cos(...) >= 0
-    let cos_th = f64::to_radians(params.theta_max).cos();
-    for k in 0..N {
-        let up_dot_t = params.e_hat_up.x * vars.steps[k].t[0]
-            + params.e_hat_up.y * vars.steps[k].t[1]
-            + params.e_hat_up.z * vars.steps[k].t[2];
-
-        model.add_constraint(constraint!(up_dot_t >= cos_th * vars.steps[k].gamma));
+    if settings.is_enabled(ConstraintGroup::Tilt) {
+        let cos_th = params.theta_max().get::<radian>().cos();
+        for k in 0..N {
+            let up_dot_t = params.e_hat_up.x * vars.steps[k].t[0]
+                + params.e_hat_up.y * vars.steps[k].t[1]
+                + params.e_hat_up.z * vars.steps[k].t[2];
+
+            model.add_constraint(constraint!(up_dot_t >= cos_th * vars.steps[k].gamma));
+        }
     }
 
     // Rate of change of thrust (Equation 73):
     // dot_min*dt <= Gamma[k+1] - Gamma[k] <= Tdot_max*dt
-    for k in 0..N - 1 {
-        model.add_constraint(constraint!(
-            vars.steps[k + 1].gamma - vars.steps[k].gamma >= params.tdot_min * settings.dt
-        ));
-        model.add_constraint(constraint!(
-            vars.steps[k + 1].gamma - vars.steps[k].gamma <= params.tdot_max * settings.dt
-        ));
+    if settings.is_enabled(ConstraintGroup::ThrustRate) {
+        for k in 0..N - 1 {
+            model.add_constraint(constraint!(
+                vars.steps[k + 1].gamma - vars.steps[k].gamma >= params.tdot_min * settings.dt
+            ));
+            model.add_constraint(constraint!(
+                vars.steps[k + 1].gamma - vars.steps[k].gamma <= params.tdot_max * settings.dt
+            ));
+        }
+    }
+}
+
+/// Add the user-defined constraints
+fn add_custom_constraints(
+    model: &mut impl SolverModel,
+    vars: &DecisionVariables,
+    params: &SimulationParams,
+    settings: &AlgorithmParams,
+    constraints: &[Arc<dyn TrajectoryConstraint>],
+) {
+    let nodes: Vec<NodeVariables> = vars.steps.iter().map(TimeStepVariables::node).collect();
+    let ctx = ConstraintContext {
+        nodes: &nodes,
+        dt: Expression::from(settings.dt),
+        reference: None,
+        params,
+        algo: settings,
+    };
+    for constraint in constraints {
+        for c in constraint.constraints(&ctx) {
+            model.add_constraint(c);
+        }
     }
 }
 
@@ -486,15 +612,23 @@ fn add_slack_constraints(
     params: &SimulationParams,
     settings: &AlgorithmParams,
 ) {
+    let relaxed = settings.is_enabled(ConstraintGroup::Relaxation);
     for k in 0..settings.N {
-        // SC Modifications
-        // ||a_R[k]|| <= k_aR[k] }
-        model.add_constraint(soc_constraint!(
-            norm2(
-                vars.steps[k].aR[0],
-                vars.steps[k].aR[1],
-                vars.steps[k].aR[2]
-            ) <= vars.steps[k].kappa_aR
-        ));
+        if relaxed {
+            // SC Modifications
+            // ||a_R[k]|| <= k_aR[k] }
+            model.add_constraint(soc_constraint!(
+                norm2(
+                    vars.steps[k].aR[0],
+                    vars.steps[k].aR[1],
+                    vars.steps[k].aR[2]
+                ) <= vars.steps[k].kappa_aR
+            ));
+        } else {
+            // Without the relaxation the acceleration is exact, a_R[k] = 0
+            for aR in vars.steps[k].aR.iter() {
+                model.add_constraint(constraint!(*aR == 0.0));
+            }
+        }
     }
 }
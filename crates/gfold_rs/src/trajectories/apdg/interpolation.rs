@@ -0,0 +1,171 @@
+//! Continuous-time sampling of an APDG solution.
+//!
+//! The solver only returns the state at the discretisation nodes. Between
+//! nodes position and velocity are reconstructed with cubic Hermite splines
+//! whose end slopes are the node velocity and acceleration respectively, so
+//! `r' = v` and `v' = a` hold at every node. Acceleration, mass, thrust and the
+//! relaxation term are first-order-hold, which is the same assumption the
+//! trapezoidal dynamics constraints make.
+
+use nalgebra::Vector3;
+
+use super::{APDGSolution, APDGSolutionTimeStep};
+
+impl APDGSolution {
+    /// Total time of flight covered by the solution [s]
+    pub fn duration(&self) -> f64 {
+        self.num_steps().saturating_sub(1) as f64 * self.dt
+    }
+
+    /// Interpolated state and control at time `t` since the first node.
+    ///
+    /// `t` is clamped to `[0, duration()]`. Returns `None` for a solution
+    /// without nodes.
+    pub fn sample(&self, t: f64) -> Option<APDGSolutionTimeStep> {
+        let steps = &self.steps;
+        if steps.len() < 2 || self.dt <= 0.0 {
+            return steps.first().cloned();
+        }
+
+        let t = t.clamp(0.0, self.duration());
+        let k = ((t / self.dt) as usize).min(steps.len() - 2);
+        let tau = t - k as f64 * self.dt;
+        let s = tau / self.dt;
+
+        let (p, q) = (&steps[k], &steps[k + 1]);
+        let lerp_vec = |a: &Vector3<f64>, b: &Vector3<f64>| a + (b - a) * s;
+
+        Some(APDGSolutionTimeStep {
+            r: hermite(&p.r, &p.v, &q.r, &q.v, s, self.dt),
+            v: hermite(&p.v, &p.a, &q.v, &q.a, s, self.dt),
+            a: lerp_vec(&p.a, &q.a),
+            m: p.m + (q.m - p.m) * s,
+            t: lerp_vec(&p.t, &q.t),
+            gamma: p.gamma + (q.gamma - p.gamma) * s,
+            aR: lerp_vec(&p.aR, &q.aR),
+        })
+    }
+
+    /// Resample the solution at a fixed rate [Hz].
+    ///
+    /// Yields `(t, step)` pairs starting at `t = 0`, the last sample is at or
+    /// just before the end of the trajectory. A solution without nodes yields
+    /// nothing.
+    pub fn resample(&self, rate_hz: f64) -> impl Iterator<Item = (f64, APDGSolutionTimeStep)> + '_ {
+        let period = 1.0 / rate_hz;
+        // Small tolerance so the final node is not lost to rounding
+        let n_samples = (self.duration() / period + 1e-9).floor() as usize + 1;
+        (0..n_samples).filter_map(move |i| {
+            let t = i as f64 * period;
+            self.sample(t).map(|step| (t, step))
+        })
+    }
+}
+
+/// Cubic Hermite interpolation on a unit interval of length `h`.
+fn hermite(
+    p0: &Vector3<f64>,
+    m0: &Vector3<f64>,
+    p1: &Vector3<f64>,
+    m1: &Vector3<f64>,
+    s: f64,
+    h: f64,
+) -> Vector3<f64> {
+    let s2 = s * s;
+    let s3 = s2 * s;
+    let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+    let h10 = s3 - 2.0 * s2 + s;
+    let h01 = -2.0 * s3 + 3.0 * s2;
+    let h11 = s3 - s2;
+    p0 * h00 + m0 * (h10 * h) + p1 * h01 + m1 * (h11 * h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a solution from a trajectory with linearly varying acceleration,
+    /// which the interpolant should reproduce exactly.
+    fn linear_jerk_solution() -> APDGSolution {
+        let r0 = Vector3::new(100.0, 5.0, -3.0);
+        let v0 = Vector3::new(-10.0, 1.0, 2.0);
+        let a0 = Vector3::new(2.0, -0.5, 0.1);
+        let j = Vector3::new(0.3, 0.2, -0.1);
+        let dt = 0.4;
+
+        let steps = (0..6)
+            .map(|k| {
+                let t = k as f64 * dt;
+                APDGSolutionTimeStep {
+                    r: r0 + v0 * t + a0 * t.powi(2) / 2.0 + j * t.powi(3) / 6.0,
+                    v: v0 + a0 * t + j * t.powi(2) / 2.0,
+                    a: a0 + j * t,
+                    m: 1000.0 - 10.0 * t,
+                    t: Vector3::new(1000.0 * t, 0.0, 0.0),
+                    gamma: 1000.0 * t,
+                    aR: Vector3::zeros(),
+                }
+            })
+            .collect();
+
+        APDGSolution {
+            steps,
+            dt,
+            duals: None,
+            constraint_groups: None,
+        }
+    }
+
+    #[test]
+    fn test_sample_reproduces_cubic_motion() {
+        let sol = linear_jerk_solution();
+        let t = 0.97;
+        let s = sol.sample(t).unwrap();
+
+        let r0 = Vector3::new(100.0, 5.0, -3.0);
+        let v0 = Vector3::new(-10.0, 1.0, 2.0);
+        let a0 = Vector3::new(2.0, -0.5, 0.1);
+        let j = Vector3::new(0.3, 0.2, -0.1);
+
+        let r = r0 + v0 * t + a0 * t.powi(2) / 2.0 + j * t.powi(3) / 6.0;
+        let v = v0 + a0 * t + j * t.powi(2) / 2.0;
+        assert!((s.r - r).norm() < 1e-9);
+        assert!((s.v - v).norm() < 1e-9);
+        assert!((s.a - (a0 + j * t)).norm() < 1e-9);
+        assert!((s.m - (1000.0 - 10.0 * t)).abs() < 1e-9);
+        assert!((s.gamma - 1000.0 * t).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sample_clamps_and_hits_nodes() {
+        let sol = linear_jerk_solution();
+        let last = sol.steps().last().unwrap();
+
+        assert!((sol.sample(-1.0).unwrap().r - sol.steps()[0].r).norm() < 1e-12);
+        assert!((sol.sample(100.0).unwrap().r - last.r).norm() < 1e-9);
+        assert!((sol.sample(2.0 * sol.dt()).unwrap().v - sol.steps()[2].v).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_resample_covers_duration() {
+        let sol = linear_jerk_solution();
+        let samples: Vec<_> = sol.resample(10.0).collect();
+
+        // 2.0 s at 10 Hz, both ends included
+        assert_eq!(samples.len(), 21);
+        assert!((samples.last().unwrap().0 - sol.duration()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_solution_has_no_samples() {
+        let sol = APDGSolution {
+            steps: Vec::new(),
+            dt: 0.4,
+            duals: None,
+            constraint_groups: None,
+        };
+
+        assert!(sol.sample(1.0).is_none());
+        assert_eq!(sol.resample(10.0).count(), 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Mission profiles.
+//!
+//! A [`Mission::Descent`] is the powered descent of the paper. It starts
+//! airborne with the engine already burning at `gamma_0_vac` along `n_hat0`
+//! and the glide slope cone has its vertex at the origin. For a moving
+//! target the vertex is the target position at touchdown instead.
+//!
+//! A [`Mission::Hop`] lifts off from rest at `r0` and lands at `rf`. The
+//! initial thrust is left free, and the trajectory is split at the apex
+//! node: the ascent is kept inside a glide slope cone with its vertex at the
+//! launch pad, the descent inside the usual cone at the landing pad. The
+//! cone at the launch pad also keeps the vehicle from sinking below the pad.
+//! Optional altitude constraints shape the ascent, they are linear and added
+//! to both the initial guess and every SC step. A hop has to start at rest,
+//! so it cannot be re-planned in flight.
+
+use good_lp::{constraint, Constraint, Expression, Variable};
+use nalgebra::Vector3;
+
+use super::{models::SimulationParams, target::LandingTarget};
+
+/// Shape of the trajectory.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mission {
+    /// Powered descent from an airborne, thrusting start
+    #[default]
+    Descent,
+    /// Point-to-point hop from rest on the ground
+    Hop(HopMission),
+}
+
+/// Settings of a [`Mission::Hop`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct HopMission {
+    /// Node separating the ascent from the descent, `(N - 1) / 2` if unset
+    pub apex_node: Option<usize>,
+
+    /// Altitude `e_u^T r` reached at the apex node, where the vertical
+    /// velocity is zero
+    /// [m]
+    pub apex_altitude: Option<f64>,
+
+    /// Altitude `e_u^T r` not to be exceeded at any node
+    /// [m]
+    pub max_altitude: Option<f64>,
+}
+
+impl HopMission {
+    /// Node separating the ascent from the descent.
+    pub fn apex(&self, N: usize) -> usize {
+        self.apex_node.unwrap_or((N - 1) / 2).min(N - 1)
+    }
+}
+
+impl Mission {
+    /// Whether the initial thrust is fixed to `gamma_0_vac * n_hat0`.
+    pub fn fixes_initial_thrust(&self) -> bool {
+        matches!(self, Mission::Descent)
+    }
+
+    /// Vertex of the glide slope cone that node `k` of `N` has to stay in,
+    /// for a time of flight `tf` [s].
+    ///
+    /// The origin for a descent to a fixed target, the target position at
+    /// `tf` for a moving target, and the launch or landing pad for a hop.
+    pub fn glide_slope_vertex(
+        &self,
+        params: &SimulationParams,
+        k: usize,
+        N: usize,
+        tf: f64,
+    ) -> Vector3<f64> {
+        match (self, &params.target) {
+            (Mission::Hop(hop), _) if k <= hop.apex(N) => params.r0,
+            (Mission::Descent, LandingTarget::Fixed) => Vector3::zeros(),
+            _ => params.target.state(params, tf).0,
+        }
+    }
+}
+
+/// Altitude constraints of a hop on the position and velocity variables.
+///
+/// Empty for a descent.
+pub(super) fn altitude_constraints(
+    params: &SimulationParams,
+    r: &[Vector3<Variable>],
+    v: &[Vector3<Variable>],
+) -> Vec<Constraint> {
+    let Mission::Hop(hop) = params.mission else {
+        return Vec::new();
+    };
+    let up = params.e_hat_up;
+    let vertical =
+        |x: &Vector3<Variable>| -> Expression { up.x * x[0] + up.y * x[1] + up.z * x[2] };
+
+    let mut constraints = Vec::new();
+    if let Some(h_max) = hop.max_altitude {
+        // e_u^T r[k] <= h_max
+        constraints.extend(r.iter().map(|r_k| constraint!(vertical(r_k) <= h_max)));
+    }
+    if let Some(h_apex) = hop.apex_altitude {
+        // e_u^T r[k_apex] = h_apex, e_u^T v[k_apex] = 0
+        let k = hop.apex(r.len());
+        constraints.push(constraint!(vertical(&r[k]) == h_apex));
+        constraints.push(constraint!(vertical(&v[k]) == 0.0));
+    }
+    constraints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::{
+        apdg::guess, APDGProblemSolver, APDGSolution, AlgorithmParams, Settings,
+    };
+
+    #[test]
+    fn test_hop_splits_glide_slope_at_apex() {
+        let params = SimulationParams::builder()
+            .r0([0.0, 0.0, 0.0].into())
+            .rf([0.0, 300.0, 0.0].into())
+            .v0(Vector3::zeros())
+            .mission(Mission::Hop(HopMission {
+                apex_node: Some(4),
+                ..Default::default()
+            }))
+            .build();
+
+        assert_eq!(
+            params.mission.glide_slope_vertex(&params, 4, 10, 9.0),
+            params.r0
+        );
+        assert_eq!(
+            params.mission.glide_slope_vertex(&params, 5, 10, 9.0),
+            params.rf
+        );
+        assert!(!params.mission.fixes_initial_thrust());
+    }
+
+    #[test]
+    fn test_hop_lifts_off_and_meets_apex() {
+        let hop = HopMission {
+            apex_altitude: Some(100.0),
+            ..Default::default()
+        };
+        let params = SimulationParams::builder()
+            .r0(Vector3::zeros())
+            .rf(Vector3::new(0.0, 300.0, 0.0))
+            .v0(Vector3::zeros())
+            .mission(Mission::Hop(hop))
+            .build();
+        let settings = Settings::builder()
+            .simulation_settings(params.clone())
+            .solver_settings(AlgorithmParams::builder().N(20).tf_guess(20.0).build())
+            .build();
+        settings.validate().unwrap();
+        let (solution, _) = APDGProblemSolver::default().solve(&settings).unwrap();
+
+        let steps = solution.steps();
+        let altitude = |r: &Vector3<f64>| params.e_hat_up.dot(r);
+        assert!(steps[0].v.norm() < 1e-6);
+        assert!(steps.iter().all(|s| altitude(&s.r) >= -1e-6));
+
+        let apex = &steps[hop.apex(steps.len())];
+        assert!((altitude(&apex.r) - 100.0).abs() < 1e-6);
+        assert!(params.e_hat_up.dot(&apex.v).abs() < 1e-6);
+        assert!(solution.check_constraints(&params, 1e-3).is_empty());
+    }
+
+    #[test]
+    fn test_descent_glide_slope_vertex() {
+        // A fixed landing site away from the origin keeps the cone at the origin
+        let params = SimulationParams::builder()
+            .rf(Vector3::new(0.0, 200.0, 0.0))
+            .build();
+        assert_eq!(
+            Mission::Descent.glide_slope_vertex(&params, 0, 20, 15.0),
+            Vector3::zeros()
+        );
+
+        // A moving target carries the cone to where it is at touchdown
+        let velocity = Vector3::new(0.0, 2.0, 0.0);
+        let moving = SimulationParams {
+            target: LandingTarget::ConstantVelocity { velocity },
+            ..params.clone()
+        };
+        assert_eq!(
+            Mission::Descent.glide_slope_vertex(&moving, 0, 20, 15.0),
+            params.rf + velocity * 15.0
+        );
+    }
+}
@@ -5,19 +5,70 @@ use clarabel::algebra::*;
 use clarabel::solver::traits::Solution;
 use clarabel::solver::*;
 use good_lp::solvers::clarabel::ClarabelProblem;
-use models::{AlgorithmParams, SimulationParams};
+use models::{AlgorithmParams, ConstraintGroup, InitialGuess, SimulationParams};
 use nalgebra::Vector3;
+use std::sync::Arc;
 use thiserror::Error;
+use tightness::TightnessReport;
 
 mod error;
-use error::Error;
+pub use error::Error;
 mod guess;
 mod sucessive;
 
 // Settings
 pub mod models;
 
+// Continuous-time sampling of solutions
+pub mod interpolation;
+
+// Nonlinear verification of solutions
+pub mod propagation;
+
+// Constraint checks on solutions
+pub mod verification;
+
+// Dual variables and constraint sensitivities
+pub mod duals;
+use duals::ConstraintDuals;
+
+// User-defined constraints
+pub mod constraints;
+use constraints::TrajectoryConstraint;
+
+// Configurable cost terms
+pub mod objective;
+
+// Descent and hop mission profiles
+pub mod mission;
+
+// Coast and burn sequences
+pub mod phases;
+
+// Moving landing targets
+pub mod target;
+
+// Relaxation gaps of solutions
+pub mod tightness;
+
+// Constraint tightening for uncertainty
+pub mod robust;
+
+// Input validation
+pub mod validation;
+
+// Closed-form feasibility checks
+pub mod screening;
+
+// Receding-horizon re-planning
+pub mod replan;
+
+// Non-blocking solves
+pub mod background;
+use background::{BestIterate, SolveControl, SolveProgress, Stop};
+
 #[derive(Debug, Clone, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A single time step of the APDG solution
 pub struct APDGSolutionTimeStep {
     /// Position [m]
@@ -38,21 +89,47 @@ pub struct APDGSolutionTimeStep {
 
 /// A complete APDG solution
 #[derive(Debug, Clone, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct APDGSolution {
     /// An array of optimal variables at each time step
     steps: Vec<APDGSolutionTimeStep>,
 
     /// The time between each time step [s]
     dt: f64,
+
+    /// Dual variables of the solve that produced the solution
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    duals: Option<ConstraintDuals>,
+
+    /// Constraint groups enforced by the solve that produced the solution
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    constraint_groups: Option<Vec<ConstraintGroup>>,
 }
 
 /// Required settings for a trajectory to be generated.
 #[derive(Builder, Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct Settings {
     #[builder(default = SimulationParams::builder().build())]
     simulation_settings: SimulationParams,
     #[builder(default = AlgorithmParams::builder().build())]
     solver_settings: AlgorithmParams,
+    #[builder(default)]
+    initial_guess: InitialGuess,
+    /// Mission-specific constraints, added to every convex subproblem
+    #[builder(default)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    constraints: Vec<Arc<dyn TrajectoryConstraint>>,
 }
 
 impl Settings {
@@ -65,6 +142,22 @@ impl Settings {
     pub fn solver_settings(&self) -> &AlgorithmParams {
         &self.solver_settings
     }
+
+    /// Returns the initial guess strategy.
+    pub fn initial_guess(&self) -> &InitialGuess {
+        &self.initial_guess
+    }
+
+    /// Returns the user-defined constraints.
+    pub fn constraints(&self) -> &[Arc<dyn TrajectoryConstraint>] {
+        &self.constraints
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::builder().build()
+    }
 }
 
 impl APDGSolution {
@@ -78,12 +171,20 @@ impl APDGSolution {
         self.dt
     }
 
+    /// Constraint groups enforced by the solve that produced the solution.
+    ///
+    /// `None` for solutions that were not produced by a convex solve.
+    pub fn constraint_groups(&self) -> Option<&[ConstraintGroup]> {
+        self.constraint_groups.as_deref()
+    }
+
     /// Individual steps of the solution
     pub fn steps(&self) -> &[APDGSolutionTimeStep] {
         &self.steps
     }
 }
 
+/// Successive convexification solver for the APDG problem.
 #[derive(Debug, Clone, Default)]
 pub struct APDGProblemSolver {}
 
@@ -93,23 +194,106 @@ impl APDGProblemSolver {
         &mut self,
         settings: &Settings,
     ) -> Result<(APDGSolution, ConvergenceHistory), Error> {
-        _solve(settings)
+        settings.validate()?;
+        _solve(settings, &SolveControl::default())
     }
 }
 
-fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Error> {
-    println!("Settings: {settings:?}");
+fn _solve(
+    settings: &Settings,
+    control: &SolveControl,
+) -> Result<(APDGSolution, ConvergenceHistory), Error> {
+    eprintln!("Settings: {settings:?}");
 
-    // --- Step 1: Initial Guess (Problem 4) ---
-    println!("Solving Initial Guess Problem...");
-    let initial_problem = guess::problem::APDGProblem::new(
-        settings.simulation_settings().clone(),
-        settings.solver_settings().clone(),
-    );
-    let mut current_solution = initial_problem.solve()?;
-    println!("Initial Guess Solved.");
+    match control.should_stop() {
+        Some(Stop::Cancelled) => return Err(Error::Cancelled),
+        Some(Stop::Deadline) => return Err(Error::DeadlineExceeded),
+        None => {}
+    }
+
+    // --- Step 1: Initial Guess ---
+    let sim = settings.simulation_settings();
+    let algo = settings.solver_settings();
+    let initial = initial_guess(settings, algo)?;
+    validation::check_finite(&initial, "initial guess")?;
+    eprintln!("Initial Guess Solved.");
+    control.report(SolveProgress::InitialGuess);
+
+    // --- Step 2: Successive Convexification ---
+    successive_convexification(settings, control, initial, |prev_trajectory| {
+        let successive_problem = sucessive::problem::APDGProblem::new(
+            sim.clone(),
+            algo.clone(),
+            prev_trajectory.clone(),
+            settings.constraints().to_vec(),
+        );
+        let new_solution = successive_problem.solve()?;
+        validation::check_finite(&new_solution, "successive problem")?;
+        Ok(new_solution)
+    })
+}
+
+/// Initial guess on the grid of `algo`, with the strategy of `settings`.
+fn initial_guess(settings: &Settings, algo: &AlgorithmParams) -> Result<APDGSolution, Error> {
+    let sim = settings.simulation_settings();
+    let solution = match settings.initial_guess() {
+        InitialGuess::Problem4 => {
+            eprintln!("Solving Initial Guess Problem...");
+            let initial_problem = guess::problem::APDGProblem::new(
+                sim.clone(),
+                algo.clone(),
+                settings.constraints().to_vec(),
+            );
+            initial_problem.solve()?
+        }
+        InitialGuess::LogMass => guess::log_mass::solve(sim, algo, settings.constraints())?,
+        InitialGuess::Polynomial => guess::analytic::polynomial(sim, algo),
+        InitialGuess::StraightLine => guess::analytic::straight_line(sim, algo),
+        InitialGuess::Provided(solution) => guess::analytic::resample(solution, algo),
+    };
+    Ok(solution)
+}
+
+/// Reference trajectory of the SC loop, split into segments that are each
+/// discretised with their own time step.
+trait Reference: Clone {
+    /// Segments of the trajectory, in order
+    fn segments(&self) -> impl Iterator<Item = &APDGSolution>;
+
+    /// Relaxation gaps at every node of every segment
+    fn tightness(&self, params: &SimulationParams) -> TightnessReport;
+
+    /// Every node of every segment, in order
+    fn nodes(&self) -> impl Iterator<Item = &APDGSolutionTimeStep> {
+        self.segments().flat_map(|s| s.steps.iter())
+    }
+}
+
+impl Reference for APDGSolution {
+    fn segments(&self) -> impl Iterator<Item = &APDGSolution> {
+        std::iter::once(self)
+    }
+
+    fn tightness(&self, _params: &SimulationParams) -> TightnessReport {
+        APDGSolution::tightness(self)
+    }
+}
 
-    let n_sc = settings.solver_settings().n_sc;
+/// Successive convexification from `initial`, `step` solves the convex
+/// subproblem about the previous iterate.
+///
+/// Stops on convergence, after `n_sc` iterations or when `control` asks to.
+/// At the deadline the best iterate so far is returned, and the result is
+/// rejected if `relaxation_tolerance` is set and not met.
+fn successive_convexification<T: Reference>(
+    settings: &Settings,
+    control: &SolveControl,
+    initial: T,
+    mut step: impl FnMut(&T) -> Result<T, Error>,
+) -> Result<(T, ConvergenceHistory), Error> {
+    let algo = settings.solver_settings();
+    let n_sc = algo.n_sc;
+    let mut current_solution = initial;
 
     // Store convergence history
     let mut pos_log: Vec<f64> = Vec::with_capacity(n_sc);
@@ -117,27 +301,32 @@ fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Err
     let mut thrust_log: Vec<f64> = Vec::with_capacity(n_sc);
     let mut aR_log: Vec<f64> = Vec::with_capacity(n_sc);
     const LOG_EPSILON: f64 = 1e-10; // Prevent a log10(0) error
+    let mut converged = false;
+    let mut truncated = false;
+    let mut best = BestIterate::default();
     for i in 0..n_sc {
-        println!("Starting Iteration {}...", i + 1);
+        match control.should_stop() {
+            Some(Stop::Cancelled) => return Err(Error::Cancelled),
+            Some(Stop::Deadline) => {
+                truncated = true;
+                break;
+            }
+            None => {}
+        }
 
-        // Use current solution as the previous trajectory
-        let prev_trajectory: APDGSolution = current_solution;
+        eprintln!("Starting Iteration {}...", i + 1);
 
-        // Setup and solve the successive problem
-        let successive_problem = sucessive::problem::APDGProblem::new(
-            settings.simulation_settings().clone(),
-            settings.solver_settings().clone(),
-            prev_trajectory.clone(),
-        );
+        // Use current solution as the previous trajectory
+        let prev_trajectory = current_solution;
 
-        match successive_problem.solve() {
+        match step(&prev_trajectory) {
             Ok(new_solution) => {
-                println!("Iteration {} Solved.", i + 1);
+                eprintln!("Iteration {} Solved.", i + 1);
 
                 // Check for convergence
                 let solution_differences =
                     calculate_solution_differences(&prev_trajectory, &new_solution);
-                println!(
+                eprintln!(
                     "Iteration {}: Max Absolute Differences - Pos: {:.6e}, Vel: {:.6e}, Mass: {:.6e}, Thrust: {:.6e}, Max Relative: {:.6e}",
                     i + 1,
                     solution_differences.abs_pos,
@@ -152,20 +341,27 @@ fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Err
                 vel_log.push((solution_differences.abs_vel + LOG_EPSILON).log10());
                 thrust_log.push((solution_differences.abs_thrust + LOG_EPSILON).log10());
                 aR_log.push((solution_differences.abs_aR + LOG_EPSILON).log10());
+                best.offer(algo, &prev_trajectory, &new_solution);
                 // Promote new solution to current solution and iterate until convergence
                 current_solution = new_solution;
-
-                if solution_differences.max_relative < settings.solver_settings().sc_tolerance {
-                    println!(
+                control.report(SolveProgress::Iteration {
+                    iteration: i + 1,
+                    n_sc,
+                    max_relative: solution_differences.max_relative,
+                });
+
+                if solution_differences.max_relative < algo.sc_tolerance {
+                    converged = true;
+                    eprintln!(
                         "Converged after {} iterations (Tolerance: {:.1e}).",
                         i + 1,
-                        settings.solver_settings().sc_tolerance
+                        algo.sc_tolerance
                     );
                     break;
                 }
 
                 if i == n_sc - 1 {
-                    println!("Reached maximum iterations ({}).", n_sc);
+                    eprintln!("Reached maximum iterations ({}).", n_sc);
                 }
             }
             Err(e) => {
@@ -174,16 +370,21 @@ fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Err
         }
     }
 
-    println!("Successive Convexification Finished.");
-    println!("\nConvergence History (Log10 Max Differences):");
-    println!(
+    if truncated {
+        // Out of time, fall back to the best iterate rather than the latest
+        current_solution = best.into_solution(current_solution);
+    }
+
+    eprintln!("Successive Convexification Finished.");
+    eprintln!("\nConvergence History (Log10 Max Differences):");
+    eprintln!(
         "Iteration | Pos Diff (log10) | Vel Diff (log10) | Thrust Diff (log10) | aR Diff (log10)"
     );
-    println!(
+    eprintln!(
         "----------|------------------|------------------|---------------------|------------------|"
     );
     for i in 0..pos_log.len() {
-        println!(
+        eprintln!(
             "{:>9} | {:>16.6e} | {:>16.6e} | {:>19.6e} | {:>16.6e}",
             i + 1,
             pos_log[i],
@@ -193,6 +394,16 @@ fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Err
         );
     }
 
+    let tightness = current_solution.tightness(settings.simulation_settings());
+    if let Some(tolerance) = algo.relaxation_tolerance {
+        if !tightness.is_realisable(tolerance) {
+            return Err(Error::LooseRelaxation {
+                thrust_gap: tightness.max_thrust_gap_relative(),
+                aR: tightness.max_aR_relative(),
+            });
+        }
+    }
+
     Ok((
         current_solution,
         ConvergenceHistory {
@@ -200,6 +411,8 @@ fn _solve(settings: &Settings) -> Result<(APDGSolution, ConvergenceHistory), Err
             vel: vel_log,
             thrust: thrust_log,
             aR: aR_log,
+            converged,
+            truncated,
         },
     ))
 }
@@ -222,13 +435,10 @@ struct SolutionDifferences {
 
 /// Calculates the maximum absolute differences between two trajectories,
 /// and the combined relative difference for convergence checks.
-fn calculate_solution_differences(sol1: &APDGSolution, sol2: &APDGSolution) -> SolutionDifferences {
-    let steps1 = &sol1.steps;
-    let steps2 = &sol2.steps;
-
+fn calculate_solution_differences<T: Reference>(sol1: &T, sol2: &T) -> SolutionDifferences {
     assert_eq!(
-        steps1.len(),
-        steps2.len(),
+        sol1.nodes().count(),
+        sol2.nodes().count(),
         "Cannot compare solutions with different numbers of steps."
     );
 
@@ -246,7 +456,7 @@ fn calculate_solution_differences(sol1: &APDGSolution, sol2: &APDGSolution) -> S
     let mut max_rel_thrust = 0.0_f64;
     let mut max_rel_aR = 0.0_f64;
 
-    for (s1, s2) in steps1.iter().zip(steps2.iter()) {
+    for (s1, s2) in sol1.nodes().zip(sol2.nodes()) {
         // Absolute differences for history tracking
         let abs_pos_diff = (s2.r - s1.r).norm();
         max_abs_pos = max_abs_pos.max(abs_pos_diff);
@@ -1,8 +1,17 @@
+use std::collections::BTreeSet;
+
 use bon::{builder, Builder};
 use nalgebra::Vector3;
 
+use super::{mission::Mission, objective::CostTerm, target::LandingTarget, APDGSolution};
+
 /// Simulation parameters (Table 1).
 #[derive(Debug, Builder, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct SimulationParams {
     /// Ambient fluid density
     /// [kg/m^3]
@@ -119,10 +128,23 @@ pub struct SimulationParams {
     /// Drag coefficient
     #[builder(default = 1.0)]
     pub c_d: f64,
+
+    /// Descent or hop
+    #[builder(default)]
+    pub mission: Mission,
+
+    /// Fixed or moving landing target
+    #[builder(default)]
+    pub target: LandingTarget,
 }
 
 /// Boundary Conditions and Algorithm parameters
 #[derive(Debug, Builder, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "AlgorithmParamsConfig")
+)]
 pub struct AlgorithmParams {
     /// Initial guess for total flight time
     /// [s]
@@ -160,4 +182,154 @@ pub struct AlgorithmParams {
     /// Convergence tolerance for the SC loop (relative difference)
     #[builder(default = 1e-4)]
     pub sc_tolerance: f64,
+
+    /// Fail with [`Error::LooseRelaxation`](super::Error::LooseRelaxation)
+    /// if a relaxation gap of the final solution exceeds this (relative)
+    pub relaxation_tolerance: Option<f64>,
+
+    /// Terms added to the final mass cost
+    #[builder(default)]
+    pub cost_terms: Vec<CostTerm>,
+
+    /// Constraint groups left out of both problems
+    #[builder(default)]
+    pub disabled_constraints: BTreeSet<ConstraintGroup>,
+}
+
+impl AlgorithmParams {
+    /// Whether `group` is added to the problems.
+    pub fn is_enabled(&self, group: ConstraintGroup) -> bool {
+        !self.disabled_constraints.contains(&group)
+    }
+
+    /// Every constraint group that is added to the problems.
+    pub fn enabled_constraints(&self) -> Vec<ConstraintGroup> {
+        ConstraintGroup::ALL
+            .into_iter()
+            .filter(|&group| self.is_enabled(group))
+            .collect()
+    }
+}
+
+/// Constraint families that can be switched off.
+///
+/// The initial conditions, dynamics and trust regions are always enforced. A
+/// disabled group can be replaced by a
+/// [`TrajectoryConstraint`](super::constraints::TrajectoryConstraint).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstraintGroup {
+    /// `r[N-1] = rf`
+    FinalPosition,
+    /// `v[N-1] = vf`
+    FinalVelocity,
+    /// `T[N-1] = Γ[N-1] * n_hatf`
+    FinalThrustDirection,
+    /// `m[k] >= m_dry`
+    MassFloor,
+    /// `||r[k] - c[k]|| cos(γ_gs) <= e_u^T (r[k] - c[k])` with the vertex
+    /// `c[k]` from [`Mission::glide_slope_vertex`](super::mission::Mission::glide_slope_vertex)
+    GlideSlope,
+    /// `||T[k]|| <= Γ[k]`
+    ThrustMagnitude,
+    /// `T_min <= Γ[k] <= T_max`
+    ThrustBounds,
+    /// `Γ[k] cos(θ_max) <= e_u^T T[k]`
+    Tilt,
+    /// `Tdot_min dt <= Γ[k+1] - Γ[k] <= Tdot_max dt`
+    ThrustRate,
+    /// `||a_R[k]|| <= κ_aR[k]`, disabling it forces `a_R = 0`
+    Relaxation,
+}
+
+impl ConstraintGroup {
+    /// Every group, in the order they are added.
+    pub const ALL: [ConstraintGroup; 10] = [
+        ConstraintGroup::FinalPosition,
+        ConstraintGroup::FinalVelocity,
+        ConstraintGroup::FinalThrustDirection,
+        ConstraintGroup::MassFloor,
+        ConstraintGroup::GlideSlope,
+        ConstraintGroup::ThrustMagnitude,
+        ConstraintGroup::ThrustBounds,
+        ConstraintGroup::Tilt,
+        ConstraintGroup::ThrustRate,
+        ConstraintGroup::Relaxation,
+    ];
+}
+
+/// Strategy for the reference trajectory of the first SC iteration.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InitialGuess {
+    /// Solve the convex Problem 4 with assumed mass and speed profiles
+    #[default]
+    Problem4,
+    /// Solve Problem 4 in the lossless log-mass variables `z = ln m` and
+    /// `σ = Γ / m`
+    ///
+    /// Exact up to discretisation without drag and back pressure. User
+    /// constraints on mass and thrust are only applied to first order, cost
+    /// terms are not supported.
+    LogMass,
+    /// Closed-form minimum-energy (ZEM/ZEV) trajectory
+    Polynomial,
+    /// Linear interpolation between the boundary conditions
+    StraightLine,
+    /// A previous solution, resampled to `N` nodes if needed
+    Provided(Box<APDGSolution>),
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Default for AlgorithmParams {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// On-disk form of [`AlgorithmParams`].
+///
+/// `dt` defaults to `tf_guess / N`, so missing fields have to go through the
+/// builder rather than a plain `#[serde(default)]`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlgorithmParamsConfig {
+    tf_guess: Option<f64>,
+    N: Option<usize>,
+    dt: Option<f64>,
+    n_sc: Option<usize>,
+    w_mf: Option<f64>,
+    w_eta_dt: Option<f64>,
+    w_eta_T: Option<f64>,
+    w_kappa_aR: Option<f64>,
+    sc_tolerance: Option<f64>,
+    relaxation_tolerance: Option<f64>,
+    cost_terms: Option<Vec<CostTerm>>,
+    disabled_constraints: Option<BTreeSet<ConstraintGroup>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<AlgorithmParamsConfig> for AlgorithmParams {
+    fn from(config: AlgorithmParamsConfig) -> Self {
+        AlgorithmParams::builder()
+            .maybe_tf_guess(config.tf_guess)
+            .maybe_N(config.N)
+            .maybe_dt(config.dt)
+            .maybe_n_sc(config.n_sc)
+            .maybe_w_mf(config.w_mf)
+            .maybe_w_eta_dt(config.w_eta_dt)
+            .maybe_w_eta_T(config.w_eta_T)
+            .maybe_w_kappa_aR(config.w_kappa_aR)
+            .maybe_sc_tolerance(config.sc_tolerance)
+            .maybe_relaxation_tolerance(config.relaxation_tolerance)
+            .maybe_cost_terms(config.cost_terms)
+            .maybe_disabled_constraints(config.disabled_constraints)
+            .build()
+    }
 }
@@ -0,0 +1,202 @@
+//! Configurable cost terms.
+//!
+//! The cost always contains `-w_mf * m[N-1]` and the slack penalties of the
+//! relaxation and trust regions. [`CostTerm`]s listed in
+//! [`AlgorithmParams::cost_terms`](super::models::AlgorithmParams::cost_terms)
+//! are added on top, so a minimum time landing is `w_mf = 0` with a
+//! [`CostTerm::FinalTime`], and any weighted mixture is a longer list. With
+//! `w_mf = 0` the duals cannot be converted into final mass, so the
+//! sensitivities of [`ConstraintDuals`](super::duals::ConstraintDuals) are
+//! `None`.
+//!
+//! Thrust terms are normalised by the maximum thrust so their weights are
+//! comparable with each other. Quadratic terms are written as second-order
+//! cone epigraphs, since the patched `good_lp` Clarabel backend has no
+//! quadratic objective. Only [`sum_of_squares`] needs to change once it does.
+
+use good_lp::{soc_constraint, Constraint, Expression, ProblemVariables, Variable};
+
+use super::{constraints::NodeVariables, models::SimulationParams};
+
+/// A weighted term added to the cost function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CostTerm {
+    /// Time of flight `(N - 1) * dt`, only has an effect when `dt` is free
+    /// (successive convexification)
+    FinalTime {
+        /// Weight [1/s]
+        weight: f64,
+    },
+    /// Distance of the final position from `rf`
+    ///
+    /// The final position is only held to the altitude of `rf`, giving the
+    /// minimum landing error problem.
+    LandingError {
+        /// Weight [1/m]
+        weight: f64,
+    },
+    /// Sum of `Γ[k] / T_max` over all nodes
+    ThrustL1 {
+        /// Weight
+        weight: f64,
+    },
+    /// Sum of `(Γ[k] / T_max)^2` over all nodes
+    ThrustL2 {
+        /// Weight
+        weight: f64,
+    },
+    /// Sum of `((Γ[k+1] - Γ[k]) / T_max)^2` over all steps
+    ThrustRate {
+        /// Weight
+        weight: f64,
+    },
+}
+
+impl CostTerm {
+    /// Weight of the term.
+    pub fn weight(&self) -> f64 {
+        match *self {
+            CostTerm::FinalTime { weight }
+            | CostTerm::LandingError { weight }
+            | CostTerm::ThrustL1 { weight }
+            | CostTerm::ThrustL2 { weight }
+            | CostTerm::ThrustRate { weight } => weight,
+        }
+    }
+}
+
+/// Whether the final position is left free by a landing error term.
+pub(super) fn frees_landing_position(terms: &[CostTerm]) -> bool {
+    terms
+        .iter()
+        .any(|term| matches!(term, CostTerm::LandingError { .. }))
+}
+
+/// Objective contribution and epigraph constraints of `terms`.
+///
+/// Epigraph variables are added to `vars`, so this has to be called before
+/// the problem is created. `landing_site` is the target position at the
+/// final node.
+pub(super) fn cost_terms(
+    vars: &mut ProblemVariables,
+    terms: &[CostTerm],
+    nodes: &[NodeVariables],
+    dt: Expression,
+    landing_site: &[Expression],
+    params: &SimulationParams,
+) -> (Expression, Vec<Constraint>) {
+    let mut objective = Expression::default();
+    let mut constraints = Vec::new();
+    let Some(last) = nodes.last() else {
+        return (objective, constraints);
+    };
+    let inv_t_max = 1.0 / params.t_max_vac;
+
+    for term in terms {
+        match *term {
+            CostTerm::FinalTime { weight } => {
+                objective += weight * (nodes.len() - 1) as f64 * dt.clone();
+            }
+            CostTerm::LandingError { weight } => {
+                let error = vars.add_variable();
+                let offset: Vec<Expression> = (0..3)
+                    .map(|i| last.r[i] - landing_site[i].clone())
+                    .collect();
+                constraints.push(soc_constraint!(norm2_vec(offset) <= error));
+                objective += weight * error;
+            }
+            CostTerm::ThrustL1 { weight } => {
+                for node in nodes {
+                    objective += weight * inv_t_max * node.gamma;
+                }
+            }
+            CostTerm::ThrustL2 { weight } => {
+                let thrust = nodes.iter().map(|n| inv_t_max * n.gamma).collect();
+                let (total, constraint) = sum_of_squares(vars, thrust);
+                constraints.push(constraint);
+                objective += weight * total;
+            }
+            CostTerm::ThrustRate { weight } => {
+                let rate = nodes
+                    .windows(2)
+                    .map(|w| inv_t_max * (w[1].gamma - w[0].gamma))
+                    .collect();
+                let (total, constraint) = sum_of_squares(vars, rate);
+                constraints.push(constraint);
+                objective += weight * total;
+            }
+        }
+    }
+
+    (objective, constraints)
+}
+
+/// Epigraph `s >= sum(x_i^2)` as the rotated cone `||(2x, s - 1)|| <= s + 1`.
+fn sum_of_squares(vars: &mut ProblemVariables, xs: Vec<Expression>) -> (Variable, Constraint) {
+    let total = vars.add_variable();
+    let mut sides: Vec<Expression> = xs.into_iter().map(|x| 2.0 * x).collect();
+    sides.push(total - 1.0);
+    (total, soc_constraint!(norm2_vec(sides) <= total + 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use good_lp::{clarabel, constraint, variables, Solution, SolverModel};
+
+    use super::*;
+    use nalgebra::Vector3;
+
+    use crate::trajectories::{
+        APDGProblemSolver, AlgorithmParams, ConstraintFamily, Settings, SimulationParams,
+    };
+
+    #[test]
+    fn test_minimum_time_landing_has_no_final_mass_sensitivity() {
+        let settings = Settings::builder()
+            .simulation_settings(
+                SimulationParams::builder()
+                    .r0(Vector3::new(500.0, 100.0, 0.0))
+                    .v0(Vector3::new(-30.0, 0.0, 0.0))
+                    .build(),
+            )
+            .solver_settings(
+                AlgorithmParams::builder()
+                    .N(20)
+                    .w_mf(0.0)
+                    .cost_terms(vec![CostTerm::FinalTime { weight: 100.0 }])
+                    .build(),
+            )
+            .build();
+        settings.validate().unwrap();
+        let (solution, _) = APDGProblemSolver::default().solve(&settings).unwrap();
+
+        // The raw duals are there, but not in final mass
+        let duals = solution.duals().unwrap();
+        assert_eq!(duals.family(ConstraintFamily::ThrustMax).len(), 20);
+        assert_eq!(solution.final_mass_per_newton_max_thrust(), None);
+        assert_eq!(
+            solution.final_mass_per_degree_tilt(settings.simulation_settings()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sum_of_squares_epigraph_is_tight() {
+        let mut vars = variables!();
+        let x = vars.add_variable();
+        let y = vars.add_variable();
+        let (total, epigraph) = sum_of_squares(&mut vars, vec![x.into(), y.into()]);
+
+        let solution = vars
+            .minimise(total)
+            .using(clarabel)
+            .with(epigraph)
+            .with(constraint!(x == 3.0))
+            .with(constraint!(y == -4.0))
+            .solve()
+            .unwrap();
+
+        assert!((solution.value(total) - 25.0).abs() < 1e-6);
+    }
+}
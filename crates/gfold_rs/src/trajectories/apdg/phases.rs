@@ -0,0 +1,508 @@
+//! Multi-phase trajectories.
+//!
+//! A single APDG solve is one continuous burn on `N` nodes. Planning from
+//! well before the landing burn needs a sequence of phases instead, e.g. an
+//! unpowered coast, an entry burn, a second coast and the landing burn. Each
+//! [`Phase`] has its own node count and time step `dt[p]`, and consecutive
+//! phases are linked by continuity of position, velocity and mass between
+//! the last node of one phase and the first node of the next. Every `dt[p]`
+//! is a decision variable with its own trust region, so the ignition time of
+//! a burn that follows a coast is optimised along with everything else.
+//!
+//! Every phase carries the Problem 5 constraints of a single solve, with
+//! the node indices of user constraints counted from the start of the phase.
+//! Burn phases can have their own thrust limits, coast phases have zero
+//! thrust and no mass flow. The SC iterations are the same as for a single
+//! solve, including the initial guess strategy, the relaxation tolerance,
+//! cancellation and the deadline of [`APDGProblemSolver::spawn_phases`].
+//!
+//! The initial guess is computed over the total guessed duration and split
+//! between the phases, with the thrust of coast nodes moved into the
+//! relaxation term. Only the descent mission is supported, and cost terms
+//! are not applied.
+
+use std::sync::Arc;
+
+use bon::Builder;
+use nalgebra::Vector3;
+
+use super::{
+    background::{SolveControl, SolveProgress, Stop},
+    constraints::TrajectoryConstraint,
+    initial_guess,
+    mission::Mission,
+    models::{AlgorithmParams, ConstraintGroup, SimulationParams},
+    successive_convexification, sucessive,
+    tightness::{relative, TightnessReport},
+    validation::{self, ValidationErrors},
+    APDGProblemSolver, APDGSolution, Error, Reference, Settings,
+};
+use crate::trajectories::ConvergenceHistory;
+
+/// Whether the engine is burning during a phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PhaseKind {
+    /// Engine off, the vehicle follows gravity and drag
+    Coast,
+    /// Engine on
+    Burn,
+}
+
+/// A segment of a multi-phase trajectory.
+#[derive(Debug, Clone, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Phase {
+    /// Coast or burn
+    pub kind: PhaseKind,
+
+    /// Number of nodes, including both ends
+    #[builder(default = 10)]
+    pub N: usize,
+
+    /// Initial guess for the duration of the phase
+    /// [s]
+    pub tf_guess: f64,
+
+    /// Shortest allowed duration
+    /// [s]
+    pub min_duration: Option<f64>,
+
+    /// Longest allowed duration
+    /// [s]
+    pub max_duration: Option<f64>,
+
+    /// Minimum vacuum thrust of a burn, `t_min_vac` if unset
+    /// [N]
+    pub t_min_vac: Option<f64>,
+
+    /// Maximum vacuum thrust of a burn, `t_max_vac` if unset
+    /// [N]
+    pub t_max_vac: Option<f64>,
+
+    /// Whether the glide slope cone at the landing target applies to the phase
+    #[builder(default)]
+    pub glide_slope: bool,
+
+    /// Constraints of this phase only, in addition to those of the settings
+    #[builder(default)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub constraints: Vec<Arc<dyn TrajectoryConstraint>>,
+}
+
+impl Phase {
+    /// Thrust limits of the phase [N]
+    pub fn thrust_bounds(&self, params: &SimulationParams) -> (f64, f64) {
+        (
+            self.t_min_vac.unwrap_or(params.t_min_vac),
+            self.t_max_vac.unwrap_or(params.t_max_vac),
+        )
+    }
+
+    /// Simulation parameters the phase is solved with. A burn has its own
+    /// thrust limits, and without the engine running there is no mass flow
+    /// from back pressure.
+    pub(super) fn simulation_params(&self, params: &SimulationParams) -> SimulationParams {
+        let (t_min_vac, t_max_vac) = self.thrust_bounds(params);
+        match self.kind {
+            PhaseKind::Coast => SimulationParams {
+                p_amb: 0.0,
+                ..params.clone()
+            },
+            PhaseKind::Burn => SimulationParams {
+                t_min_vac,
+                t_max_vac,
+                ..params.clone()
+            },
+        }
+    }
+
+    /// Algorithm parameters the phase is solved with, on the grid of the
+    /// phase. Thrust constraints do not apply to a coast.
+    pub(super) fn algorithm_params(&self, algo: &AlgorithmParams) -> AlgorithmParams {
+        let mut disabled_constraints = algo.disabled_constraints.clone();
+        if !self.glide_slope {
+            disabled_constraints.insert(ConstraintGroup::GlideSlope);
+        }
+        if self.kind == PhaseKind::Coast {
+            disabled_constraints.extend([
+                ConstraintGroup::ThrustMagnitude,
+                ConstraintGroup::ThrustBounds,
+                ConstraintGroup::Tilt,
+                ConstraintGroup::ThrustRate,
+            ]);
+        }
+        AlgorithmParams {
+            N: self.N,
+            dt: self.tf_guess / (self.N - 1) as f64,
+            tf_guess: self.tf_guess,
+            disabled_constraints,
+            ..algo.clone()
+        }
+    }
+}
+
+/// The trajectory of a single phase.
+#[derive(Debug, Clone)]
+pub struct PhaseSolution {
+    /// Coast or burn
+    pub kind: PhaseKind,
+    /// Time of the first node since the start of the first phase [s]
+    pub start_time: f64,
+    /// Nodes of the phase, with the phase time step
+    pub trajectory: APDGSolution,
+}
+
+/// A solved sequence of phases.
+#[derive(Debug, Clone)]
+pub struct MultiPhaseSolution {
+    phases: Vec<PhaseSolution>,
+}
+
+impl MultiPhaseSolution {
+    /// Individual phases, in order.
+    pub fn phases(&self) -> &[PhaseSolution] {
+        &self.phases
+    }
+
+    /// Total time of flight [s]
+    pub fn duration(&self) -> f64 {
+        self.phases
+            .last()
+            .map_or(0.0, |p| p.start_time + p.trajectory.duration())
+    }
+
+    /// Start time of the first burn [s]
+    pub fn ignition_time(&self) -> Option<f64> {
+        self.phases
+            .iter()
+            .find(|p| p.kind == PhaseKind::Burn)
+            .map(|p| p.start_time)
+    }
+
+    /// Mass at the last node [kg]
+    pub fn final_mass(&self) -> f64 {
+        self.phases
+            .last()
+            .and_then(|p| p.trajectory.steps().last())
+            .map_or(0.0, |s| s.m)
+    }
+
+    /// Relaxation gaps at every node of every phase.
+    ///
+    /// Without thrust, the gaps of a coast node are relative to the maximum
+    /// thrust and to gravity rather than to `Γ[k]` and `Γ[k] / m[k]`.
+    pub fn tightness(&self, params: &SimulationParams) -> TightnessReport {
+        let nodes = self
+            .phases
+            .iter()
+            .flat_map(|p| {
+                let mut report = p.trajectory.tightness();
+                if p.kind == PhaseKind::Coast {
+                    for node in &mut report.nodes {
+                        node.thrust_gap_relative = relative(node.thrust_gap, params.t_max_vac);
+                        node.aR_relative = relative(node.aR, params.g_vec.norm());
+                    }
+                }
+                report.nodes
+            })
+            .collect();
+        TightnessReport { nodes }
+    }
+
+    /// Phases of the trajectory, each starting where the previous one ends.
+    fn new(phases: &[Phase], trajectory: Vec<APDGSolution>) -> Self {
+        let mut start_time = 0.0;
+        let phases = phases
+            .iter()
+            .zip(trajectory)
+            .map(|(phase, trajectory)| {
+                let solution = PhaseSolution {
+                    kind: phase.kind,
+                    start_time,
+                    trajectory,
+                };
+                start_time += solution.trajectory.duration();
+                solution
+            })
+            .collect();
+        MultiPhaseSolution { phases }
+    }
+}
+
+impl APDGProblemSolver {
+    /// Solve a multi-phase trajectory by successive convexification.
+    ///
+    /// The boundary conditions, vehicle and SC settings come from `settings`,
+    /// `AlgorithmParams::N`, `dt` and `tf_guess` are replaced by the phases.
+    pub fn solve_phases(
+        &self,
+        settings: &Settings,
+        phases: &[Phase],
+    ) -> Result<(MultiPhaseSolution, ConvergenceHistory), Error> {
+        settings.validate()?;
+        validate_phases(settings, phases)?;
+        _solve_phases(settings, phases, &SolveControl::default())
+    }
+}
+
+pub(super) fn _solve_phases(
+    settings: &Settings,
+    phases: &[Phase],
+    control: &SolveControl,
+) -> Result<(MultiPhaseSolution, ConvergenceHistory), Error> {
+    match control.should_stop() {
+        Some(Stop::Cancelled) => return Err(Error::Cancelled),
+        Some(Stop::Deadline) => return Err(Error::DeadlineExceeded),
+        None => {}
+    }
+
+    let sim = settings.simulation_settings();
+    let algo = settings.solver_settings();
+    let initial = MultiPhaseSolution::new(phases, initial_reference(settings, phases)?);
+    for phase in initial.phases() {
+        validation::check_finite(&phase.trajectory, "initial guess")?;
+    }
+    control.report(SolveProgress::InitialGuess);
+
+    successive_convexification(settings, control, initial, |prev_trajectory| {
+        let problem = sucessive::phases::MultiPhaseProblem::new(
+            sim.clone(),
+            algo.clone(),
+            phases.to_vec(),
+            prev_trajectory.segments().cloned().collect(),
+            settings.constraints().to_vec(),
+        );
+        let next = problem.solve()?;
+        for trajectory in &next {
+            validation::check_finite(trajectory, "multi-phase problem")?;
+        }
+        Ok(MultiPhaseSolution::new(phases, next))
+    })
+}
+
+impl Reference for MultiPhaseSolution {
+    fn segments(&self) -> impl Iterator<Item = &APDGSolution> {
+        self.phases.iter().map(|p| &p.trajectory)
+    }
+
+    fn tightness(&self, params: &SimulationParams) -> TightnessReport {
+        MultiPhaseSolution::tightness(self, params)
+    }
+}
+
+/// Check the phases against the settings they are solved with.
+pub(super) fn validate_phases(settings: &Settings, phases: &[Phase]) -> Result<(), Error> {
+    let mut errors = ValidationErrors::default();
+    if phases.is_empty() {
+        errors.push("settings", "phases", "at least one phase is needed");
+    }
+    if settings.simulation_settings().mission != Mission::Descent {
+        errors.push(
+            "simulation_settings",
+            "mission",
+            "multi-phase trajectories only support the descent mission",
+        );
+    }
+    if !settings.solver_settings().cost_terms.is_empty() {
+        errors.push(
+            "solver_settings",
+            "cost_terms",
+            "multi-phase trajectories do not support cost terms",
+        );
+    }
+
+    for (i, phase) in phases.iter().enumerate() {
+        let section = format!("phases[{i}]");
+        if phase.N < 2 {
+            errors.push(
+                &section,
+                "N",
+                format!("must be at least 2, got {}", phase.N),
+            );
+        }
+        if !(phase.tf_guess.is_finite() && phase.tf_guess > 0.0) {
+            errors.push(
+                &section,
+                "tf_guess",
+                format!("must be positive and finite, got {}", phase.tf_guess),
+            );
+        }
+        for (field, value) in [
+            ("min_duration", phase.min_duration),
+            ("max_duration", phase.max_duration),
+        ] {
+            if value.is_some_and(|d| !(d.is_finite() && d >= 0.0)) {
+                errors.push(
+                    &section,
+                    field,
+                    format!("must be non-negative and finite, got {value:?}"),
+                );
+            }
+        }
+        if let (Some(min), Some(max)) = (phase.min_duration, phase.max_duration) {
+            if min > max {
+                errors.push(
+                    &section,
+                    "min_duration",
+                    format!("must not exceed max_duration ({max}), got {min}"),
+                );
+            }
+        }
+        let (t_min, t_max) = phase.thrust_bounds(settings.simulation_settings());
+        if !(0.0 <= t_min && t_min < t_max) {
+            errors.push(
+                &section,
+                "t_min_vac",
+                format!("thrust limits must satisfy 0 <= min < max, got [{t_min}, {t_max}]"),
+            );
+        }
+    }
+
+    if errors.errors().is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidSettings(errors))
+    }
+}
+
+/// First reference of every phase, the initial guess over the total guessed
+/// duration split between the phases. A provided guess keeps its own
+/// duration, split in proportion to the guessed phase durations.
+fn initial_reference(settings: &Settings, phases: &[Phase]) -> Result<Vec<APDGSolution>, Error> {
+    let total: f64 = phases.iter().map(|p| p.tf_guess).sum();
+    let N: usize = phases.iter().map(|p| p.N).sum();
+    let fine = AlgorithmParams {
+        N,
+        dt: total / (N - 1) as f64,
+        tf_guess: total,
+        ..settings.solver_settings().clone()
+    };
+    let guess = initial_guess(settings, &fine)?;
+    let scale = guess.duration() / total;
+
+    let mut start_time = 0.0;
+    Ok(phases
+        .iter()
+        .map(|phase| {
+            let t0 = start_time;
+            let dt = scale * phase.tf_guess / (phase.N - 1) as f64;
+            start_time += scale * phase.tf_guess;
+
+            let steps = (0..phase.N)
+                .filter_map(|k| {
+                    let mut step = guess.sample(t0 + k as f64 * dt)?;
+                    if phase.kind == PhaseKind::Coast {
+                        // Keep the kinematics, the relaxation provides the thrust
+                        step.aR += step.t / step.m;
+                        step.t = Vector3::zeros();
+                        step.gamma = 0.0;
+                    }
+                    Some(step)
+                })
+                .collect();
+            APDGSolution {
+                steps,
+                dt,
+                duals: None,
+                constraint_groups: None,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::apdg::models::InitialGuess;
+
+    #[test]
+    fn test_initial_reference_is_continuous_across_phases() {
+        let settings = Settings::builder()
+            .initial_guess(InitialGuess::Polynomial)
+            .build();
+        let phases = [
+            Phase::builder()
+                .kind(PhaseKind::Coast)
+                .N(5)
+                .tf_guess(4.0)
+                .build(),
+            Phase::builder()
+                .kind(PhaseKind::Burn)
+                .N(12)
+                .tf_guess(11.0)
+                .glide_slope(true)
+                .build(),
+        ];
+
+        let reference = initial_reference(&settings, &phases).unwrap();
+
+        assert_eq!(reference[0].num_steps(), 5);
+        assert_eq!(reference[1].num_steps(), 12);
+        assert!(reference[0].steps().iter().all(|s| s.gamma == 0.0));
+        let (end, start) = (&reference[0].steps()[4], &reference[1].steps()[0]);
+        assert!((end.r - start.r).norm() < 1e-9);
+        assert!((reference[1].duration() - 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_coast_then_burn_moves_ignition() {
+        let settings = Settings::builder()
+            .simulation_settings(
+                SimulationParams::builder()
+                    .r0(Vector3::new(500.0, 100.0, 0.0))
+                    .v0(Vector3::new(-30.0, 0.0, 0.0))
+                    .build(),
+            )
+            .solver_settings(
+                AlgorithmParams::builder()
+                    .relaxation_tolerance(1e-4)
+                    .build(),
+            )
+            .build();
+        let phases = [
+            Phase::builder()
+                .kind(PhaseKind::Coast)
+                .N(5)
+                .tf_guess(2.0)
+                .build(),
+            Phase::builder()
+                .kind(PhaseKind::Burn)
+                .N(15)
+                .tf_guess(13.0)
+                .glide_slope(true)
+                .build(),
+        ];
+
+        let (solution, _) = APDGProblemSolver::default()
+            .solve_phases(&settings, &phases)
+            .unwrap();
+        let [coast, burn] = solution.phases() else {
+            panic!("expected two phases");
+        };
+
+        // Falling for longer before lighting the engine saves propellant
+        let ignition = solution.ignition_time().unwrap();
+        assert!(ignition > 2.5, "ignition stayed at {ignition} s");
+        assert!((ignition - coast.trajectory.duration()).abs() < 1e-9);
+
+        // No thrust or mass flow during the coast
+        let m_0 = settings.simulation_settings().m_0;
+        for step in coast.trajectory.steps() {
+            assert!(step.gamma.abs() < 1e-6 && step.t.norm() < 1e-6);
+            assert!((step.m - m_0).abs() < 1e-6);
+        }
+
+        // The state is continuous across the phase boundary
+        let (end, start) = (
+            coast.trajectory.steps().last().unwrap(),
+            &burn.trajectory.steps()[0],
+        );
+        assert!((end.r - start.r).norm() < 1e-6);
+        assert!((end.v - start.v).norm() < 1e-6);
+        assert!((end.m - start.m).abs() < 1e-6);
+
+        assert!(burn.trajectory.steps().last().unwrap().r.norm() < 1e-5);
+        assert!(burn.trajectory.duals().is_some());
+    }
+}
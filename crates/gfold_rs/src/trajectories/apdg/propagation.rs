@@ -0,0 +1,446 @@
+//! Nonlinear propagation of an APDG thrust profile.
+//!
+//! The convex subproblems only enforce a discretised (and, in Problem 5,
+//! linearised) model of the vehicle. Here the thrust profile of a solution is
+//! replayed through the full equations of motion so the discretisation and
+//! linearisation error can be measured:
+//!
+//! ```text
+//! r' = v
+//! v' = (T + D) / m + g,        D = -1/2 * rho * S_D * C_D * ||v|| * v
+//! m' = -(alpha * ||T|| + m_dot_bp)
+//! ```
+//!
+//! The relaxation term `aR` is deliberately left out, it is not a physical
+//! acceleration.
+
+use nalgebra::{SVector, Vector3};
+
+use super::{models::SimulationParams, APDGSolution};
+
+/// Propagated state `[r, v, m]`
+type State = SVector<f64, 7>;
+
+/// Integration scheme used to propagate a solution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    /// Fixed step fourth-order Runge-Kutta with `substeps` steps per node interval.
+    RK4 {
+        /// Number of integration steps between two nodes
+        substeps: usize,
+    },
+    /// Adaptive Dormand-Prince 5(4) scheme.
+    RK45 {
+        /// Relative error tolerance per step
+        rel_tol: f64,
+        /// Absolute error tolerance per step
+        abs_tol: f64,
+    },
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::RK45 {
+            rel_tol: 1e-9,
+            abs_tol: 1e-9,
+        }
+    }
+}
+
+/// Propagated state and its drift from the discrete solution at a single node.
+#[derive(Debug, Clone)]
+pub struct NodeDefect {
+    /// Time since the start of the trajectory [s]
+    pub t: f64,
+    /// Propagated position [m]
+    pub r: Vector3<f64>,
+    /// Propagated velocity [m/s]
+    pub v: Vector3<f64>,
+    /// Propagated mass [kg]
+    pub m: f64,
+    /// Position drift ||r_prop - r_sol|| [m]
+    pub pos_drift: f64,
+    /// Velocity drift ||v_prop - v_sol|| [m/s]
+    pub vel_drift: f64,
+    /// Mass drift m_prop - m_sol [kg]
+    pub mass_drift: f64,
+}
+
+/// Outcome of propagating a solution through the nonlinear dynamics.
+#[derive(Debug, Clone)]
+pub struct PropagationReport {
+    /// Propagated state and drift at every node
+    pub nodes: Vec<NodeDefect>,
+    /// Distance between the propagated final position and the landing
+    /// target [m]
+    pub touchdown_miss: f64,
+    /// Difference between the propagated final velocity and the target
+    /// landing velocity [m/s]
+    pub touchdown_velocity_error: f64,
+}
+
+impl PropagationReport {
+    /// Report for a propagation that could not reach touchdown
+    fn diverged(nodes: Vec<NodeDefect>) -> Self {
+        PropagationReport {
+            nodes,
+            touchdown_miss: f64::INFINITY,
+            touchdown_velocity_error: f64::INFINITY,
+        }
+    }
+
+    /// Largest position drift over all nodes [m]
+    pub fn max_pos_drift(&self) -> f64 {
+        self.nodes.iter().map(|n| n.pos_drift).fold(0.0, f64::max)
+    }
+
+    /// Largest velocity drift over all nodes [m/s]
+    pub fn max_vel_drift(&self) -> f64 {
+        self.nodes.iter().map(|n| n.vel_drift).fold(0.0, f64::max)
+    }
+
+    /// Largest absolute mass drift over all nodes [kg]
+    pub fn max_mass_drift(&self) -> f64 {
+        self.nodes
+            .iter()
+            .map(|n| n.mass_drift.abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// Propagated final mass [kg]
+    pub fn final_mass(&self) -> Option<f64> {
+        self.nodes.last().map(|n| n.m)
+    }
+}
+
+impl APDGSolution {
+    /// Replay the thrust profile through the full nonlinear dynamics.
+    ///
+    /// Thrust is held first-order-hold between nodes, matching the trapezoidal
+    /// discretisation used by the solver. The propagation starts from the
+    /// first node of the solution. A solution without nodes gives a report
+    /// without nodes and an infinite touchdown miss. If the state diverges to
+    /// a non-finite value the propagation stops there, the report keeps the
+    /// nodes reached so far and the touchdown miss is infinite.
+    pub fn propagate(
+        &self,
+        params: &SimulationParams,
+        integrator: Integrator,
+    ) -> PropagationReport {
+        let steps = self.steps();
+        let dt = self.dt();
+        if steps.is_empty() {
+            return PropagationReport::diverged(Vec::new());
+        }
+
+        let mut x = pack(&steps[0].r, &steps[0].v, steps[0].m);
+        let mut nodes = Vec::with_capacity(steps.len());
+        nodes.push(node_defect(0.0, &x, &steps[0].r, &steps[0].v, steps[0].m));
+
+        for k in 0..steps.len() - 1 {
+            let (t_k, t_k1) = (steps[k].t, steps[k + 1].t);
+            // Linear thrust profile over the interval, tau in [0, dt]
+            let thrust = |tau: f64| t_k + (t_k1 - t_k) * (tau / dt);
+
+            let propagated = match integrator {
+                Integrator::RK4 { substeps } => {
+                    Some(integrate_rk4(params, &thrust, x, dt, substeps.max(1)))
+                }
+                Integrator::RK45 { rel_tol, abs_tol } => {
+                    integrate_rk45(params, &thrust, x, dt, rel_tol, abs_tol)
+                }
+            };
+            x = match propagated.filter(is_finite) {
+                Some(x) => x,
+                None => return PropagationReport::diverged(nodes),
+            };
+
+            let next = &steps[k + 1];
+            nodes.push(node_defect(
+                (k + 1) as f64 * dt,
+                &x,
+                &next.r,
+                &next.v,
+                next.m,
+            ));
+        }
+
+        let (r_f, v_f, _) = unpack(&x);
+        let (r_target, v_target) = params.target.state(params, self.duration());
+        PropagationReport {
+            nodes,
+            touchdown_miss: (r_f - r_target).norm(),
+            touchdown_velocity_error: (v_f - v_target).norm(),
+        }
+    }
+}
+
+fn pack(r: &Vector3<f64>, v: &Vector3<f64>, m: f64) -> State {
+    State::from_column_slice(&[r.x, r.y, r.z, v.x, v.y, v.z, m])
+}
+
+fn unpack(x: &State) -> (Vector3<f64>, Vector3<f64>, f64) {
+    (
+        Vector3::new(x[0], x[1], x[2]),
+        Vector3::new(x[3], x[4], x[5]),
+        x[6],
+    )
+}
+
+fn is_finite(x: &State) -> bool {
+    x.iter().all(|v| v.is_finite())
+}
+
+fn node_defect(t: f64, x: &State, r: &Vector3<f64>, v: &Vector3<f64>, m: f64) -> NodeDefect {
+    let (r_p, v_p, m_p) = unpack(x);
+    NodeDefect {
+        t,
+        r: r_p,
+        v: v_p,
+        m: m_p,
+        pos_drift: (r_p - r).norm(),
+        vel_drift: (v_p - v).norm(),
+        mass_drift: m_p - m,
+    }
+}
+
+/// Time derivative of the full nonlinear state
+fn derivative(params: &SimulationParams, thrust: &Vector3<f64>, x: &State) -> State {
+    let alpha = 1.0 / (params.i_sp * params.g_0);
+    let m_dot_bp = (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0);
+    let drag_coeff = 0.5 * params.rho * params.s_d * params.c_d;
+
+    let (_, v, m) = unpack(x);
+    let drag = -drag_coeff * v.norm() * v;
+    let a = (thrust + drag) / m + params.g_vec;
+    let m_dot = -(alpha * thrust.norm() + m_dot_bp);
+
+    State::from_column_slice(&[v.x, v.y, v.z, a.x, a.y, a.z, m_dot])
+}
+
+/// Integrate over `[0, span]` with fixed RK4 steps
+fn integrate_rk4(
+    params: &SimulationParams,
+    thrust: &impl Fn(f64) -> Vector3<f64>,
+    mut x: State,
+    span: f64,
+    substeps: usize,
+) -> State {
+    let h = span / substeps as f64;
+    for i in 0..substeps {
+        let tau = i as f64 * h;
+        let k1 = derivative(params, &thrust(tau), &x);
+        let k2 = derivative(params, &thrust(tau + h / 2.0), &(x + k1 * (h / 2.0)));
+        let k3 = derivative(params, &thrust(tau + h / 2.0), &(x + k2 * (h / 2.0)));
+        let k4 = derivative(params, &thrust(tau + h), &(x + k3 * h));
+        x += (k1 + 2.0 * k2 + 2.0 * k3 + k4) * (h / 6.0);
+    }
+    x
+}
+
+/// Integrate over `[0, span]` with adaptive Dormand-Prince 5(4) steps,
+/// `None` if the state or the error estimate stops being finite
+fn integrate_rk45(
+    params: &SimulationParams,
+    thrust: &impl Fn(f64) -> Vector3<f64>,
+    mut x: State,
+    span: f64,
+    rel_tol: f64,
+    abs_tol: f64,
+) -> Option<State> {
+    // Dormand-Prince tableau
+    const C: [f64; 6] = [1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+    const A: [[f64; 6]; 6] = [
+        [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+        [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+        [
+            19372.0 / 6561.0,
+            -25360.0 / 2187.0,
+            64448.0 / 6561.0,
+            -212.0 / 729.0,
+            0.0,
+            0.0,
+        ],
+        [
+            9017.0 / 3168.0,
+            -355.0 / 33.0,
+            46732.0 / 5247.0,
+            49.0 / 176.0,
+            -5103.0 / 18656.0,
+            0.0,
+        ],
+        [
+            35.0 / 384.0,
+            0.0,
+            500.0 / 1113.0,
+            125.0 / 192.0,
+            -2187.0 / 6784.0,
+            11.0 / 84.0,
+        ],
+    ];
+    // 5th order weights are the last row of A, these are the 4th order ones
+    const B4: [f64; 7] = [
+        5179.0 / 57600.0,
+        0.0,
+        7571.0 / 16695.0,
+        393.0 / 640.0,
+        -92097.0 / 339200.0,
+        187.0 / 2100.0,
+        1.0 / 40.0,
+    ];
+    const MIN_STEP_FRACTION: f64 = 1e-9;
+
+    let mut tau = 0.0;
+    let mut h = span / 4.0;
+    while tau < span {
+        h = h.min(span - tau);
+
+        let mut k = [State::zeros(); 7];
+        k[0] = derivative(params, &thrust(tau), &x);
+        for s in 0..6 {
+            let mut x_stage = x;
+            for (j, k_j) in k.iter().enumerate().take(s + 1) {
+                x_stage += k_j * (A[s][j] * h);
+            }
+            k[s + 1] = derivative(params, &thrust(tau + C[s] * h), &x_stage);
+        }
+
+        // The 7th stage is evaluated at the 5th order solution (FSAL)
+        let mut x5 = x;
+        for (j, k_j) in k.iter().enumerate().take(6) {
+            x5 += k_j * (A[5][j] * h);
+        }
+        let mut x4 = x;
+        for (j, k_j) in k.iter().enumerate() {
+            x4 += k_j * (B4[j] * h);
+        }
+
+        // Scaled RMS error norm
+        let err = (x5 - x4)
+            .iter()
+            .zip(x.iter().zip(x5.iter()))
+            .map(|(e, (a, b))| {
+                let scale = abs_tol + rel_tol * a.abs().max(b.abs());
+                (e / scale).powi(2)
+            })
+            .sum::<f64>()
+            / 7.0;
+        let err = err.sqrt();
+        // Shrinking the step cannot recover from a non-finite state
+        if !err.is_finite() || !is_finite(&x5) {
+            return None;
+        }
+
+        if err <= 1.0 || h <= span * MIN_STEP_FRACTION {
+            tau += h;
+            x = x5;
+        }
+
+        let factor = if err == 0.0 {
+            5.0
+        } else {
+            (0.9 * err.powf(-0.2)).clamp(0.2, 5.0)
+        };
+        h = (h * factor).max(span * MIN_STEP_FRACTION);
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::APDGSolutionTimeStep;
+
+    /// Unpowered, drag free fall has a closed form solution that both schemes
+    /// should reproduce.
+    fn free_fall() -> (SimulationParams, APDGSolution) {
+        let params = SimulationParams::builder()
+            .c_d(0.0)
+            .p_amb(0.0)
+            .rf(Vector3::new(0.0, 0.0, 0.0))
+            .build();
+
+        let r0 = Vector3::new(500.0, 10.0, -20.0);
+        let v0 = Vector3::new(5.0, -3.0, 2.0);
+        let dt = 0.5;
+        let steps = (0..10)
+            .map(|k| {
+                let t = k as f64 * dt;
+                APDGSolutionTimeStep::builder()
+                    .r(r0 + v0 * t + 0.5 * params.g_vec * t * t)
+                    .v(v0 + params.g_vec * t)
+                    .a(params.g_vec)
+                    .m(params.m_0)
+                    .t(Vector3::zeros())
+                    .gamma(0.0)
+                    .aR(Vector3::zeros())
+                    .build()
+            })
+            .collect();
+
+        (params, APDGSolution::builder().steps(steps).dt(dt).build())
+    }
+
+    #[test]
+    fn test_rk4_free_fall_has_no_drift() {
+        let (params, solution) = free_fall();
+        let report = solution.propagate(&params, Integrator::RK4 { substeps: 4 });
+
+        assert_eq!(report.nodes.len(), solution.num_steps());
+        assert!(report.max_pos_drift() < 1e-9);
+        assert!(report.max_vel_drift() < 1e-9);
+        assert!(report.max_mass_drift() < 1e-9);
+
+        let last = solution.steps().last().unwrap();
+        assert!((report.touchdown_miss - last.r.norm()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_solution_is_not_propagated() {
+        let params = SimulationParams::default();
+        let solution = APDGSolution::builder().steps(Vec::new()).dt(1.0).build();
+        let report = solution.propagate(&params, Integrator::RK4 { substeps: 4 });
+
+        assert!(report.nodes.is_empty());
+        assert_eq!(report.final_mass(), None);
+        assert_eq!(report.touchdown_miss, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_non_finite_thrust_stops_the_propagation() {
+        let (params, mut solution) = free_fall();
+        solution.steps[3].t = Vector3::new(f64::NAN, 0.0, 0.0);
+
+        for integrator in [Integrator::RK4 { substeps: 4 }, Integrator::default()] {
+            let report = solution.propagate(&params, integrator);
+
+            // The nodes up to the bad thrust are kept
+            assert_eq!(report.nodes.len(), 3);
+            assert_eq!(report.touchdown_miss, f64::INFINITY);
+            assert_eq!(report.touchdown_velocity_error, f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn test_rk45_matches_rk4() {
+        let (params, mut solution) = free_fall();
+        // Add a constant thrust and drag so the dynamics are nonlinear
+        let params = SimulationParams { c_d: 1.0, ..params };
+        for step in solution.steps.iter_mut() {
+            step.t = Vector3::new(150_000.0, 1_000.0, 0.0);
+            step.gamma = step.t.norm();
+        }
+
+        let rk4 = solution.propagate(&params, Integrator::RK4 { substeps: 200 });
+        let rk45 = solution.propagate(&params, Integrator::default());
+
+        for (a, b) in rk4.nodes.iter().zip(&rk45.nodes) {
+            assert!((a.r - b.r).norm() < 1e-6);
+            assert!((a.v - b.v).norm() < 1e-6);
+            assert!((a.m - b.m).abs() < 1e-6);
+        }
+        // Mass must decrease under thrust
+        assert!(rk45.final_mass().unwrap() < params.m_0);
+    }
+}
@@ -0,0 +1,213 @@
+//! Receding-horizon re-planning.
+//!
+//! A [`Replanner`] keeps the plan currently being flown. On every call the
+//! measured state replaces the initial conditions, the horizon shrinks by the
+//! time flown since the plan was made and the previous solution, shifted to
+//! the new start time, replaces the Problem 4 initial guess. The SC loop is
+//! capped at a small iteration budget so a new plan is available at a fixed
+//! rate, converged or not.
+//!
+//! The replanner also keeps the time since the start of the first plan, and
+//! every plan sees the landing target as it will be from then on, so a
+//! moving target keeps moving between re-plans.
+
+use bon::Builder;
+
+use super::{models::InitialGuess, APDGProblemSolver, APDGSolution, Error, Settings};
+use crate::guidance::VehicleState;
+use crate::trajectories::ConvergenceHistory;
+
+/// Stateful re-planner around [`APDGProblemSolver`].
+#[derive(Debug, Clone, Builder)]
+pub struct Replanner {
+    /// Vehicle, limits and solver settings. The initial conditions are
+    /// replaced by the measured state on every call.
+    settings: Settings,
+
+    /// Maximum number of SC iterations per re-plan, the first plan uses
+    /// `n_sc` from the settings
+    #[builder(default = 2)]
+    iteration_budget: usize,
+
+    /// Shortest horizon worth re-planning over
+    /// [s]
+    #[builder(default = 1.0)]
+    min_horizon: f64,
+
+    #[builder(skip)]
+    plan: Option<APDGSolution>,
+
+    /// Start of the current plan since the start of the first plan [s]
+    #[builder(skip)]
+    start_time: f64,
+}
+
+impl Replanner {
+    /// The plan currently being flown, if any.
+    pub fn plan(&self) -> Option<&APDGSolution> {
+        self.plan.as_ref()
+    }
+
+    /// Start of the current plan since the start of the first plan [s]
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
+    /// Forget the current plan, the next call plans from scratch.
+    pub fn reset(&mut self) {
+        self.plan = None;
+        self.start_time = 0.0;
+    }
+
+    /// Plan from the measured `state`.
+    ///
+    /// `elapsed` is the time since the start of the current plan [s]. Without
+    /// a current plan the full problem is solved with the configured initial
+    /// guess and `elapsed` is ignored. The landing target is advanced to the
+    /// start of the new plan.
+    pub fn replan(
+        &mut self,
+        state: &VehicleState,
+        elapsed: f64,
+    ) -> Result<(APDGSolution, ConvergenceHistory), Error> {
+        let mut sim = self.settings.simulation_settings().clone();
+        let mut algo = self.settings.solver_settings().clone();
+        sim.r0 = state.r;
+        sim.v0 = state.v;
+        sim.m_0 = state.m;
+
+        let start_time = match self.plan {
+            None => 0.0,
+            Some(_) => self.start_time + elapsed,
+        };
+        sim.advance_target(start_time);
+
+        let initial_guess = match &self.plan {
+            None => self.settings.initial_guess().clone(),
+            Some(previous) => {
+                let horizon = previous.duration() - elapsed;
+                if horizon < self.min_horizon {
+                    return Err(Error::HorizonExpired);
+                }
+
+                let dt = horizon / (algo.N - 1) as f64;
+                let mut steps: Vec<_> = (0..algo.N)
+                    .map(|k| previous.sample(elapsed + k as f64 * dt))
+                    .collect::<Option<_>>()
+                    .ok_or(Error::HorizonExpired)?;
+
+                // The thrust being flown right now is the initial thrust
+                let current = &steps[0];
+                sim.gamma_0_vac = current.gamma.clamp(sim.t_min_vac, sim.t_max_vac);
+                if let Some(n_hat) = current.t.try_normalize(f64::EPSILON) {
+                    sim.n_hat0 = n_hat;
+                }
+                steps[0].r = state.r;
+                steps[0].v = state.v;
+                steps[0].m = state.m;
+
+                algo.tf_guess = horizon;
+                algo.dt = dt;
+                algo.n_sc = self.iteration_budget;
+                InitialGuess::Provided(Box::new(APDGSolution {
+                    steps,
+                    dt,
+                    duals: None,
+                    constraint_groups: None,
+                }))
+            }
+        };
+
+        let settings = Settings::builder()
+            .simulation_settings(sim)
+            .solver_settings(algo)
+            .initial_guess(initial_guess)
+            .constraints(self.settings.constraints().to_vec())
+            .build();
+        let (solution, history) = APDGProblemSolver::default().solve(&settings)?;
+
+        self.plan = Some(solution.clone());
+        self.start_time = start_time;
+        Ok((solution, history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::{
+        apdg::{models::AlgorithmParams, target::LandingTarget},
+        APDGSolutionTimeStep, SimulationParams,
+    };
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_expired_horizon_is_rejected() {
+        let step = APDGSolutionTimeStep::builder()
+            .r(Vector3::new(100.0, 0.0, 0.0))
+            .v(Vector3::new(-10.0, 0.0, 0.0))
+            .a(Vector3::zeros())
+            .m(12_000.0)
+            .t(Vector3::new(150_000.0, 0.0, 0.0))
+            .gamma(150_000.0)
+            .aR(Vector3::zeros())
+            .build();
+        let previous = APDGSolution::builder()
+            .steps(vec![step; 11])
+            .dt(1.0)
+            .build();
+
+        let mut replanner = Replanner::builder().settings(Settings::default()).build();
+        replanner.plan = Some(previous);
+
+        let state = VehicleState {
+            r: Vector3::new(5.0, 0.0, 0.0),
+            v: Vector3::new(-1.0, 0.0, 0.0),
+            m: 11_000.0,
+        };
+        // 10 s plan with 9.5 s flown leaves less than the minimum horizon
+        assert!(matches!(
+            replanner.replan(&state, 9.5),
+            Err(Error::HorizonExpired)
+        ));
+        assert!(replanner.plan().is_some());
+    }
+
+    #[test]
+    fn test_moving_target_keeps_moving_between_replans() {
+        let velocity = Vector3::new(0.0, 2.0, 0.0);
+        let sim = SimulationParams::builder()
+            .r0(Vector3::new(500.0, 100.0, 0.0))
+            .v0(Vector3::new(-30.0, 0.0, 0.0))
+            .target(LandingTarget::ConstantVelocity { velocity })
+            .build();
+        let settings = Settings::builder()
+            .simulation_settings(sim.clone())
+            .solver_settings(AlgorithmParams::builder().N(20).n_sc(3).build())
+            .build();
+        let mut replanner = Replanner::builder().settings(settings).build();
+
+        let start = VehicleState {
+            r: sim.r0,
+            v: sim.v0,
+            m: sim.m_0,
+        };
+        let (first, _) = replanner.replan(&start, 0.0).unwrap();
+        let landing = first.steps().last().unwrap().r;
+        assert!((landing - (sim.rf + velocity * first.duration())).norm() < 1e-6);
+
+        // Fly 3 s of the plan and re-plan from where it says the vehicle is
+        let flown = first.sample(3.0).unwrap();
+        let state = VehicleState {
+            r: flown.r,
+            v: flown.v,
+            m: flown.m,
+        };
+        let (second, _) = replanner.replan(&state, 3.0).unwrap();
+
+        assert_eq!(replanner.start_time(), 3.0);
+        let landing = second.steps().last().unwrap().r;
+        let tf = 3.0 + second.duration();
+        assert!((landing - (sim.rf + velocity * tf)).norm() < 1e-6);
+    }
+}
@@ -0,0 +1,626 @@
+//! Robust planning with constraint tightening.
+//!
+//! A nominal plan touches the thrust, tilt and glide slope limits exactly, so
+//! any tracking error or model mismatch pushes the flown trajectory over them.
+//! Given bounded [`Uncertainty`] in the thrust magnitude, `i_sp`, drag and the
+//! initial state, [`APDGProblemSolver::solve_robust`] tightens the limits by
+//! the margins needed to absorb it and returns a [`RobustPlan`] with the
+//! nominal plan and the margins required and retained at every node.
+//!
+//! With [`MarginPolicy::Fixed`] the state errors are the given bounds at
+//! every node and only drag is corrected by feedback. With
+//! [`MarginPolicy::Tube`] the tracking errors form a tube around the plan:
+//! feedback with time constant `τ` decays the initial errors while the
+//! disturbance acceleration `w` from thrust and drag uncertainty feeds them,
+//!
+//! ```text
+//! e_v(t) = δv e^(-t/τ) + w τ (1 - e^(-t/τ))
+//! e_r(t) = δr e^(-t/τ) + w τ^2 (1 - e^(-t/τ))
+//! ```
+//!
+//! and the feedback needs an extra `e_v(t) / τ` of control acceleration.
+//! Drag is bounded at the highest speed of the reference trajectory, or of
+//! the initial and final speeds when there is none yet. A hop starts and ends
+//! at rest, so only the reference gives it a drag margin.
+//!
+//! The margins are applied through a [`TrajectoryConstraint`], so the nominal
+//! constraints stay in place:
+//!
+//! - thrust: the relative thrust error and the thrust needed for feedback are
+//!   kept from both thrust bounds,
+//! - tilt: the angle needed to point the feedback acceleration at the lowest
+//!   thrust is kept from `theta_max`,
+//! - glide slope: the cone is narrowed so that a ball of radius `e_r` around
+//!   the node fits inside, using the distance to the vertex of the reference
+//!   trajectory. It is therefore only applied in Problem 5, and close to the
+//!   vertex the cone collapses to the vertical before the full margin fits,
+//! - mass: the extra propellant burnt at the lowest `i_sp` is kept above
+//!   `m_dry`, `m[k] - m_dry >= δI_sp / (I_sp - δI_sp) (m_0 - m[k])`.
+//!
+//! The initial node is given and the final node is the glide slope vertex, so
+//! neither is tightened.
+
+use std::sync::Arc;
+
+use bon::Builder;
+use good_lp::{constraint, soc_constraint, Constraint, Expression};
+use uom::si::angle::radian;
+
+use super::{
+    constraints::{ConstraintContext, TrajectoryConstraint},
+    models::{ConstraintGroup, SimulationParams},
+    validation::ValidationErrors,
+    APDGProblemSolver, APDGSolution, Error, Settings,
+};
+use crate::trajectories::ConvergenceHistory;
+
+/// Bounds on the uncertainty a plan has to absorb.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Builder)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Uncertainty {
+    /// Thrust magnitude error relative to the commanded thrust
+    #[builder(default)]
+    pub thrust: f64,
+
+    /// Specific impulse shortfall
+    /// [s]
+    #[builder(default)]
+    pub i_sp: f64,
+
+    /// Drag coefficient error relative to `c_d`
+    #[builder(default)]
+    pub drag: f64,
+
+    /// Position error
+    /// [m]
+    #[builder(default)]
+    pub position: f64,
+
+    /// Velocity error
+    /// [m/s]
+    #[builder(default)]
+    pub velocity: f64,
+}
+
+/// How the margins evolve along the trajectory.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MarginPolicy {
+    /// The same margins at every node
+    #[default]
+    Fixed,
+    /// Margins from the tracking error tube of a feedback controller
+    Tube {
+        /// Time constant of the tracking feedback
+        /// [s]
+        time_constant: f64,
+    },
+}
+
+/// Margins to the constraint limits at a single node.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeMargins {
+    /// Distance of `Γ[k]` below `t_max_vac` [N]
+    pub thrust_upper: f64,
+    /// Distance of `Γ[k]` above `t_min_vac` [N]
+    pub thrust_lower: f64,
+    /// Angle of the thrust inside `theta_max` [deg]
+    pub tilt: f64,
+    /// Distance of `r[k]` inside the glide slope cone [m]
+    pub glide_slope: f64,
+    /// Mass above `m_dry` [kg]
+    pub mass: f64,
+}
+
+/// Position error and feedback acceleration at one instant.
+struct Tube {
+    position: f64,
+    correction: f64,
+}
+
+/// Highest speed along `reference`, and at the initial and final nodes [m/s]
+pub fn speed_bound(params: &SimulationParams, reference: Option<&APDGSolution>) -> f64 {
+    reference
+        .into_iter()
+        .flat_map(|r| r.steps().iter().map(|s| s.v.norm()))
+        .fold(params.v0.norm().max(params.vf.norm()), f64::max)
+}
+
+impl Uncertainty {
+    /// Bound on the acceleration disturbance from thrust and drag errors
+    /// [m/s^2].
+    ///
+    /// Drag is evaluated at `speed` [m/s], see [`speed_bound`].
+    pub fn disturbance(&self, params: &SimulationParams, speed: f64) -> f64 {
+        self.thrust * params.t_max_vac / params.m_dry + self.drag_disturbance(params, speed)
+    }
+
+    /// Bound on the acceleration disturbance from drag errors at `speed`
+    /// [m/s^2].
+    fn drag_disturbance(&self, params: &SimulationParams, speed: f64) -> f64 {
+        self.drag * 0.5 * params.rho * params.s_d * params.c_d * speed.powi(2) / params.m_dry
+    }
+
+    /// Margins required at time `t` [s] since ignition for a node of mass
+    /// `m` [kg], with drag bounded at `speed` [m/s].
+    pub fn margins(
+        &self,
+        params: &SimulationParams,
+        policy: MarginPolicy,
+        t: f64,
+        m: f64,
+        speed: f64,
+    ) -> NodeMargins {
+        let tube = self.tube(params, policy, t, speed);
+        let authority = params.m_0 * tube.correction;
+        let thrust_upper = self.thrust * params.t_max_vac + authority;
+        let thrust_lower = self.thrust * params.t_min_vac + authority;
+        let t_min = params.t_min_vac + thrust_lower;
+        let tilt = if t_min > 0.0 {
+            (authority / t_min).min(1.0).asin().to_degrees()
+        } else {
+            0.0
+        };
+        NodeMargins {
+            thrust_upper,
+            thrust_lower,
+            tilt,
+            glide_slope: tube.position,
+            mass: self.propellant_ratio(params) * (params.m_0 - m),
+        }
+    }
+
+    /// Extra propellant per unit of nominal propellant at the lowest `i_sp`.
+    fn propellant_ratio(&self, params: &SimulationParams) -> f64 {
+        self.i_sp / (params.i_sp - self.i_sp)
+    }
+
+    fn tube(&self, params: &SimulationParams, policy: MarginPolicy, t: f64, speed: f64) -> Tube {
+        let drag = self.drag_disturbance(params, speed);
+        match policy {
+            MarginPolicy::Fixed => Tube {
+                position: self.position,
+                correction: drag,
+            },
+            MarginPolicy::Tube { time_constant: tau } => {
+                let w = self.disturbance(params, speed);
+                let decay = (-t / tau).exp();
+                let velocity = self.velocity * decay + w * tau * (1.0 - decay);
+                Tube {
+                    position: self.position * decay + w * tau.powi(2) * (1.0 - decay),
+                    correction: drag + velocity / tau,
+                }
+            }
+        }
+    }
+
+    /// Margins enforced at node `k` of `N` with time step `dt` [s].
+    ///
+    /// Zero at the initial node, and zero glide slope margin at the final
+    /// node.
+    #[allow(clippy::too_many_arguments)]
+    fn node_margins(
+        &self,
+        params: &SimulationParams,
+        policy: MarginPolicy,
+        k: usize,
+        N: usize,
+        dt: f64,
+        m: f64,
+        speed: f64,
+    ) -> NodeMargins {
+        if k == 0 {
+            return NodeMargins::default();
+        }
+        let mut margins = self.margins(params, policy, k as f64 * dt, m, speed);
+        if k == N - 1 {
+            margins.glide_slope = 0.0;
+        }
+        margins
+    }
+}
+
+/// Margins actually left to the nominal limits by a solution.
+fn retained_margins(solution: &APDGSolution, params: &SimulationParams) -> Vec<NodeMargins> {
+    let up = params.e_hat_up;
+    let gamma_gs = params.gamma_gs().get::<radian>();
+    let n = solution.num_steps();
+    let tf = solution.duration();
+    solution
+        .steps()
+        .iter()
+        .enumerate()
+        .map(|(k, s)| {
+            let tilt = if s.t.norm() > 0.0 {
+                (up.dot(&s.t) / s.t.norm())
+                    .clamp(-1.0, 1.0)
+                    .acos()
+                    .to_degrees()
+            } else {
+                0.0
+            };
+            // Distance to the cone surface, sin(γ) e_u^T d - cos(γ) ||d_h||
+            let offset = s.r - params.mission.glide_slope_vertex(params, k, n, tf);
+            let height = up.dot(&offset);
+            let horizontal = (offset - up * height).norm();
+            NodeMargins {
+                thrust_upper: params.t_max_vac - s.gamma,
+                thrust_lower: s.gamma - params.t_min_vac,
+                tilt: params.theta_max - tilt,
+                glide_slope: gamma_gs.sin() * height - gamma_gs.cos() * horizontal,
+                mass: s.m - params.m_dry,
+            }
+        })
+        .collect()
+}
+
+/// A nominal plan with the margins it keeps to the constraint limits.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RobustPlan {
+    nominal: APDGSolution,
+    required: Vec<NodeMargins>,
+    retained: Vec<NodeMargins>,
+}
+
+impl RobustPlan {
+    /// The plan to fly.
+    pub fn nominal(&self) -> &APDGSolution {
+        &self.nominal
+    }
+
+    /// Margins the uncertainty calls for at every node.
+    pub fn required(&self) -> &[NodeMargins] {
+        &self.required
+    }
+
+    /// Margins the plan keeps to the nominal limits at every node.
+    pub fn retained(&self) -> &[NodeMargins] {
+        &self.retained
+    }
+
+    /// Nodes where any retained margin falls short of the required margin
+    /// by more than `tolerance` (relative).
+    pub fn deficient_nodes(&self, tolerance: f64) -> Vec<usize> {
+        let short = |retained: f64, required: f64| retained < (1.0 - tolerance) * required;
+        self.required
+            .iter()
+            .zip(&self.retained)
+            .enumerate()
+            .filter(|(_, (req, ret))| {
+                short(ret.thrust_upper, req.thrust_upper)
+                    || short(ret.thrust_lower, req.thrust_lower)
+                    || short(ret.tilt, req.tilt)
+                    || short(ret.glide_slope, req.glide_slope)
+                    || short(ret.mass, req.mass)
+            })
+            .map(|(k, _)| k)
+            .collect()
+    }
+}
+
+/// Tightened limits added to every convex subproblem.
+#[derive(Debug)]
+struct RobustConstraints {
+    uncertainty: Uncertainty,
+    policy: MarginPolicy,
+}
+
+impl TrajectoryConstraint for RobustConstraints {
+    fn name(&self) -> &str {
+        "robust margins"
+    }
+
+    fn constraints(&self, ctx: &ConstraintContext<'_>) -> Vec<Constraint> {
+        let params = ctx.params;
+        let N = ctx.nodes.len();
+        let dt = ctx.reference.map_or(ctx.algo.dt, APDGSolution::dt);
+        let up = params.e_hat_up;
+        let ratio = self.uncertainty.propellant_ratio(params);
+        let speed = speed_bound(params, ctx.reference);
+
+        let mut constraints = Vec::new();
+        for (k, node) in ctx.nodes.iter().enumerate().skip(1) {
+            // The mass margin is imposed through `ratio`
+            let margins =
+                self.uncertainty
+                    .node_margins(params, self.policy, k, N, dt, params.m_0, speed);
+
+            // t_min + margin <= Γ[k] <= t_max - margin
+            if ctx.algo.is_enabled(ConstraintGroup::ThrustBounds) {
+                constraints.push(constraint!(
+                    node.gamma <= params.t_max_vac - margins.thrust_upper
+                ));
+                constraints.push(constraint!(
+                    node.gamma >= params.t_min_vac + margins.thrust_lower
+                ));
+            }
+
+            // Γ[k] cos(theta_max - margin) <= e_u^T T[k]
+            if ctx.algo.is_enabled(ConstraintGroup::Tilt) {
+                let cos_th = (params.theta_max - margins.tilt)
+                    .max(0.0)
+                    .to_radians()
+                    .cos();
+                let up_dot_t = up.x * node.t[0] + up.y * node.t[1] + up.z * node.t[2];
+                constraints.push(constraint!(up_dot_t >= cos_th * node.gamma));
+            }
+
+            // (1 + ratio) m[k] >= m_dry + ratio m_0
+            if ctx.algo.is_enabled(ConstraintGroup::MassFloor) {
+                constraints.push(constraint!(
+                    (1.0 + ratio) * node.m >= params.m_dry + ratio * params.m_0
+                ));
+            }
+
+            // ||r[k] - c[k]|| cos(gamma_gs - margin) <= e_u^T (r[k] - c[k]),
+            // with the margin angle from the reference distance to c[k]
+            let glide_slope =
+                ctx.algo.is_enabled(ConstraintGroup::GlideSlope) && margins.glide_slope > 0.0;
+            if let Some(reference) = ctx.reference.filter(|_| glide_slope) {
+                let tf = (N - 1) as f64 * dt;
+                let vertex = params.mission.glide_slope_vertex(params, k, N, tf);
+                let distance = (reference.steps()[k].r - vertex).norm();
+                let gamma_gs = params.gamma_gs().get::<radian>();
+                let margin = if distance > 0.0 {
+                    (margins.glide_slope / distance).min(1.0).asin()
+                } else {
+                    gamma_gs
+                };
+                let sec_gs = 1.0 / (gamma_gs - margin).max(0.0).cos();
+                let offset: Vec<Expression> = (0..3).map(|i| node.r[i] - vertex[i]).collect();
+                let height =
+                    up.x * node.r[0] + up.y * node.r[1] + up.z * node.r[2] - up.dot(&vertex);
+                constraints.push(soc_constraint!(norm2_vec(offset) <= sec_gs * height));
+            }
+        }
+        constraints
+    }
+}
+
+impl APDGProblemSolver {
+    /// Solve with the limits tightened for `uncertainty`.
+    ///
+    /// The nominal plan is solved with the settings and the margins of
+    /// `policy` added as a user constraint, the margins are then evaluated
+    /// against the nominal limits.
+    pub fn solve_robust(
+        &mut self,
+        settings: &Settings,
+        uncertainty: &Uncertainty,
+        policy: MarginPolicy,
+    ) -> Result<(RobustPlan, ConvergenceHistory), Error> {
+        validate_uncertainty(settings.simulation_settings(), uncertainty, policy)?;
+
+        let mut constraints = settings.constraints().to_vec();
+        constraints.push(Arc::new(RobustConstraints {
+            uncertainty: *uncertainty,
+            policy,
+        }));
+        let robust = Settings {
+            constraints,
+            ..settings.clone()
+        };
+        let (nominal, history) = self.solve(&robust)?;
+
+        let params = settings.simulation_settings();
+        let n = nominal.num_steps();
+        let speed = speed_bound(params, Some(&nominal));
+        let required = nominal
+            .steps()
+            .iter()
+            .enumerate()
+            .map(|(k, s)| uncertainty.node_margins(params, policy, k, n, nominal.dt(), s.m, speed))
+            .collect();
+        let retained = retained_margins(&nominal, params);
+        let plan = RobustPlan {
+            nominal,
+            required,
+            retained,
+        };
+        Ok((plan, history))
+    }
+}
+
+/// Check the uncertainty bounds and that the tightened limits leave room.
+fn validate_uncertainty(
+    params: &SimulationParams,
+    uncertainty: &Uncertainty,
+    policy: MarginPolicy,
+) -> Result<(), Error> {
+    const SECTION: &str = "uncertainty";
+    let mut errors = ValidationErrors::default();
+    for (field, value) in [
+        ("thrust", uncertainty.thrust),
+        ("i_sp", uncertainty.i_sp),
+        ("drag", uncertainty.drag),
+        ("position", uncertainty.position),
+        ("velocity", uncertainty.velocity),
+    ] {
+        if !(value.is_finite() && value >= 0.0) {
+            errors.push(
+                SECTION,
+                field,
+                format!("must be non-negative and finite, got {value}"),
+            );
+        }
+    }
+    if uncertainty.i_sp >= params.i_sp {
+        errors.push(
+            SECTION,
+            "i_sp",
+            format!(
+                "must be less than i_sp ({}), got {}",
+                params.i_sp, uncertainty.i_sp
+            ),
+        );
+    }
+    if let MarginPolicy::Tube { time_constant } = policy {
+        if !(time_constant.is_finite() && time_constant > 0.0) {
+            errors.push(
+                "policy",
+                "time_constant",
+                format!("must be positive and finite, got {time_constant}"),
+            );
+        }
+    }
+    if !errors.errors().is_empty() {
+        return Err(Error::InvalidSettings(errors));
+    }
+
+    // The tube margins are monotonic in time, so the extremes are at
+    // ignition and in steady state. Without a trajectory yet, drag can only
+    // be bounded at the initial and final speeds.
+    let speed = speed_bound(params, None);
+    for t in [0.0, f64::INFINITY] {
+        let margins = uncertainty.margins(params, policy, t, params.m_0, speed);
+        let (t_min, t_max) = (
+            params.t_min_vac + margins.thrust_lower,
+            params.t_max_vac - margins.thrust_upper,
+        );
+        if t_min >= t_max {
+            errors.push(
+                "simulation_settings",
+                "t_max_vac",
+                format!("tightened thrust limits [{t_min}, {t_max}] leave no range"),
+            );
+            break;
+        }
+        if margins.tilt >= params.theta_max {
+            errors.push(
+                "simulation_settings",
+                "theta_max",
+                format!(
+                    "tilt margin {} exceeds theta_max ({})",
+                    margins.tilt, params.theta_max
+                ),
+            );
+            break;
+        }
+    }
+
+    if errors.errors().is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidSettings(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::*;
+    use crate::trajectories::{APDGSolutionTimeStep, AlgorithmParams};
+
+    #[test]
+    fn test_tube_margins_settle_to_steady_state() {
+        let params = SimulationParams::default();
+        let uncertainty = Uncertainty::builder()
+            .thrust(0.01)
+            .position(5.0)
+            .velocity(1.0)
+            .build();
+
+        let speed = speed_bound(&params, None);
+        let fixed = uncertainty.margins(&params, MarginPolicy::Fixed, 30.0, params.m_0, speed);
+        assert_eq!(fixed.glide_slope, 5.0);
+        assert!((fixed.thrust_upper - 0.01 * params.t_max_vac).abs() < 1e-9);
+
+        let tube = MarginPolicy::Tube { time_constant: 2.0 };
+        let w = uncertainty.disturbance(&params, speed);
+        let start = uncertainty.margins(&params, tube, 0.0, params.m_0, speed);
+        let steady = uncertainty.margins(&params, tube, f64::INFINITY, params.m_0, speed);
+        assert_eq!(start.glide_slope, 5.0);
+        assert!((steady.glide_slope - w * 4.0).abs() < 1e-9);
+        assert!(steady.tilt > 0.0);
+    }
+
+    #[test]
+    fn test_retained_margins_against_nominal_limits() {
+        let params = SimulationParams::builder()
+            .rf(Vector3::zeros())
+            .e_hat_up(Vector3::x())
+            .build();
+        let step = APDGSolutionTimeStep::builder()
+            .r(Vector3::new(100.0, 0.0, 0.0))
+            .v(Vector3::zeros())
+            .a(Vector3::zeros())
+            .m(params.m_dry + 500.0)
+            .t(Vector3::new(params.t_max_vac - 1000.0, 0.0, 0.0))
+            .gamma(params.t_max_vac - 1000.0)
+            .aR(Vector3::zeros())
+            .build();
+        let solution = APDGSolution::builder()
+            .steps(vec![step.clone(), step])
+            .dt(1.0)
+            .build();
+
+        let retained = retained_margins(&solution, &params);
+        assert!((retained[0].thrust_upper - 1000.0).abs() < 1e-9);
+        assert!((retained[0].tilt - params.theta_max).abs() < 1e-9);
+        assert!((retained[0].mass - 500.0).abs() < 1e-9);
+        // On the cone axis the distance to the surface is 100 sin(gamma_gs)
+        let sin_gs = params.gamma_gs.to_radians().sin();
+        assert!((retained[0].glide_slope - 100.0 * sin_gs).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_drag_is_bounded_at_the_fastest_reference_node() {
+        // A hop starts and ends at rest
+        let params = SimulationParams::builder()
+            .v0(Vector3::zeros())
+            .vf(Vector3::zeros())
+            .build();
+        let uncertainty = Uncertainty::builder().drag(0.2).build();
+        assert_eq!(speed_bound(&params, None), 0.0);
+
+        let step = |v: f64| {
+            APDGSolutionTimeStep::builder()
+                .r(Vector3::zeros())
+                .v(Vector3::new(v, 0.0, 0.0))
+                .a(Vector3::zeros())
+                .m(params.m_0)
+                .t(Vector3::zeros())
+                .gamma(0.0)
+                .aR(Vector3::zeros())
+                .build()
+        };
+        let reference = APDGSolution::builder()
+            .steps(vec![step(0.0), step(25.0), step(-40.0), step(0.0)])
+            .dt(1.0)
+            .build();
+
+        let speed = speed_bound(&params, Some(&reference));
+        assert_eq!(speed, 40.0);
+        let drag = 0.2 * 0.5 * params.rho * params.s_d * params.c_d * 40.0_f64.powi(2);
+        assert!((uncertainty.disturbance(&params, speed) - drag / params.m_dry).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_robust_plan_keeps_the_thrust_margin() {
+        let settings = Settings::builder()
+            .solver_settings(AlgorithmParams::builder().N(20).build())
+            .build();
+        let uncertainty = Uncertainty::builder().thrust(0.02).build();
+        let (plan, _) = APDGProblemSolver::default()
+            .solve_robust(&settings, &uncertainty, MarginPolicy::Fixed)
+            .unwrap();
+
+        assert!(plan.deficient_nodes(1e-3).is_empty());
+        let params = settings.simulation_settings();
+        let retained = plan.retained();
+        for (k, margins) in retained.iter().enumerate().take(retained.len() - 1).skip(1) {
+            assert!(
+                margins.thrust_upper >= 0.02 * params.t_max_vac * (1.0 - 1e-3),
+                "node {k}: {} N below the maximum thrust",
+                margins.thrust_upper
+            );
+        }
+    }
+}
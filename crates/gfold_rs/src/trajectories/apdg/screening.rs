@@ -0,0 +1,146 @@
+//! Fast feasibility screening of a landing scenario.
+//!
+//! A full SC solve takes several convex solves. The checks here are closed
+//! form and only look at [`SimulationParams`], so they can be run on every
+//! candidate ignition point in real time to throw out the hopeless ones. They
+//! are necessary conditions only, passing them does not guarantee that the
+//! solver finds a trajectory.
+
+use nalgebra::Vector3;
+
+use super::{mission::Mission, models::SimulationParams};
+
+/// Outcome of [`SimulationParams::screen_feasibility`].
+///
+/// Margins are positive when the check passes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeasibilityReport {
+    /// Maximum thrust-to-weight ratio at ignition mass
+    pub twr_max: f64,
+    /// Minimum thrust-to-weight ratio at dry mass
+    pub twr_min: f64,
+    /// Ideal delta-v from the rocket equation at maximum thrust [m/s]
+    pub delta_v_available: f64,
+    /// Impulsive lower bound on the delta-v needed to land [m/s]
+    pub delta_v_lower_bound: f64,
+    /// Lower bound plus an estimate of the gravity losses [m/s]
+    pub delta_v_estimate: f64,
+    /// Glide slope angle minus the angle of `r0` from vertical [deg]
+    pub glide_slope_margin: f64,
+}
+
+impl FeasibilityReport {
+    /// Thrust-to-weight margin at ignition, the vehicle cannot decelerate
+    /// against gravity when this is negative.
+    pub fn twr_margin(&self) -> f64 {
+        self.twr_max - 1.0
+    }
+
+    /// Propellant margin against the delta-v estimate [m/s]
+    pub fn delta_v_margin(&self) -> f64 {
+        self.delta_v_available - self.delta_v_estimate
+    }
+
+    /// Whether the minimum thrust lifts the vehicle at dry mass.
+    ///
+    /// The vehicle can then not hover near touchdown and the landing has to
+    /// be timed so the velocity reaches zero exactly at the ground.
+    pub fn hover_infeasible(&self) -> bool {
+        self.twr_min > 1.0
+    }
+
+    /// Whether the scenario passes every hard check.
+    ///
+    /// A hover infeasible vehicle is still plausible, and only the impulsive
+    /// lower bound is used for the delta-v check so a scenario is never
+    /// rejected on the strength of the gravity loss estimate.
+    pub fn is_plausible(&self) -> bool {
+        self.twr_margin() > 0.0
+            && self.delta_v_available >= self.delta_v_lower_bound
+            && self.glide_slope_margin >= 0.0
+    }
+}
+
+impl SimulationParams {
+    /// Cheap necessary conditions for a landing to be feasible.
+    pub fn screen_feasibility(&self) -> FeasibilityReport {
+        let g = self.g_vec.norm();
+
+        // Effective exhaust velocity includes the back pressure mass flow,
+        // it is best at maximum thrust
+        let back_pressure = self.p_amb * self.a_nozzle;
+        let c_eff = self.i_sp * self.g_0 * self.t_max_vac / (self.t_max_vac + back_pressure);
+        let delta_v_available = c_eff * (self.m_0 / self.m_dry).ln();
+
+        // With infinite thrust the cheapest landing is a free fall followed by
+        // an impulsive burn at the ground, so energy conservation gives a
+        // lower bound on the speed to remove
+        let h = self.e_hat_up.dot(&(self.r0 - self.rf));
+        let ground_speed = (self.v0.norm_squared() + 2.0 * g * h).max(0.0).sqrt();
+        let delta_v_lower_bound = (ground_speed - self.vf.norm()).abs();
+
+        // A burn at maximum thrust loses g for every second it lasts
+        let a_net = self.t_max_vac / self.m_0 - g;
+        let delta_v_estimate = if a_net > 0.0 {
+            delta_v_lower_bound * (1.0 + g / a_net)
+        } else {
+            f64::INFINITY
+        };
+
+        // A hop starts at the vertex of its own cone
+        let r0_offset = match self.mission {
+            Mission::Descent => self.r0 - self.rf,
+            Mission::Hop(_) => Vector3::zeros(),
+        };
+        let cos_r0 = self.e_hat_up.dot(&r0_offset) / r0_offset.norm();
+        let r0_angle = if cos_r0.is_finite() {
+            cos_r0.clamp(-1.0, 1.0).acos().to_degrees()
+        } else {
+            // Starting on the cone vertex
+            0.0
+        };
+
+        FeasibilityReport {
+            twr_max: self.t_max_vac / (self.m_0 * g),
+            twr_min: self.t_min_vac / (self.m_dry * g),
+            delta_v_available,
+            delta_v_lower_bound,
+            delta_v_estimate,
+            glide_slope_margin: self.gamma_gs - r0_angle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_scenario_is_plausible() {
+        let report = SimulationParams::default().screen_feasibility();
+
+        assert!(report.is_plausible());
+        assert!(report.delta_v_margin() > 0.0);
+        // The default engine cannot throttle below dry weight
+        assert!(report.hover_infeasible());
+    }
+
+    #[test]
+    fn test_rejects_underpowered_and_out_of_cone_starts() {
+        let heavy = SimulationParams::builder()
+            .m_0(30_000.0)
+            .build()
+            .screen_feasibility();
+        assert!(heavy.twr_margin() < 0.0);
+        assert!(heavy.delta_v_estimate.is_infinite());
+        assert!(!heavy.is_plausible());
+
+        // Far out horizontally at low altitude, outside the 80 deg cone
+        let low = SimulationParams::builder()
+            .r0([50.0, 1000.0, 0.0].into())
+            .build()
+            .screen_feasibility();
+        assert!(low.glide_slope_margin < 0.0);
+        assert!(!low.is_plausible());
+    }
+}
@@ -0,0 +1,216 @@
+//! Analytic linearisation of the Problem 5 dynamics.
+//!
+//! The dynamics terms are few and known, so their Jacobians are written out
+//! here and each term is linearised with a single evaluation, without
+//! automatic differentiation. The tests check every gradient against central
+//! finite differences.
+//!
+//! Every term returns a [`Linearisation`] whose gradient follows the same
+//! input order as the `(Variable, bar)` pairs it is expanded around.
+
+use good_lp::{Expression, Variable};
+use nalgebra::Vector3;
+
+use crate::trajectories::apdg::models::SimulationParams;
+
+/// Value and gradient of a scalar function at a reference point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(in crate::trajectories::apdg) struct Linearisation<const N: usize> {
+    pub value: f64,
+    pub gradient: [f64; N],
+}
+
+impl<const N: usize> Linearisation<N> {
+    /// First-order Taylor expansion `f_bar + sum(df_dxi * (xi - xi_bar))`.
+    pub fn expression(&self, vars_and_bars: [(Variable, f64); N]) -> Expression {
+        let mut expr = Expression::from(self.value);
+        for ((variable, bar), derivative) in vars_and_bars.into_iter().zip(self.gradient) {
+            expr += derivative * (Expression::from(variable) - bar);
+        }
+        expr
+    }
+}
+
+/// Constants of the discretised dynamics.
+#[derive(Debug, Clone)]
+pub(in crate::trajectories::apdg) struct Dynamics {
+    /// Mass flow per unit thrust [kg/(N s)]
+    alpha: f64,
+    /// Mass flow lost to back pressure [kg/s]
+    m_dot_bp: f64,
+    /// Drag force per squared speed [kg/m]
+    drag_coeff: f64,
+    /// Gravity vector [m/s^2]
+    g_vec: Vector3<f64>,
+}
+
+impl Dynamics {
+    pub fn new(params: &SimulationParams) -> Self {
+        Dynamics {
+            alpha: 1.0 / (params.i_sp * params.g_0),
+            m_dot_bp: (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0),
+            drag_coeff: 0.5 * params.rho * params.s_d * params.c_d,
+            g_vec: params.g_vec,
+        }
+    }
+
+    /// Mass change over a step
+    ///
+    /// `f_m = -(alpha / 2 * (Γ[k] + Γ[k+1]) + m_dot_bp) * dt`
+    ///
+    /// Inputs `[Γ[k], Γ[k+1], dt]`.
+    pub fn mass(&self, gamma_k: f64, gamma_k1: f64, dt: f64) -> Linearisation<3> {
+        let rate = self.alpha / 2.0 * (gamma_k + gamma_k1) + self.m_dot_bp;
+        Linearisation {
+            value: -rate * dt,
+            gradient: [-self.alpha / 2.0 * dt, -self.alpha / 2.0 * dt, -rate],
+        }
+    }
+
+    /// Position change over a step, one component
+    ///
+    /// `f_r = v[k] * dt + 1/3 * (a[k] + 1/2 * a[k+1]) * dt^2`
+    ///
+    /// Inputs `[v[k], a[k], a[k+1], dt]`.
+    pub fn position(&self, v_k: f64, a_k: f64, a_k1: f64, dt: f64) -> Linearisation<4> {
+        let a_mix = a_k + 0.5 * a_k1;
+        Linearisation {
+            value: v_k * dt + a_mix * dt.powi(2) / 3.0,
+            gradient: [
+                dt,
+                dt.powi(2) / 3.0,
+                dt.powi(2) / 6.0,
+                v_k + 2.0 / 3.0 * a_mix * dt,
+            ],
+        }
+    }
+
+    /// Velocity change over a step, one component
+    ///
+    /// `f_v = 1/2 * (a[k] + a[k+1]) * dt`
+    ///
+    /// Inputs `[a[k], a[k+1], dt]`.
+    pub fn velocity(&self, a_k: f64, a_k1: f64, dt: f64) -> Linearisation<3> {
+        Linearisation {
+            value: 0.5 * (a_k + a_k1) * dt,
+            gradient: [0.5 * dt, 0.5 * dt, 0.5 * (a_k + a_k1)],
+        }
+    }
+
+    /// Acceleration at a node, component `i`
+    ///
+    /// `f_a = (T[k] - c * ||v[k]|| * v[k]) / m[k] + a_R[k] + g`
+    ///
+    /// Inputs `[m[k], T[k], v[k].x, v[k].y, v[k].z, a_R[k]]`. The drag
+    /// Jacobian `c * (v v^T / ||v|| + ||v|| I)` is taken as zero at rest,
+    /// which is its limit.
+    pub fn acceleration(
+        &self,
+        i: usize,
+        m: f64,
+        t_i: f64,
+        v: &Vector3<f64>,
+        aR_i: f64,
+    ) -> Linearisation<6> {
+        let speed = v.norm();
+        let force = t_i - self.drag_coeff * speed * v[i];
+
+        let mut d_drag = [0.0; 3];
+        if speed > 0.0 {
+            for (j, d) in d_drag.iter_mut().enumerate() {
+                let identity = if i == j { speed } else { 0.0 };
+                *d = -self.drag_coeff * (v[i] * v[j] / speed + identity) / m;
+            }
+        }
+
+        Linearisation {
+            value: force / m + aR_i + self.g_vec[i],
+            gradient: [
+                -force / m.powi(2),
+                1.0 / m,
+                d_drag[0],
+                d_drag[1],
+                d_drag[2],
+                1.0,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central finite difference of `f` at `x`
+    fn finite_difference<const N: usize>(f: impl Fn(&[f64; N]) -> f64, x: [f64; N]) -> [f64; N] {
+        std::array::from_fn(|i| {
+            let h = 1e-6 * x[i].abs().max(1.0);
+            let (mut up, mut down) = (x, x);
+            up[i] += h;
+            down[i] -= h;
+            (f(&up) - f(&down)) / (2.0 * h)
+        })
+    }
+
+    fn assert_gradient<const N: usize>(
+        lin: Linearisation<N>,
+        f: impl Fn(&[f64; N]) -> f64,
+        x: [f64; N],
+    ) {
+        assert!((lin.value - f(&x)).abs() <= 1e-12 * lin.value.abs().max(1.0));
+        for (i, (analytic, numeric)) in lin.gradient.iter().zip(finite_difference(f, x)).enumerate()
+        {
+            let tol = 1e-6 * numeric.abs().max(1e-3);
+            assert!(
+                (analytic - numeric).abs() <= tol,
+                "input {i}: analytic {analytic}, finite difference {numeric}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_kinematics_match_finite_differences() {
+        let dynamics = Dynamics::new(&SimulationParams::default());
+
+        let x = [120_000.0, 140_000.0, 0.8];
+        assert_gradient(
+            dynamics.mass(x[0], x[1], x[2]),
+            |x| dynamics.mass(x[0], x[1], x[2]).value,
+            x,
+        );
+
+        let x = [-30.0, 4.0, -2.5, 0.8];
+        assert_gradient(
+            dynamics.position(x[0], x[1], x[2], x[3]),
+            |x| dynamics.position(x[0], x[1], x[2], x[3]).value,
+            x,
+        );
+
+        let x = [4.0, -2.5, 0.8];
+        assert_gradient(
+            dynamics.velocity(x[0], x[1], x[2]),
+            |x| dynamics.velocity(x[0], x[1], x[2]).value,
+            x,
+        );
+    }
+
+    #[test]
+    fn test_acceleration_matches_finite_differences() {
+        let params = SimulationParams::builder().c_d(1.2).build();
+        let dynamics = Dynamics::new(&params);
+
+        for i in 0..3 {
+            let x = [14_000.0, 90_000.0, -40.0, 12.0, 7.0, 0.3];
+            let f = |x: &[f64; 6]| {
+                let v = Vector3::new(x[2], x[3], x[4]);
+                dynamics.acceleration(i, x[0], x[1], &v, x[5]).value
+            };
+            let v = Vector3::new(x[2], x[3], x[4]);
+            assert_gradient(dynamics.acceleration(i, x[0], x[1], &v, x[5]), f, x);
+        }
+
+        // Drag has no first-order effect at rest
+        let at_rest = dynamics.acceleration(0, 14_000.0, 90_000.0, &Vector3::zeros(), 0.0);
+        assert_eq!(&at_rest.gradient[2..5], &[0.0; 3]);
+    }
+}
@@ -1,5 +1,6 @@
+pub(super) mod linearise;
+pub(super) mod phases;
 pub(super) mod problem;
-mod taylor_expansion;
 
 use super::error::Error;
 
@@ -0,0 +1,205 @@
+use super::{
+    problem::{
+        add_custom_constraints, add_final_constraints, add_initial_constraints,
+        add_initial_thrust_constraints, add_linearised_dynamics_constraints, add_slack_constraints,
+        add_state_constraints, ConstraintReferences, DecisionVariables,
+    },
+    Error,
+};
+use crate::trajectories::{
+    apdg::{
+        constraints::TrajectoryConstraint,
+        models::{AlgorithmParams, SimulationParams},
+        phases::{Phase, PhaseKind},
+        target,
+    },
+    APDGSolution,
+};
+use good_lp::{
+    clarabel, constraint, variables, Expression, Solution, SolutionStatus, SolutionWithDual,
+    SolverModel,
+};
+use std::sync::Arc;
+
+// -------------------------------------------------------
+// Problem 5 over a sequence of phases
+//
+//     min -w_mf * m[kf] + sum_p (w_eta_dt * eta_dt[p] + w_eta_T * ||eta_T[p]|| + w_kappa_aR * ||kappa_aR[p]||)
+//
+// s.t.
+//    Problem 5 constraints of every phase, phase linkage,
+//    boundary conditions at the first and last node
+// -------------------------------------------------
+
+pub struct MultiPhaseProblem {
+    sim_params: SimulationParams,
+    algo_params: AlgorithmParams,
+    phases: Vec<Phase>,
+    prev_trajectory: Vec<APDGSolution>,
+    constraints: Vec<Arc<dyn TrajectoryConstraint>>,
+}
+
+impl MultiPhaseProblem {
+    pub fn new(
+        sim_params: SimulationParams,
+        algo_params: AlgorithmParams,
+        phases: Vec<Phase>,
+        prev_trajectory: Vec<APDGSolution>,
+        constraints: Vec<Arc<dyn TrajectoryConstraint>>,
+    ) -> MultiPhaseProblem {
+        MultiPhaseProblem {
+            sim_params,
+            algo_params,
+            phases,
+            prev_trajectory,
+            constraints,
+        }
+    }
+
+    /// Solve the problem, one solution per phase
+    pub fn solve(self) -> Result<Vec<APDGSolution>, Error> {
+        let algo = &self.algo_params;
+        let n_phases = self.phases.len();
+        if n_phases == 0 {
+            return Err(Error::SolverError("no phases to solve".to_string()));
+        }
+
+        // Every phase is a Problem 5 segment with its own parameters
+        let params: Vec<(SimulationParams, AlgorithmParams)> = self
+            .phases
+            .iter()
+            .map(|p| {
+                (
+                    p.simulation_params(&self.sim_params),
+                    p.algorithm_params(algo),
+                )
+            })
+            .collect();
+
+        let mut vars = variables!();
+        let phase_vars: Vec<DecisionVariables> = self
+            .phases
+            .iter()
+            .map(|p| DecisionVariables::new(&mut vars, p.N))
+            .collect();
+        let (first, last) = (&phase_vars[0], &phase_vars[n_phases - 1]);
+
+        let mut objective = Expression::default();
+        objective += -algo.w_mf * last.steps[last.steps.len() - 1].m;
+        for p in &phase_vars {
+            objective += algo.w_eta_dt * p.eta_dt;
+            objective += algo.w_eta_T * p.norm_eta_T;
+            objective += algo.w_kappa_aR * p.norm_kappa_aR;
+        }
+        let mut model = vars.minimise(objective).using(clarabel);
+        let mut refs: Vec<ConstraintReferences> = (0..n_phases)
+            .map(|_| ConstraintReferences::default())
+            .collect();
+
+        // Initial conditions at the first node of the first phase
+        add_initial_constraints(&mut model, &mut refs[0], first, &params[0].0);
+        if self.phases[0].kind == PhaseKind::Burn && self.sim_params.mission.fixes_initial_thrust()
+        {
+            add_initial_thrust_constraints(&mut model, first, &params[0].0);
+        }
+
+        // Final conditions relative to the target at the total time of flight,
+        // linearised about the reference time of flight
+        let mut tf = Expression::default();
+        let mut tf_bar = 0.0;
+        for (p, prev) in phase_vars.iter().zip(&self.prev_trajectory) {
+            let n_steps = (p.steps.len() - 1) as f64;
+            tf += n_steps * p.dt;
+            tf_bar += prev.duration();
+        }
+        let (last_sim, last_algo) = &params[n_phases - 1];
+        add_final_constraints(
+            &mut model,
+            &mut refs[n_phases - 1],
+            last,
+            last_sim,
+            last_algo,
+            target::final_state(&self.sim_params, tf, tf_bar),
+        );
+
+        // Phase linkage, the state is continuous across phase boundaries
+        for pair in phase_vars.windows(2) {
+            let (end, start) = (&pair[0].steps[pair[0].steps.len() - 1], &pair[1].steps[0]);
+            model.add_constraint(constraint!(start.m == end.m));
+            for i in 0..3 {
+                model.add_constraint(constraint!(start.r[i] == end.r[i]));
+                model.add_constraint(constraint!(start.v[i] == end.v[i]));
+            }
+        }
+
+        for (i, phase) in self.phases.iter().enumerate() {
+            let (sim, phase_algo) = &params[i];
+            let (vars, prev) = (&phase_vars[i], &self.prev_trajectory[i]);
+
+            add_linearised_dynamics_constraints(&mut model, vars, sim, phase_algo, prev);
+            add_state_constraints(
+                &mut model,
+                &mut refs[i],
+                vars,
+                sim,
+                phase_algo,
+                prev,
+                tf_bar,
+            );
+            add_slack_constraints(&mut model, vars, sim, phase_algo);
+
+            let constraints: Vec<_> = self
+                .constraints
+                .iter()
+                .chain(&phase.constraints)
+                .cloned()
+                .collect();
+            add_custom_constraints(&mut model, vars, sim, phase_algo, prev, &constraints);
+
+            // The engine is off during a coast, T[k] = 0 and Gamma[k] = 0
+            if phase.kind == PhaseKind::Coast {
+                for step in &vars.steps {
+                    model.add_constraint(constraint!(step.gamma == 0.0));
+                    for t in step.t.iter() {
+                        model.add_constraint(constraint!(*t == 0.0));
+                    }
+                }
+            }
+
+            // Duration limits
+            let n_steps = (phase.N - 1) as f64;
+            model.add_constraint(constraint!(vars.dt >= 0.0));
+            if let Some(min) = phase.min_duration {
+                model.add_constraint(constraint!(n_steps * vars.dt >= min));
+            }
+            if let Some(max) = phase.max_duration {
+                model.add_constraint(constraint!(n_steps * vars.dt <= max));
+            }
+        }
+
+        // Run the solver
+        let mut solution = model.solve()?;
+
+        match solution.status() {
+            SolutionStatus::Optimal => {
+                let mut trajectory = Vec::with_capacity(n_phases);
+                for ((vars, refs), (_, phase_algo)) in phase_vars.iter().zip(&refs).zip(&params) {
+                    let steps = vars.values(&solution);
+                    let dt = solution.value(vars.dt);
+                    let duals = refs.read(solution.compute_dual(), algo.w_mf);
+                    trajectory.push(APDGSolution {
+                        steps,
+                        dt,
+                        duals: Some(duals),
+                        constraint_groups: Some(phase_algo.enabled_constraints()),
+                    });
+                }
+                Ok(trajectory)
+            }
+            _ => Err(Error::SolverError(format!(
+                "Solver did not find an optimal solution. Status: {:?}",
+                solution.status()
+            ))),
+        }
+    }
+}
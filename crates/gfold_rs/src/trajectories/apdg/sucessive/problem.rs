@@ -1,18 +1,22 @@
-use super::{
-    taylor_expansion::{build_taylor_expression, F64},
-    Error,
-};
+use super::{linearise::Dynamics, Error};
 use crate::trajectories::{
-    apdg::models::{AlgorithmParams, SimulationParams},
+    apdg::{
+        constraints::{ConstraintContext, NodeVariables, TrajectoryConstraint},
+        duals::{BoundaryDuals, ConstraintDuals},
+        mission,
+        models::{AlgorithmParams, ConstraintGroup, SimulationParams},
+        objective, target,
+    },
     APDGSolution, APDGSolutionTimeStep,
 };
-use autodiff::F;
 use good_lp::{
-    clarabel, constraint, soc_constraint, variable, variables, Constraint, Expression,
-    ProblemVariables, Solution, SolutionStatus, SolverModel, Variable,
+    clarabel, constraint, constraint::ConstraintReference, soc_constraint,
+    solvers::clarabel::ClarabelProblem, variable, variables, Constraint, DualValues, Expression,
+    ProblemVariables, Solution, SolutionStatus, SolutionWithDual, SolverModel, Variable,
 };
-use nalgebra::{DVector, Vector3};
-use num_traits::real::Real;
+use nalgebra::Vector3;
+use std::sync::Arc;
+use uom::si::angle::radian;
 
 // -------------------------------------------------------
 // Problem 5: Rocket Landing Optimal Control Problem
@@ -28,23 +32,24 @@ pub struct APDGProblem {
     sim_params: SimulationParams,
     algo_params: AlgorithmParams,
     prev_trajectory: APDGSolution,
+    constraints: Vec<Arc<dyn TrajectoryConstraint>>,
 }
 
 // Store all decision variables for a single time step
 #[derive(Clone, Debug)]
 pub struct TimeStepVariables {
     /// Position [m]
-    r: Vector3<Variable>,
+    pub(super) r: Vector3<Variable>,
     /// Velocity [m/s]
-    v: Vector3<Variable>,
+    pub(super) v: Vector3<Variable>,
     /// Acceleration [m/s^2]
     a: Vector3<Variable>,
     /// Mass [kg]
-    m: Variable,
+    pub(super) m: Variable,
     /// Thrust [N]
-    t: Vector3<Variable>,
+    pub(super) t: Vector3<Variable>,
     /// Thrust magnitude scalar [N]
-    gamma: Variable,
+    pub(super) gamma: Variable,
     /// Acceleration relaxation term [m/s^2]
     aR: Vector3<Variable>,
     /// Relaxation slack []
@@ -53,19 +58,32 @@ pub struct TimeStepVariables {
     eta_T: Variable, // Represents ηT[k] from Eq. 93
 }
 
+impl TimeStepVariables {
+    fn node(&self) -> NodeVariables {
+        NodeVariables {
+            r: self.r,
+            v: self.v,
+            a: self.a,
+            m: self.m,
+            t: self.t,
+            gamma: self.gamma,
+        }
+    }
+}
+
 // Store all decision variables for all time steps
 // Basically the global state
-struct DecisionVariables {
-    steps: Vec<TimeStepVariables>,
+pub(super) struct DecisionVariables {
+    pub(super) steps: Vec<TimeStepVariables>,
     N: usize,
     /// Time step duration [s]
-    dt: Variable,
+    pub(super) dt: Variable,
     /// Trust region slack variable for dt
-    eta_dt: Variable,
+    pub(super) eta_dt: Variable,
     /// Auxiliary variable for L2 norm of eta_T (||ηT|| from Eq. 58)
-    norm_eta_T: Variable,
+    pub(super) norm_eta_T: Variable,
     /// Auxiliary variable for L2 norm of kappa_aR (||κa,R|| from Eq. 59)
-    norm_kappa_aR: Variable,
+    pub(super) norm_kappa_aR: Variable,
 }
 
 impl DecisionVariables {
@@ -78,7 +96,7 @@ impl DecisionVariables {
     }
 
     // Create variables in an interleaved order by time step
-    fn new(vars: &mut good_lp::ProblemVariables, N: usize) -> Self {
+    pub(super) fn new(vars: &mut good_lp::ProblemVariables, N: usize) -> Self {
         let mut steps = Vec::with_capacity(N);
         for _k in 0..N {
             let r_k = Self::fixed_vector(vars);
@@ -119,6 +137,106 @@ impl DecisionVariables {
             norm_kappa_aR: norm_kappa_aR_var,
         }
     }
+
+    /// Values of the node variables in a solution
+    pub(super) fn values(&self, solution: &impl Solution) -> Vec<APDGSolutionTimeStep> {
+        let mut steps_solution = Vec::with_capacity(self.N);
+
+        for step_vars in &self.steps {
+            let r_sol = Vector3::new(
+                solution.value(step_vars.r[0]),
+                solution.value(step_vars.r[1]),
+                solution.value(step_vars.r[2]),
+            );
+            let v_sol = Vector3::new(
+                solution.value(step_vars.v[0]),
+                solution.value(step_vars.v[1]),
+                solution.value(step_vars.v[2]),
+            );
+            let a_sol = Vector3::new(
+                solution.value(step_vars.a[0]),
+                solution.value(step_vars.a[1]),
+                solution.value(step_vars.a[2]),
+            );
+            let m_sol = solution.value(step_vars.m);
+            let t_sol = Vector3::new(
+                solution.value(step_vars.t[0]),
+                solution.value(step_vars.t[1]),
+                solution.value(step_vars.t[2]),
+            );
+            let gamma_sol = solution.value(step_vars.gamma);
+            let aR_sol = Vector3::new(
+                solution.value(step_vars.aR[0]),
+                solution.value(step_vars.aR[1]),
+                solution.value(step_vars.aR[2]),
+            );
+
+            // Populate using the expected APDGSolutionTimeStep struct
+            steps_solution.push(APDGSolutionTimeStep {
+                r: r_sol,
+                v: v_sol,
+                a: a_sol,
+                m: m_sol,
+                t: t_sol,
+                gamma: gamma_sol,
+                aR: aR_sol,
+            });
+        }
+        steps_solution
+    }
+}
+
+// Constraints whose dual variables are reported with the solution
+#[derive(Default)]
+pub(super) struct ConstraintReferences {
+    m0: Option<ConstraintReference>,
+    r0: Vec<ConstraintReference>,
+    v0: Vec<ConstraintReference>,
+    rf: Vec<ConstraintReference>,
+    vf: Vec<ConstraintReference>,
+    thrust_min: Vec<ConstraintReference>,
+    thrust_max: Vec<ConstraintReference>,
+    tilt: Vec<ConstraintReference>,
+    glide_slope: Vec<ConstraintReference>,
+    mass_floor: Vec<ConstraintReference>,
+    thrust_rate_min: Vec<ConstraintReference>,
+    thrust_rate_max: Vec<ConstraintReference>,
+}
+
+impl ConstraintReferences {
+    /// Read the duals of every tracked constraint.
+    ///
+    /// Second-order cone references point at the row of the scalar side,
+    /// whose dual is the sensitivity to relaxing the cone.
+    pub(super) fn read(&self, solution: impl DualValues, w_mf: f64) -> ConstraintDuals {
+        let read = |refs: &[ConstraintReference]| -> Vec<f64> {
+            refs.iter().map(|c| solution.dual(c.clone())).collect()
+        };
+        // Missing when the final position is left free
+        let vector = |refs: &[ConstraintReference]| {
+            let mut duals = Vector3::zeros();
+            duals.iter_mut().zip(read(refs)).for_each(|(d, z)| *d = z);
+            duals
+        };
+
+        ConstraintDuals {
+            w_mf,
+            thrust_min: read(&self.thrust_min),
+            thrust_max: read(&self.thrust_max),
+            tilt: read(&self.tilt),
+            glide_slope: read(&self.glide_slope),
+            mass_floor: read(&self.mass_floor),
+            thrust_rate_min: read(&self.thrust_rate_min),
+            thrust_rate_max: read(&self.thrust_rate_max),
+            boundary: BoundaryDuals {
+                m0: self.m0.clone().map_or(0.0, |c| solution.dual(c)),
+                r0: vector(&self.r0),
+                v0: vector(&self.v0),
+                rf: vector(&self.rf),
+                vf: vector(&self.vf),
+            },
+        }
+    }
 }
 
 impl APDGProblem {
@@ -126,28 +244,39 @@ impl APDGProblem {
         sim_params: SimulationParams,
         algo_params: AlgorithmParams,
         prev_trajectory: APDGSolution,
+        constraints: Vec<Arc<dyn TrajectoryConstraint>>,
     ) -> APDGProblem {
         APDGProblem {
             sim_params,
             algo_params,
             prev_trajectory,
+            constraints,
         }
     }
 
     /// Solve the problem
     pub fn solve(self) -> Result<APDGSolution, Error> {
         // Setup the problem inside solve
-        let (decision_vars, mut model) = setup_problem(&self.sim_params, &self.algo_params);
+        let (decision_vars, mut model) =
+            setup_problem(&self.sim_params, &self.algo_params, &self.prev_trajectory);
+        let mut refs = ConstraintReferences::default();
 
         // Add initial constraints
-        add_initial_constraints(&mut model, &decision_vars, &self.sim_params);
+        add_initial_constraints(&mut model, &mut refs, &decision_vars, &self.sim_params);
+        // A hop lifts off with whatever thrust is best
+        if self.sim_params.mission.fixes_initial_thrust() {
+            add_initial_thrust_constraints(&mut model, &decision_vars, &self.sim_params);
+        }
 
         // Add final constraints
+        let target = final_target(&self.sim_params, &decision_vars, &self.prev_trajectory);
         add_final_constraints(
             &mut model,
+            &mut refs,
             &decision_vars,
             &self.sim_params,
             &self.algo_params,
+            target,
         );
 
         // Add dynamics constraints
@@ -162,10 +291,12 @@ impl APDGProblem {
         // Add state constraints
         add_state_constraints(
             &mut model,
+            &mut refs,
             &decision_vars,
             &self.sim_params,
             &self.algo_params,
             &self.prev_trajectory,
+            self.prev_trajectory.duration(),
         );
 
         // Add slack constraints
@@ -176,63 +307,30 @@ impl APDGProblem {
             &self.algo_params,
         );
 
+        // Add user-defined constraints
+        add_custom_constraints(
+            &mut model,
+            &decision_vars,
+            &self.sim_params,
+            &self.algo_params,
+            &self.prev_trajectory,
+            &self.constraints,
+        );
+
         // Run the solver
-        let solution = model
-            .solve()
-            .map_err(|e| Error::SolverError(format!("{:?}", e)))?;
+        let mut solution = model.solve()?;
 
         match solution.status() {
             SolutionStatus::Optimal => {
-                let N = self.algo_params.N;
                 // Get the optimized dt from the solution
                 let dt_sol = solution.value(decision_vars.dt);
-                let mut steps_solution = Vec::with_capacity(N);
-
-                for k in 0..N {
-                    let step_vars = &decision_vars.steps[k];
-
-                    let r_sol = Vector3::new(
-                        solution.value(step_vars.r[0]),
-                        solution.value(step_vars.r[1]),
-                        solution.value(step_vars.r[2]),
-                    );
-                    let v_sol = Vector3::new(
-                        solution.value(step_vars.v[0]),
-                        solution.value(step_vars.v[1]),
-                        solution.value(step_vars.v[2]),
-                    );
-                    let a_sol = Vector3::new(
-                        solution.value(step_vars.a[0]),
-                        solution.value(step_vars.a[1]),
-                        solution.value(step_vars.a[2]),
-                    );
-                    let m_sol = solution.value(step_vars.m);
-                    let t_sol = Vector3::new(
-                        solution.value(step_vars.t[0]),
-                        solution.value(step_vars.t[1]),
-                        solution.value(step_vars.t[2]),
-                    );
-                    let gamma_sol = solution.value(step_vars.gamma);
-                    let aR_sol = Vector3::new(
-                        solution.value(step_vars.aR[0]),
-                        solution.value(step_vars.aR[1]),
-                        solution.value(step_vars.aR[2]),
-                    );
-
-                    // Populate using the expected APDGSolutionTimeStep struct
-                    steps_solution.push(APDGSolutionTimeStep {
-                        r: r_sol,
-                        v: v_sol,
-                        a: a_sol,
-                        m: m_sol,
-                        t: t_sol,
-                        gamma: gamma_sol,
-                        aR: aR_sol,
-                    });
-                }
+                let steps_solution = decision_vars.values(&solution);
+                let duals = refs.read(solution.compute_dual(), self.algo_params.w_mf);
                 Ok(APDGSolution {
                     steps: steps_solution,
                     dt: dt_sol,
+                    duals: Some(duals),
+                    constraint_groups: Some(self.algo_params.enabled_constraints()),
                 })
             }
             _ => Err(Error::SolverError(format!(
@@ -247,21 +345,42 @@ impl APDGProblem {
 fn setup_problem(
     params: &SimulationParams,
     algo: &AlgorithmParams,
-) -> (DecisionVariables, impl SolverModel) {
+    prev_trajectory: &APDGSolution,
+) -> (DecisionVariables, ClarabelProblem) {
     let N = algo.N;
 
     let mut vars = variables!();
 
     let decision_variables = DecisionVariables::new(&mut vars, N);
 
+    let nodes: Vec<NodeVariables> = decision_variables
+        .steps
+        .iter()
+        .map(TimeStepVariables::node)
+        .collect();
+    let (landing_site, _) = final_target(params, &decision_variables, prev_trajectory);
+    let (cost_terms, cost_constraints) = objective::cost_terms(
+        &mut vars,
+        &algo.cost_terms,
+        &nodes,
+        Expression::from(decision_variables.dt),
+        &landing_site,
+        params,
+    );
+
     let mut objective = Expression::default();
     // Minimise: -w_mf * m[kf] + w_eta_dt * eta_dt + w_eta_T * ||eta_T|| + w_kappa_aR * ||kappa_aR||
+    //           + cost terms
     objective += -algo.w_mf * decision_variables.steps[N - 1].m;
     objective += algo.w_eta_dt * decision_variables.eta_dt;
     objective += algo.w_eta_T * decision_variables.norm_eta_T;
     objective += algo.w_kappa_aR * decision_variables.norm_kappa_aR;
+    objective += cost_terms;
 
     let mut model = vars.minimise(objective).using(clarabel);
+    for constraint in cost_constraints {
+        model.add_constraint(constraint);
+    }
 
     model.settings();
 
@@ -269,24 +388,32 @@ fn setup_problem(
 }
 
 /// Add initial condition constraints to the problem
-fn add_initial_constraints(
+pub(super) fn add_initial_constraints(
     model: &mut impl SolverModel,
+    refs: &mut ConstraintReferences,
     vars: &DecisionVariables,
     params: &SimulationParams,
 ) {
     // Initial Mass m[0] = m_0
-    model.add_constraint(constraint!(vars.steps[0].m == params.m_0));
+    refs.m0 = Some(model.add_constraint(constraint!(vars.steps[0].m == params.m_0)));
 
     // Initial Position r[0] = r_0
     for (var, &r0) in vars.steps[0].r.iter().zip(params.r0.iter()) {
-        model.add_constraint(constraint!(*var == r0));
+        refs.r0.push(model.add_constraint(constraint!(*var == r0)));
     }
 
     // Initial Velocity v[0] = v_0
     for (var, &v0) in vars.steps[0].v.iter().zip(params.v0.iter()) {
-        model.add_constraint(constraint!(*var == v0));
+        refs.v0.push(model.add_constraint(constraint!(*var == v0)));
     }
+}
 
+/// Add the initial thrust constraints to the problem
+pub(super) fn add_initial_thrust_constraints(
+    model: &mut impl SolverModel,
+    vars: &DecisionVariables,
+    params: &SimulationParams,
+) {
     // T[0] = Gamma_0 * n_hat0
     // n_hat0 is the initial normal vector
     for (i, var) in vars.steps[0].t.iter().enumerate() {
@@ -297,36 +424,68 @@ fn add_initial_constraints(
     model.add_constraint(constraint!(vars.steps[0].gamma == params.gamma_0_vac));
 }
 
-/// Add final condition constraints to the problem
-fn add_final_constraints(
+/// Target position and velocity at the time of flight `(N - 1) * dt`,
+/// linearised about the time of flight of the reference trajectory
+fn final_target(
+    params: &SimulationParams,
+    vars: &DecisionVariables,
+    prev_trajectory: &APDGSolution,
+) -> (Vec<Expression>, Vec<Expression>) {
+    let n_steps = (vars.steps.len() - 1) as f64;
+    target::final_state(params, n_steps * vars.dt, n_steps * prev_trajectory.dt)
+}
+
+/// Add final condition constraints to the problem, relative to the target
+/// position and velocity at the time of flight
+pub(super) fn add_final_constraints(
     model: &mut impl SolverModel,
+    refs: &mut ConstraintReferences,
     vars: &DecisionVariables,
     params: &SimulationParams,
     algo: &AlgorithmParams,
+    (r_target, v_target): (Vec<Expression>, Vec<Expression>),
 ) {
     let k_end = algo.N - 1;
 
-    // Final position r[N-1] = rf
-    for (var, &rf_val) in vars.steps[k_end].r.iter().zip(params.rf.iter()) {
-        model.add_constraint(constraint!(*var == rf_val));
+    // Final position r[N-1] = r_T(tf), only the altitude when minimising the
+    // landing error
+    if algo.is_enabled(ConstraintGroup::FinalPosition) {
+        if objective::frees_landing_position(&algo.cost_terms) {
+            let r_end = vars.steps[k_end].r;
+            let up = params.e_hat_up;
+            model.add_constraint(constraint!(
+                up.x * r_end[0] + up.y * r_end[1] + up.z * r_end[2]
+                    == up.x * r_target[0].clone()
+                        + up.y * r_target[1].clone()
+                        + up.z * r_target[2].clone()
+            ));
+        } else {
+            for (var, r_T) in vars.steps[k_end].r.iter().zip(r_target) {
+                refs.rf.push(model.add_constraint(constraint!(*var == r_T)));
+            }
+        }
     }
 
-    // Final velocity v[N-1] = vf
-    for (var, &vf_val) in vars.steps[k_end].v.iter().zip(params.vf.iter()) {
-        model.add_constraint(constraint!(*var == vf_val));
+    // Final velocity v[N-1] = v_T(tf)
+    if algo.is_enabled(ConstraintGroup::FinalVelocity) {
+        for (var, v_T) in vars.steps[k_end].v.iter().zip(v_target) {
+            refs.vf.push(model.add_constraint(constraint!(*var == v_T)));
+        }
     }
 
     // Final thrust direction
     // T[N-1] = Gamma[N-1] * n_hatf
-    for (i, tf) in vars.steps[k_end].t.iter().enumerate() {
-        model.add_constraint(constraint!(
-            *tf == vars.steps[k_end].gamma * params.n_hatf[i]
-        ));
+    if algo.is_enabled(ConstraintGroup::FinalThrustDirection) {
+        for (i, tf) in vars.steps[k_end].t.iter().enumerate() {
+            model.add_constraint(constraint!(
+                *tf == vars.steps[k_end].gamma * params.n_hatf[i]
+            ));
+        }
     }
 }
 
 /// Add the linearised dynamics contraints
-fn add_linearised_dynamics_constraints(
+pub(super) fn add_linearised_dynamics_constraints(
     model: &mut impl SolverModel,
     vars: &DecisionVariables,
     params: &SimulationParams,
@@ -334,33 +493,21 @@ fn add_linearised_dynamics_constraints(
     prev_trajectory: &APDGSolution,
 ) {
     let N = settings.N;
-
-    let alpha = 1.0 / (params.i_sp * params.g_0);
-    let m_dot_bp = (params.p_amb * params.a_nozzle) / (params.i_sp * params.g_0);
-
-    let drag_coeff = 0.5 * params.rho * params.s_d * params.c_d;
+    let dynamics = Dynamics::new(params);
+    let prev_dt = prev_trajectory.dt;
 
     for k in 0..N - 1 {
         let prev_step_k = &prev_trajectory.steps[k];
         let prev_step_k1 = &prev_trajectory.steps[k + 1];
-        let prev_dt = prev_trajectory.dt;
 
         // Mass dynamics
-        let fm_func = |psi_vec: &DVector<F64>| -> F64 {
-            let gamma_k = psi_vec[0];
-            let gamma_k1 = psi_vec[1];
-            let delta_t = psi_vec[2];
-            -(alpha / 2.0 * (gamma_k + gamma_k1) + m_dot_bp) * delta_t
-        };
-
-        let fm_taylor_expr = build_taylor_expression(
-            fm_func,
-            &[
+        let fm_taylor_expr = dynamics
+            .mass(prev_step_k.gamma, prev_step_k1.gamma, prev_dt)
+            .expression([
                 (vars.steps[k].gamma, prev_step_k.gamma),
                 (vars.steps[k + 1].gamma, prev_step_k1.gamma),
                 (vars.dt, prev_dt),
-            ],
-        );
+            ]);
 
         model.add_constraint(constraint!(
             vars.steps[k + 1].m == vars.steps[k].m + fm_taylor_expr
@@ -368,24 +515,19 @@ fn add_linearised_dynamics_constraints(
 
         // Position dynamics
         for i in 0..3 {
-            let fr_func = |psi_vec: &DVector<F64>| -> F64 {
-                // psi_vec contains: [v[k][i], a[k][i], a[k+1][i], dt]
-                let vk = psi_vec[0];
-                let ak = psi_vec[1];
-                let ak1 = psi_vec[2];
-                let delta_t = psi_vec[3];
-                vk * delta_t + (1.0 / 3.0) * (ak + 0.5 * ak1) * delta_t.powi(2)
-            };
-
-            let fr_taylor_expr = build_taylor_expression(
-                fr_func,
-                &[
+            let fr_taylor_expr = dynamics
+                .position(
+                    prev_step_k.v[i],
+                    prev_step_k.a[i],
+                    prev_step_k1.a[i],
+                    prev_dt,
+                )
+                .expression([
                     (vars.steps[k].v[i], prev_step_k.v[i]),
                     (vars.steps[k].a[i], prev_step_k.a[i]),
                     (vars.steps[k + 1].a[i], prev_step_k1.a[i]),
                     (vars.dt, prev_dt),
-                ],
-            );
+                ]);
 
             model.add_constraint(constraint!(
                 vars.steps[k + 1].r[i] == vars.steps[k].r[i] + fr_taylor_expr
@@ -394,20 +536,13 @@ fn add_linearised_dynamics_constraints(
 
         // Velocity dynamics
         for i in 0..3 {
-            let fv_func = |psi_vec: &DVector<F64>| -> F64 {
-                let ak = psi_vec[0];
-                let ak1 = psi_vec[1];
-                let delta_t = psi_vec[2];
-                0.5 * (ak + ak1) * delta_t
-            };
-            let fv_taylor_expr = build_taylor_expression(
-                fv_func,
-                &[
+            let fv_taylor_expr = dynamics
+                .velocity(prev_step_k.a[i], prev_step_k1.a[i], prev_dt)
+                .expression([
                     (vars.steps[k].a[i], prev_step_k.a[i]),
                     (vars.steps[k + 1].a[i], prev_step_k1.a[i]),
                     (vars.dt, prev_dt),
-                ],
-            );
+                ]);
 
             model.add_constraint(constraint!(
                 vars.steps[k + 1].v[i] == vars.steps[k].v[i] + fv_taylor_expr
@@ -422,32 +557,22 @@ fn add_linearised_dynamics_constraints(
 
         let prev_step_k = &prev_trajectory.steps[k];
         for i in 0..3 {
-            let fa_func = |psi_vec: &DVector<F64>| -> F64 {
-                let m_k = psi_vec[0];
-                let t_k = psi_vec[1];
-                let vk_0 = psi_vec[2];
-                let vk_1 = psi_vec[3];
-                let vk_2 = psi_vec[4];
-                let aR_k = psi_vec[5];
-
-                let v_norm = F::sqrt(vk_0.powi(2) + vk_1.powi(2) + vk_2.powi(2));
-
-                let drag_k_i = -drag_coeff * v_norm * psi_vec[2 + i];
-
-                (t_k + drag_k_i) / m_k + aR_k + params.g_vec[i]
-            };
-
-            let fa_taylor_expr = build_taylor_expression(
-                fa_func,
-                &[
+            let fa_taylor_expr = dynamics
+                .acceleration(
+                    i,
+                    prev_step_k.m,
+                    prev_step_k.t[i],
+                    &prev_step_k.v,
+                    prev_step_k.aR[i],
+                )
+                .expression([
                     (vars.steps[k].m, prev_step_k.m),
                     (vars.steps[k].t[i], prev_step_k.t[i]),
                     (vars.steps[k].v[0], prev_step_k.v[0]),
                     (vars.steps[k].v[1], prev_step_k.v[1]),
                     (vars.steps[k].v[2], prev_step_k.v[2]),
                     (vars.steps[k].aR[i], prev_step_k.aR[i]),
-                ],
-            );
+                ]);
 
             model.add_constraint(constraint!(vars.steps[k].a[i] == fa_taylor_expr));
         }
@@ -455,44 +580,63 @@ fn add_linearised_dynamics_constraints(
 }
 
 /// Add the state constraints
-fn add_state_constraints(
+///
+/// `tf_bar` is the time of flight of the reference trajectory, which places
+/// the glide slope vertex.
+pub(super) fn add_state_constraints(
     model: &mut impl SolverModel,
+    refs: &mut ConstraintReferences,
     vars: &DecisionVariables,
     params: &SimulationParams,
     settings: &AlgorithmParams,
     prev_trajectory: &APDGSolution,
+    tf_bar: f64,
 ) {
     let N = settings.N;
     let dt_bar = prev_trajectory.dt;
 
     // Add SOC constraints
-    for k in 0..N {
-        // Mass lowerbound constraint
-        // m[k] >= m_dry
-        model.add_constraint(constraint!(vars.steps[k].m >= params.m_dry));
+    if settings.is_enabled(ConstraintGroup::MassFloor) {
+        for k in 0..N {
+            // Mass lowerbound constraint
+            // m[k] >= m_dry
+            refs.mass_floor
+                .push(model.add_constraint(constraint!(vars.steps[k].m >= params.m_dry)));
+        }
     }
 
-    // Glide-slope constraint
-    // ||r[k]|| cos(gamma_gs) <= e_u^T * r[k]
-    let sec_gs = 1.0 / f64::to_radians(params.gamma_gs).cos();
-    for k in 0..N {
-        let t_expr = sec_gs
-            * (params.e_hat_up.x * vars.steps[k].r[0]
-                + params.e_hat_up.y * vars.steps[k].r[1]
-                + params.e_hat_up.z * vars.steps[k].r[2]);
-        // Use the expression directly
-        model.add_constraint(soc_constraint!(
-            norm2(vars.steps[k].r[0], vars.steps[k].r[1], vars.steps[k].r[2]) <= t_expr
-        ));
+    // Glide-slope constraint about the cone vertex c[k]
+    // ||r[k] - c[k]|| cos(gamma_gs) <= e_u^T * (r[k] - c[k])
+    if settings.is_enabled(ConstraintGroup::GlideSlope) {
+        let sec_gs = 1.0 / params.gamma_gs().get::<radian>().cos();
+        for k in 0..N {
+            let vertex = params.mission.glide_slope_vertex(params, k, N, tf_bar);
+            let offset: Vec<Expression> = (0..3).map(|i| vars.steps[k].r[i] - vertex[i]).collect();
+            let t_expr = sec_gs
+                * (params.e_hat_up.x * offset[0].clone()
+                    + params.e_hat_up.y * offset[1].clone()
+                    + params.e_hat_up.z * offset[2].clone());
+            refs.glide_slope
+                .push(model.add_constraint(soc_constraint!(norm2_vec(offset) <= t_expr)));
+        }
+    }
+
+    // Hop altitude constraints
+    let r: Vec<_> = vars.steps.iter().map(|s| s.r).collect();
+    let v: Vec<_> = vars.steps.iter().map(|s| s.v).collect();
+    for constraint in mission::altitude_constraints(params, &r, &v) {
+        model.add_constraint(constraint);
     }
 
     // Thrust (Equation 70)
     // ||T[k]|| <= Gamma[k]
-    for k in 0..N {
-        model.add_constraint(soc_constraint!(
-            norm2(vars.steps[k].t[0], vars.steps[k].t[1], vars.steps[k].t[2])
-                <= vars.steps[k].gamma
-        ));
+    if settings.is_enabled(ConstraintGroup::ThrustMagnitude) {
+        for k in 0..N {
+            model.add_constraint(soc_constraint!(
+                norm2(vars.steps[k].t[0], vars.steps[k].t[1], vars.steps[k].t[2])
+                    <= vars.steps[k].gamma
+            ));
+        }
     }
 
     // Thrust Trust Region ||T[k] - T_bar[k]|| <= eta_T[k]
@@ -512,32 +656,41 @@ fn add_state_constraints(
     }
 
     // Max/Min thrust (Equation 71)
-    for k in 0..N {
-        model.add_constraint(constraint!(vars.steps[k].gamma >= params.t_min_vac));
-        model.add_constraint(constraint!(vars.steps[k].gamma <= params.t_max_vac));
+    if settings.is_enabled(ConstraintGroup::ThrustBounds) {
+        for k in 0..N {
+            refs.thrust_min
+                .push(model.add_constraint(constraint!(vars.steps[k].gamma >= params.t_min_vac)));
+            refs.thrust_max
+                .push(model.add_constraint(constraint!(vars.steps[k].gamma <= params.t_max_vac)));
+        }
     }
 
     // Tilt constraint (Equation 72):
     // Gamma[k] * cos(theta_max) <= e^T T[k].
     // e_hat_up dot T[k] - Gamma[k]*cos(...) >= 0
-    let cos_th = f64::to_radians(params.theta_max).cos();
-    for k in 0..N {
-        let up_dot_t = params.e_hat_up.x * vars.steps[k].t[0]
-            + params.e_hat_up.y * vars.steps[k].t[1]
-            + params.e_hat_up.z * vars.steps[k].t[2];
-
-        model.add_constraint(constraint!(up_dot_t >= cos_th * vars.steps[k].gamma));
+    if settings.is_enabled(ConstraintGroup::Tilt) {
+        let cos_th = params.theta_max().get::<radian>().cos();
+        for k in 0..N {
+            let up_dot_t = params.e_hat_up.x * vars.steps[k].t[0]
+                + params.e_hat_up.y * vars.steps[k].t[1]
+                + params.e_hat_up.z * vars.steps[k].t[2];
+
+            refs.tilt
+                .push(model.add_constraint(constraint!(up_dot_t >= cos_th * vars.steps[k].gamma)));
+        }
     }
 
     // Rate of change of thrust (Equation 73/91):
     // dot_min*dt <= Gamma[k+1] - Gamma[k] <= Tdot_max*dt
-    for k in 0..N - 1 {
-        model.add_constraint(constraint!(
-            vars.steps[k + 1].gamma - vars.steps[k].gamma >= params.tdot_min * vars.dt
-        ));
-        model.add_constraint(constraint!(
-            vars.steps[k + 1].gamma - vars.steps[k].gamma <= params.tdot_max * vars.dt
-        ));
+    if settings.is_enabled(ConstraintGroup::ThrustRate) {
+        for k in 0..N - 1 {
+            refs.thrust_rate_min.push(model.add_constraint(constraint!(
+                vars.steps[k + 1].gamma - vars.steps[k].gamma >= params.tdot_min * vars.dt
+            )));
+            refs.thrust_rate_max.push(model.add_constraint(constraint!(
+                vars.steps[k + 1].gamma - vars.steps[k].gamma <= params.tdot_max * vars.dt
+            )));
+        }
     }
 
     // Time Step Trust Region
@@ -557,22 +710,54 @@ fn add_state_constraints(
     ));
 }
 
+/// Add the user-defined constraints
+pub(super) fn add_custom_constraints(
+    model: &mut impl SolverModel,
+    vars: &DecisionVariables,
+    params: &SimulationParams,
+    settings: &AlgorithmParams,
+    prev_trajectory: &APDGSolution,
+    constraints: &[Arc<dyn TrajectoryConstraint>],
+) {
+    let nodes: Vec<NodeVariables> = vars.steps.iter().map(TimeStepVariables::node).collect();
+    let ctx = ConstraintContext {
+        nodes: &nodes,
+        dt: Expression::from(vars.dt),
+        reference: Some(prev_trajectory),
+        params,
+        algo: settings,
+    };
+    for constraint in constraints {
+        for c in constraint.constraints(&ctx) {
+            model.add_constraint(c);
+        }
+    }
+}
+
 /// Add the slack variable constraints
-fn add_slack_constraints(
+pub(super) fn add_slack_constraints(
     model: &mut impl SolverModel,
     vars: &DecisionVariables,
     params: &SimulationParams,
     settings: &AlgorithmParams,
 ) {
+    let relaxed = settings.is_enabled(ConstraintGroup::Relaxation);
     for k in 0..settings.N {
-        // SC Modifications
-        // ||a_R[k]|| <= k_aR[k] }
-        model.add_constraint(soc_constraint!(
-            norm2(
-                vars.steps[k].aR[0],
-                vars.steps[k].aR[1],
-                vars.steps[k].aR[2]
-            ) <= vars.steps[k].kappa_aR
-        ));
+        if relaxed {
+            // SC Modifications
+            // ||a_R[k]|| <= k_aR[k] }
+            model.add_constraint(soc_constraint!(
+                norm2(
+                    vars.steps[k].aR[0],
+                    vars.steps[k].aR[1],
+                    vars.steps[k].aR[2]
+                ) <= vars.steps[k].kappa_aR
+            ));
+        } else {
+            // Without the relaxation the acceleration is exact, a_R[k] = 0
+            for aR in vars.steps[k].aR.iter() {
+                model.add_constraint(constraint!(*aR == 0.0));
+            }
+        }
     }
 }
@@ -0,0 +1,211 @@
+//! Moving landing targets.
+//!
+//! By default the vehicle lands at the fixed `rf` with velocity `vf`. A
+//! [`LandingTarget`] describes the target state as a function of the time
+//! since ignition instead, e.g. a drifting barge or a platform heaving in a
+//! swell. The final boundary conditions then hold relative to the target
+//! state at the time of flight `tf = (N - 1) * dt`.
+//!
+//! In Problem 5 `dt` is a decision variable, so the target state is
+//! linearised about the time of flight of the reference trajectory:
+//!
+//! ```text
+//! r[N-1] = r_T(tf_bar) + r_T'(tf_bar) * (tf - tf_bar)
+//! v[N-1] = v_T(tf_bar) + v_T'(tf_bar) * (tf - tf_bar)
+//! ```
+//!
+//! which is exact for a fixed or constant velocity target. In Problem 4 `dt`
+//! is fixed and the target is simply evaluated at `tf`. The glide slope cone
+//! has its vertex at the target position at the reference time of flight.
+//!
+//! Planning from part way along a trajectory, e.g. when re-planning, needs
+//! the target clock to start at the current time rather than at ignition,
+//! see [`SimulationParams::advance_target`].
+
+use good_lp::Expression;
+use nalgebra::Vector3;
+
+use super::models::SimulationParams;
+
+/// Where the vehicle has to land.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LandingTarget {
+    /// Land at `rf` with velocity `vf`
+    #[default]
+    Fixed,
+    /// Target starting at `rf` at ignition and moving with constant
+    /// `velocity`, the vehicle lands with `vf` relative to it
+    ConstantVelocity {
+        /// Target velocity
+        /// [m/s]
+        velocity: Vector3<f64>,
+    },
+    /// Target state sampled in time, linearly interpolated between samples
+    /// and held before the first and after the last sample
+    Sampled(Vec<TargetSample>),
+}
+
+/// Target state at one instant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TargetSample {
+    /// Time since ignition
+    /// [s]
+    pub time: f64,
+    /// Position
+    /// [m]
+    pub r: Vector3<f64>,
+    /// Velocity the vehicle has to land with
+    /// [m/s]
+    pub v: Vector3<f64>,
+}
+
+impl LandingTarget {
+    /// Target position [m] and landing velocity [m/s] at time `t` since
+    /// ignition.
+    pub fn state(&self, params: &SimulationParams, t: f64) -> (Vector3<f64>, Vector3<f64>) {
+        match self {
+            LandingTarget::Fixed => (params.rf, params.vf),
+            LandingTarget::ConstantVelocity { velocity } => {
+                (params.rf + velocity * t, params.vf + velocity)
+            }
+            LandingTarget::Sampled(samples) => match segment(samples, t) {
+                Segment::Held(s) => (s.r, s.v),
+                Segment::Between(a, b) => {
+                    let f = (t - a.time) / (b.time - a.time);
+                    (a.r.lerp(&b.r, f), a.v.lerp(&b.v, f))
+                }
+            },
+        }
+    }
+
+    /// Time derivative of [`LandingTarget::state`] at `t`.
+    pub fn rate(&self, t: f64) -> (Vector3<f64>, Vector3<f64>) {
+        match self {
+            LandingTarget::Fixed => (Vector3::zeros(), Vector3::zeros()),
+            LandingTarget::ConstantVelocity { velocity } => (*velocity, Vector3::zeros()),
+            LandingTarget::Sampled(samples) => match segment(samples, t) {
+                Segment::Held(_) => (Vector3::zeros(), Vector3::zeros()),
+                Segment::Between(a, b) => {
+                    let h = b.time - a.time;
+                    ((b.r - a.r) / h, (b.v - a.v) / h)
+                }
+            },
+        }
+    }
+}
+
+impl SimulationParams {
+    /// Start the clock of the landing target `offset` later [s].
+    ///
+    /// Afterwards time zero of the target is `offset` after the original
+    /// ignition, `rf` moves with a constant velocity target and sample times
+    /// are shifted back. A fixed target is unchanged.
+    pub fn advance_target(&mut self, offset: f64) {
+        match &mut self.target {
+            LandingTarget::Fixed => {}
+            LandingTarget::ConstantVelocity { velocity } => {
+                self.rf += *velocity * offset;
+            }
+            LandingTarget::Sampled(samples) => {
+                for sample in samples {
+                    sample.time -= offset;
+                }
+            }
+        }
+    }
+}
+
+enum Segment<'a> {
+    Held(&'a TargetSample),
+    Between(&'a TargetSample, &'a TargetSample),
+}
+
+/// Samples either side of `t`, assumed sorted by time and non-empty.
+fn segment(samples: &[TargetSample], t: f64) -> Segment<'_> {
+    let i = samples.partition_point(|s| s.time <= t);
+    match i {
+        0 => Segment::Held(&samples[0]),
+        i if i == samples.len() => Segment::Held(&samples[i - 1]),
+        i => Segment::Between(&samples[i - 1], &samples[i]),
+    }
+}
+
+/// Target position and velocity at the final node, affine in the time of
+/// flight `tf` and exact at `tf_bar`.
+pub(super) fn final_state(
+    params: &SimulationParams,
+    tf: Expression,
+    tf_bar: f64,
+) -> (Vec<Expression>, Vec<Expression>) {
+    let (r, v) = params.target.state(params, tf_bar);
+    let (r_dot, v_dot) = params.target.rate(tf_bar);
+    let delta = tf - tf_bar;
+    let affine = |x: Vector3<f64>, x_dot: Vector3<f64>| -> Vec<Expression> {
+        (0..3).map(|i| x_dot[i] * delta.clone() + x[i]).collect()
+    };
+    (affine(r, r_dot), affine(v, v_dot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampled_target_interpolates_and_holds() {
+        let sample = |time: f64, x: f64| TargetSample {
+            time,
+            r: Vector3::new(0.0, x, 0.0),
+            v: Vector3::new(0.0, 2.0 * x, 0.0),
+        };
+        let target = LandingTarget::Sampled(vec![sample(0.0, 0.0), sample(10.0, 5.0)]);
+        let params = SimulationParams::default();
+
+        let (r, v) = target.state(&params, 4.0);
+        assert!((r.y - 2.0).abs() < 1e-12);
+        assert!((v.y - 4.0).abs() < 1e-12);
+        let (r_dot, _) = target.rate(4.0);
+        assert!((r_dot.y - 0.5).abs() < 1e-12);
+
+        // Held after the last sample
+        assert_eq!(target.state(&params, 20.0).0.y, 5.0);
+        assert_eq!(target.rate(20.0).0, Vector3::zeros());
+    }
+
+    #[test]
+    fn test_constant_velocity_target_moves_from_rf() {
+        let params = SimulationParams::default();
+        let target = LandingTarget::ConstantVelocity {
+            velocity: Vector3::new(0.0, 3.0, 0.0),
+        };
+
+        let (r, v) = target.state(&params, 10.0);
+        assert_eq!(r, params.rf + Vector3::new(0.0, 30.0, 0.0));
+        assert_eq!(v, params.vf + Vector3::new(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn test_advanced_target_keeps_its_trajectory() {
+        let sample = |time: f64, x: f64| TargetSample {
+            time,
+            r: Vector3::new(0.0, x, 0.0),
+            v: Vector3::zeros(),
+        };
+        for target in [
+            LandingTarget::ConstantVelocity {
+                velocity: Vector3::new(0.0, 3.0, 0.0),
+            },
+            LandingTarget::Sampled(vec![sample(0.0, 0.0), sample(10.0, 5.0)]),
+        ] {
+            let params = SimulationParams::builder().target(target).build();
+            let mut advanced = params.clone();
+            advanced.advance_target(4.0);
+
+            let (r, v) = params.target.state(&params, 7.0);
+            let (r_adv, v_adv) = advanced.target.state(&advanced, 3.0);
+            assert!((r - r_adv).norm() < 1e-12);
+            assert!((v - v_adv).norm() < 1e-12);
+        }
+    }
+}
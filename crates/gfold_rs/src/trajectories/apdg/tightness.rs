@@ -0,0 +1,138 @@
+//! Tightness of the convex relaxations in a solution.
+//!
+//! Two relaxations make the subproblems convex. `||T[k]|| = Γ[k]` is relaxed
+//! to `||T[k]|| <= Γ[k]`, which lossless convexification guarantees is tight
+//! at the optimum of the ideal problem but not after discretisation, with
+//! trust regions or with user constraints. The virtual acceleration `a_R[k]`
+//! keeps every subproblem feasible and is penalised towards zero.
+//!
+//! A solution is only physically realisable when both gaps are negligible.
+//! Otherwise the engine cannot produce the planned acceleration, either
+//! because the throttle setting `Γ` does not match the thrust vector or
+//! because part of the acceleration comes from `a_R`.
+
+use super::APDGSolution;
+
+/// Relaxation gaps at a single node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeTightness {
+    /// Thrust magnitude gap `Γ[k] - ||T[k]||` [N]
+    pub thrust_gap: f64,
+    /// Thrust magnitude gap relative to `Γ[k]`
+    pub thrust_gap_relative: f64,
+    /// Virtual acceleration `||a_R[k]||` [m/s^2]
+    pub aR: f64,
+    /// Virtual acceleration relative to the thrust acceleration `Γ[k] / m[k]`
+    pub aR_relative: f64,
+}
+
+/// Per-node relaxation gaps of a solution.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TightnessReport {
+    /// Gaps at every node
+    pub nodes: Vec<NodeTightness>,
+}
+
+impl TightnessReport {
+    /// Largest relative thrust magnitude gap over all nodes.
+    pub fn max_thrust_gap_relative(&self) -> f64 {
+        self.nodes
+            .iter()
+            .map(|n| n.thrust_gap_relative.abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// Largest relative virtual acceleration over all nodes.
+    pub fn max_aR_relative(&self) -> f64 {
+        self.nodes.iter().map(|n| n.aR_relative).fold(0.0, f64::max)
+    }
+
+    /// Nodes where either relative gap exceeds `tolerance`.
+    pub fn loose_nodes(&self, tolerance: f64) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.thrust_gap_relative.abs() > tolerance || n.aR_relative > tolerance)
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    /// Whether both relaxations are tight to within `tolerance` (relative) at
+    /// every node.
+    pub fn is_realisable(&self, tolerance: f64) -> bool {
+        self.loose_nodes(tolerance).is_empty()
+    }
+}
+
+/// `value / scale`, infinite for a non-zero value over a zero scale.
+pub(super) fn relative(value: f64, scale: f64) -> f64 {
+    if scale > 0.0 {
+        value / scale
+    } else if value == 0.0 {
+        0.0
+    } else {
+        f64::INFINITY
+    }
+}
+
+impl APDGSolution {
+    /// Relaxation gaps at every node.
+    pub fn tightness(&self) -> TightnessReport {
+        let nodes = self
+            .steps()
+            .iter()
+            .map(|s| {
+                let thrust_gap = s.gamma - s.t.norm();
+                let aR = s.aR.norm();
+                NodeTightness {
+                    thrust_gap,
+                    thrust_gap_relative: relative(thrust_gap, s.gamma),
+                    aR,
+                    aR_relative: relative(aR, s.gamma / s.m),
+                }
+            })
+            .collect();
+        TightnessReport { nodes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::*;
+    use crate::trajectories::APDGSolutionTimeStep;
+
+    #[test]
+    fn test_reports_loose_nodes() {
+        let step = |t: f64, gamma: f64, aR: f64| {
+            APDGSolutionTimeStep::builder()
+                .r(Vector3::zeros())
+                .v(Vector3::zeros())
+                .a(Vector3::zeros())
+                .m(10_000.0)
+                .t(Vector3::new(t, 0.0, 0.0))
+                .gamma(gamma)
+                .aR(Vector3::new(0.0, aR, 0.0))
+                .build()
+        };
+        let steps = vec![
+            step(150_000.0, 150_000.0, 0.0),
+            // Thrust vector 20 % short of the throttle setting
+            step(120_000.0, 150_000.0, 0.0),
+            // Virtual acceleration of 10 % of the 15 m/s^2 thrust acceleration
+            step(150_000.0, 150_000.0, 1.5),
+        ];
+        let solution = APDGSolution::builder().steps(steps).dt(1.0).build();
+
+        let report = solution.tightness();
+        assert!((report.max_thrust_gap_relative() - 0.2).abs() < 1e-12);
+        assert!((report.max_aR_relative() - 0.1).abs() < 1e-12);
+        assert_eq!(report.loose_nodes(0.15), [1]);
+        assert_eq!(report.loose_nodes(1e-6), [1, 2]);
+        assert!(!report.is_realisable(1e-6));
+        assert!(report.is_realisable(0.5));
+    }
+}
@@ -0,0 +1,415 @@
+//! Input validation of [`Settings`] and sanity checks on solver output.
+//!
+//! Invalid parameters usually do not make the convex subproblems fail
+//! outright, instead they produce an infeasibility certificate or NaNs deep
+//! into the SC loop. Checking up front lets every problem be reported at once
+//! with the field it came from.
+
+use std::fmt;
+
+use nalgebra::Vector3;
+
+use super::{
+    mission::Mission,
+    models::{AlgorithmParams, InitialGuess, SimulationParams},
+    target::LandingTarget,
+    APDGSolution, Error, Settings,
+};
+
+/// Allowed deviation from unit length for direction vectors.
+const UNIT_VECTOR_TOLERANCE: f64 = 1e-6;
+
+/// A single invalid field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// Path of the field, e.g. `simulation_settings.m_0`
+    pub field: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Every problem found by [`Settings::validate`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationErrors(Vec<FieldError>);
+
+impl ValidationErrors {
+    /// Individual field errors, in the order they were found.
+    pub fn errors(&self) -> &[FieldError] {
+        &self.0
+    }
+
+    /// Whether the given field has an error.
+    pub fn contains(&self, field: &str) -> bool {
+        self.0.iter().any(|e| e.field == field)
+    }
+
+    pub(super) fn push(&mut self, section: &str, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: format!("{section}.{field}"),
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "  {e}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Settings {
+    /// Check the settings for values the solver cannot handle.
+    ///
+    /// Returns [`Error::InvalidSettings`] listing every invalid field.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut errors = ValidationErrors::default();
+        validate_simulation(self.simulation_settings(), &mut errors);
+        validate_algorithm(self.solver_settings(), &mut errors);
+        match self.initial_guess() {
+            InitialGuess::Provided(solution)
+                if solution.num_steps() < 2 || !solution.dt.is_finite() || solution.dt <= 0.0 =>
+            {
+                errors.push(
+                    "settings",
+                    "initial_guess",
+                    "provided solution needs at least 2 nodes and a positive dt",
+                );
+            }
+            InitialGuess::LogMass if !self.solver_settings().cost_terms.is_empty() => {
+                errors.push(
+                    "settings",
+                    "initial_guess",
+                    "log-mass guess does not support cost terms",
+                );
+            }
+            _ => {}
+        }
+        if let Mission::Hop(hop) = self.simulation_settings().mission {
+            let N = self.solver_settings().N;
+            if hop.apex_node.is_some_and(|k| k >= N) {
+                errors.push(
+                    "simulation_settings",
+                    "mission.apex_node",
+                    format!("must be less than N ({N}), got {:?}", hop.apex_node),
+                );
+            }
+        }
+
+        if errors.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidSettings(errors))
+        }
+    }
+}
+
+fn validate_simulation(p: &SimulationParams, errors: &mut ValidationErrors) {
+    const SECTION: &str = "simulation_settings";
+    let mut fail = |field: &str, message: String| errors.push(SECTION, field, message);
+
+    let scalars = [
+        ("rho", p.rho),
+        ("p_amb", p.p_amb),
+        ("g_0", p.g_0),
+        ("m_dry", p.m_dry),
+        ("m_0", p.m_0),
+        ("gamma_0_vac", p.gamma_0_vac),
+        ("a_nozzle", p.a_nozzle),
+        ("i_sp", p.i_sp),
+        ("t_min_vac", p.t_min_vac),
+        ("t_max_vac", p.t_max_vac),
+        ("tdot_min", p.tdot_min),
+        ("tdot_max", p.tdot_max),
+        ("theta_max", p.theta_max),
+        ("gamma_gs", p.gamma_gs),
+        ("s_d", p.s_d),
+        ("c_d", p.c_d),
+    ];
+    let vectors = [
+        ("g_vec", p.g_vec),
+        ("r0", p.r0),
+        ("rf", p.rf),
+        ("v0", p.v0),
+        ("vf", p.vf),
+        ("n_hat0", p.n_hat0),
+        ("n_hatf", p.n_hatf),
+        ("e_hat_up", p.e_hat_up),
+    ];
+    let mut all_finite = true;
+    for (field, value) in scalars {
+        if !value.is_finite() {
+            fail(field, format!("must be finite, got {value}"));
+            all_finite = false;
+        }
+    }
+    for (field, value) in vectors {
+        if !value.iter().all(|x| x.is_finite()) {
+            fail(field, "all components must be finite".to_string());
+            all_finite = false;
+        }
+    }
+    // Range checks are meaningless on NaN
+    if !all_finite {
+        return;
+    }
+
+    for (field, value) in [
+        ("m_dry", p.m_dry),
+        ("i_sp", p.i_sp),
+        ("g_0", p.g_0),
+        ("t_max_vac", p.t_max_vac),
+    ] {
+        if value <= 0.0 {
+            fail(field, format!("must be positive, got {value}"));
+        }
+    }
+    for (field, value) in [
+        ("rho", p.rho),
+        ("p_amb", p.p_amb),
+        ("a_nozzle", p.a_nozzle),
+        ("t_min_vac", p.t_min_vac),
+        ("s_d", p.s_d),
+        ("c_d", p.c_d),
+    ] {
+        if value < 0.0 {
+            fail(field, format!("must not be negative, got {value}"));
+        }
+    }
+
+    if p.m_0 <= p.m_dry {
+        fail(
+            "m_0",
+            format!("must be greater than m_dry ({}), got {}", p.m_dry, p.m_0),
+        );
+    }
+    if p.t_min_vac >= p.t_max_vac {
+        fail(
+            "t_min_vac",
+            format!(
+                "must be less than t_max_vac ({}), got {}",
+                p.t_max_vac, p.t_min_vac
+            ),
+        );
+    }
+    if !(p.t_min_vac..=p.t_max_vac).contains(&p.gamma_0_vac) {
+        fail(
+            "gamma_0_vac",
+            format!(
+                "must be within [t_min_vac, t_max_vac] = [{}, {}], got {}",
+                p.t_min_vac, p.t_max_vac, p.gamma_0_vac
+            ),
+        );
+    }
+    if p.tdot_min >= 0.0 {
+        fail("tdot_min", format!("must be negative, got {}", p.tdot_min));
+    }
+    if p.tdot_max <= 0.0 {
+        fail("tdot_max", format!("must be positive, got {}", p.tdot_max));
+    }
+    if p.theta_max <= 0.0 || p.theta_max > 180.0 {
+        fail(
+            "theta_max",
+            format!("must be in (0, 180] deg, got {}", p.theta_max),
+        );
+    }
+    if !(0.0..90.0).contains(&p.gamma_gs) {
+        fail(
+            "gamma_gs",
+            format!("must be in [0, 90) deg, got {}", p.gamma_gs),
+        );
+    }
+    for (field, value) in [
+        ("n_hat0", p.n_hat0),
+        ("n_hatf", p.n_hatf),
+        ("e_hat_up", p.e_hat_up),
+    ] {
+        let norm = value.norm();
+        if (norm - 1.0).abs() > UNIT_VECTOR_TOLERANCE {
+            fail(field, format!("must be a unit vector, has norm {norm}"));
+        }
+    }
+
+    if let Mission::Hop(hop) = p.mission {
+        if p.v0 != Vector3::zeros() {
+            fail("v0", "a hop starts from rest, must be zero".to_string());
+        }
+        for (field, value) in [
+            ("mission.apex_altitude", hop.apex_altitude),
+            ("mission.max_altitude", hop.max_altitude),
+        ] {
+            if value.is_some_and(|h| !h.is_finite()) {
+                fail(field, format!("must be finite, got {value:?}"));
+            }
+        }
+        if let (Some(apex), Some(max)) = (hop.apex_altitude, hop.max_altitude) {
+            if apex > max {
+                fail(
+                    "mission.apex_altitude",
+                    format!("must not exceed max_altitude ({max}), got {apex}"),
+                );
+            }
+        }
+    }
+
+    match &p.target {
+        LandingTarget::Fixed => {}
+        LandingTarget::ConstantVelocity { velocity } => {
+            if !velocity.iter().all(|x| x.is_finite()) {
+                fail(
+                    "target.velocity",
+                    "all components must be finite".to_string(),
+                );
+            }
+        }
+        LandingTarget::Sampled(samples) => {
+            if samples.is_empty() {
+                fail("target", "needs at least one sample".to_string());
+            }
+            for (i, s) in samples.iter().enumerate() {
+                let finite =
+                    s.time.is_finite() && s.r.iter().chain(s.v.iter()).all(|x| x.is_finite());
+                if !finite {
+                    fail(
+                        &format!("target[{i}]"),
+                        "time and all components must be finite".to_string(),
+                    );
+                }
+            }
+            if samples.windows(2).any(|w| w[1].time <= w[0].time) {
+                fail(
+                    "target",
+                    "sample times must be strictly increasing".to_string(),
+                );
+            }
+        }
+    }
+}
+
+fn validate_algorithm(p: &AlgorithmParams, errors: &mut ValidationErrors) {
+    const SECTION: &str = "solver_settings";
+    let mut fail = |field: &str, message: String| errors.push(SECTION, field, message);
+
+    for (field, value) in [("tf_guess", p.tf_guess), ("dt", p.dt)] {
+        if !(value.is_finite() && value > 0.0) {
+            fail(field, format!("must be positive and finite, got {value}"));
+        }
+    }
+    for (field, value) in [
+        ("w_mf", p.w_mf),
+        ("w_eta_dt", p.w_eta_dt),
+        ("w_eta_T", p.w_eta_T),
+        ("w_kappa_aR", p.w_kappa_aR),
+        ("sc_tolerance", p.sc_tolerance),
+    ] {
+        if !(value.is_finite() && value >= 0.0) {
+            fail(
+                field,
+                format!("must be non-negative and finite, got {value}"),
+            );
+        }
+    }
+    if let Some(tolerance) = p.relaxation_tolerance {
+        if !(tolerance.is_finite() && tolerance > 0.0) {
+            fail(
+                "relaxation_tolerance",
+                format!("must be positive and finite, got {tolerance}"),
+            );
+        }
+    }
+    for (i, term) in p.cost_terms.iter().enumerate() {
+        let weight = term.weight();
+        if !(weight.is_finite() && weight >= 0.0) {
+            fail(
+                &format!("cost_terms[{i}]"),
+                format!("weight must be non-negative and finite, got {weight}"),
+            );
+        }
+    }
+    if p.N < 2 {
+        fail("N", format!("must be at least 2, got {}", p.N));
+    }
+    if p.n_sc == 0 {
+        fail("n_sc", "must be at least 1".to_string());
+    }
+}
+
+/// Fail with [`Error::NumericError`] if a solution contains NaN or infinity.
+pub(super) fn check_finite(solution: &APDGSolution, stage: &str) -> Result<(), Error> {
+    if !solution.dt.is_finite() {
+        return Err(Error::NumericError(format!(
+            "{stage}: non-finite time step {}",
+            solution.dt
+        )));
+    }
+
+    for (k, s) in solution.steps.iter().enumerate() {
+        let fields = [
+            ("r", s.r.iter().all(|x| x.is_finite())),
+            ("v", s.v.iter().all(|x| x.is_finite())),
+            ("a", s.a.iter().all(|x| x.is_finite())),
+            ("m", s.m.is_finite()),
+            ("t", s.t.iter().all(|x| x.is_finite())),
+            ("gamma", s.gamma.is_finite()),
+            ("aR", s.aR.iter().all(|x| x.is_finite())),
+        ];
+        if let Some((field, _)) = fields.iter().find(|(_, finite)| !finite) {
+            return Err(Error::NumericError(format!(
+                "{stage}: non-finite {field} at node {k}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_default_settings_are_valid() {
+        assert!(Settings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_invalid_field() {
+        let settings = Settings::builder()
+            .simulation_settings(
+                SimulationParams::builder()
+                    .m_0(5_000.0)
+                    .tdot_min(10.0)
+                    .theta_max(-1.0)
+                    .e_hat_up(Vector3::new(2.0, 0.0, 0.0))
+                    .build(),
+            )
+            .solver_settings(AlgorithmParams::builder().N(1).build())
+            .build();
+
+        let Err(Error::InvalidSettings(errors)) = settings.validate() else {
+            panic!("expected invalid settings");
+        };
+        for field in [
+            "simulation_settings.m_0",
+            "simulation_settings.tdot_min",
+            "simulation_settings.theta_max",
+            "simulation_settings.e_hat_up",
+            "solver_settings.N",
+        ] {
+            assert!(errors.contains(field), "missing {field}");
+        }
+        assert_eq!(errors.errors().len(), 5);
+    }
+}
@@ -0,0 +1,275 @@
+//! Check a solution against the constraints of the landing problem.
+//!
+//! This is used to validate solutions loaded from disk or produced elsewhere,
+//! independent of the solver that generated them. Every constraint is scaled
+//! by a natural magnitude (maximum thrust, initial mass, ...) so a single
+//! relative tolerance can be used throughout.
+
+use std::fmt;
+
+use uom::si::angle::radian;
+
+use super::{
+    models::{ConstraintGroup, SimulationParams},
+    APDGSolution,
+};
+
+/// A single constraint that a solution does not satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintViolation {
+    /// Name of the violated constraint
+    pub constraint: &'static str,
+    /// Node index, `None` for constraints on the whole trajectory
+    pub node: Option<usize>,
+    /// Amount by which the constraint is violated, in its own units
+    pub amount: f64,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.node {
+            Some(k) => write!(
+                f,
+                "{} violated at node {k} by {:.6e}",
+                self.constraint, self.amount
+            ),
+            None => write!(f, "{} violated by {:.6e}", self.constraint, self.amount),
+        }
+    }
+}
+
+impl APDGSolution {
+    /// List every constraint violated by more than `tolerance` (relative).
+    ///
+    /// Groups the solution records as left out of the problem, see
+    /// [`APDGSolution::constraint_groups`], are not checked. A solution
+    /// without that record is checked against every group.
+    pub fn check_constraints(
+        &self,
+        params: &SimulationParams,
+        tolerance: f64,
+    ) -> Vec<ConstraintViolation> {
+        let steps = self.steps();
+        let (Some(first), Some(last)) = (steps.first(), steps.last()) else {
+            return Vec::new();
+        };
+
+        let enabled = |group: ConstraintGroup| {
+            self.constraint_groups()
+                .is_none_or(|groups| groups.contains(&group))
+        };
+        let mut violations = Vec::new();
+        // `group` is `None` for the constraints that are always enforced
+        let mut check = |group: Option<ConstraintGroup>,
+                         constraint: &'static str,
+                         node: Option<usize>,
+                         amount: f64,
+                         scale: f64| {
+            if group.is_none_or(enabled) && amount > tolerance * scale.max(1.0) {
+                violations.push(ConstraintViolation {
+                    constraint,
+                    node,
+                    amount,
+                });
+            }
+        };
+        let r_scale = params.r0.norm();
+        let v_scale = params.v0.norm();
+
+        // Boundary conditions
+        check(
+            None,
+            "initial mass",
+            Some(0),
+            (first.m - params.m_0).abs(),
+            params.m_0,
+        );
+        check(
+            None,
+            "initial position",
+            Some(0),
+            (first.r - params.r0).norm(),
+            r_scale,
+        );
+        check(
+            None,
+            "initial velocity",
+            Some(0),
+            (first.v - params.v0).norm(),
+            v_scale,
+        );
+        let k_end = steps.len() - 1;
+        let tf = self.duration();
+        let (r_target, v_target) = params.target.state(params, tf);
+        check(
+            Some(ConstraintGroup::FinalPosition),
+            "final position",
+            Some(k_end),
+            (last.r - r_target).norm(),
+            r_scale,
+        );
+        check(
+            Some(ConstraintGroup::FinalVelocity),
+            "final velocity",
+            Some(k_end),
+            (last.v - v_target).norm(),
+            v_scale,
+        );
+
+        let cos_th = params.theta_max().get::<radian>().cos();
+        let cos_gs = params.gamma_gs().get::<radian>().cos();
+        for (k, s) in steps.iter().enumerate() {
+            let gs_offset = s.r
+                - params
+                    .mission
+                    .glide_slope_vertex(params, k, steps.len(), tf);
+            let k = Some(k);
+            check(
+                Some(ConstraintGroup::MassFloor),
+                "mass floor",
+                k,
+                params.m_dry - s.m,
+                params.m_0,
+            );
+            check(
+                Some(ConstraintGroup::ThrustMagnitude),
+                "thrust magnitude",
+                k,
+                s.t.norm() - s.gamma,
+                params.t_max_vac,
+            );
+            check(
+                Some(ConstraintGroup::ThrustBounds),
+                "minimum thrust",
+                k,
+                params.t_min_vac - s.gamma,
+                params.t_max_vac,
+            );
+            check(
+                Some(ConstraintGroup::ThrustBounds),
+                "maximum thrust",
+                k,
+                s.gamma - params.t_max_vac,
+                params.t_max_vac,
+            );
+            check(
+                Some(ConstraintGroup::Tilt),
+                "tilt",
+                k,
+                cos_th * s.gamma - params.e_hat_up.dot(&s.t),
+                params.t_max_vac,
+            );
+            check(
+                Some(ConstraintGroup::GlideSlope),
+                "glide slope",
+                k,
+                gs_offset.norm() * cos_gs - params.e_hat_up.dot(&gs_offset),
+                r_scale,
+            );
+        }
+
+        for (k, pair) in steps.windows(2).enumerate() {
+            let rate = (pair[1].gamma - pair[0].gamma) / self.dt();
+            check(
+                Some(ConstraintGroup::ThrustRate),
+                "thrust rate",
+                Some(k),
+                params.tdot_min - rate,
+                params.tdot_max,
+            );
+            check(
+                Some(ConstraintGroup::ThrustRate),
+                "thrust rate",
+                Some(k),
+                rate - params.tdot_max,
+                params.tdot_max,
+            );
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectories::{
+        apdg::{guess, sucessive},
+        APDGSolutionTimeStep, AlgorithmParams,
+    };
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_reports_thrust_and_boundary_violations() {
+        let params = SimulationParams::builder().build();
+        let step = |r: Vector3<f64>, v: Vector3<f64>, gamma: f64| {
+            APDGSolutionTimeStep::builder()
+                .r(r)
+                .v(v)
+                .a(Vector3::zeros())
+                .m(params.m_0)
+                .t(params.e_hat_up * gamma)
+                .gamma(gamma)
+                .aR(Vector3::zeros())
+                .build()
+        };
+        let steps = vec![
+            step(params.r0, params.v0, params.gamma_0_vac),
+            // Above the thrust limit
+            step(params.r0 / 2.0, params.v0 / 2.0, 1.1 * params.t_max_vac),
+            // Misses the landing site
+            step(Vector3::new(10.0, 0.0, 0.0), params.vf, params.gamma_0_vac),
+        ];
+        let solution = APDGSolution::builder().steps(steps).dt(5.0).build();
+
+        let violations = solution.check_constraints(&params, 1e-6);
+        let names: Vec<_> = violations.iter().map(|v| (v.constraint, v.node)).collect();
+
+        assert!(names.contains(&("maximum thrust", Some(1))));
+        assert!(names.contains(&("final position", Some(2))));
+        assert!(!names.iter().any(|(c, _)| *c == "initial position"));
+    }
+
+    #[test]
+    fn test_disabled_group_is_left_out_of_both_problems() {
+        // A lateral offset the vehicle cannot fly out within 2 degrees of tilt
+        let params = SimulationParams::builder()
+            .r0(Vector3::new(500.0, 100.0, 0.0))
+            .v0(Vector3::new(-30.0, 0.0, 0.0))
+            .theta_max(2.0)
+            .build();
+        let algo = AlgorithmParams::builder()
+            .N(20)
+            .disabled_constraints([ConstraintGroup::Tilt].into())
+            .build();
+
+        let guess = guess::problem::APDGProblem::new(params.clone(), algo.clone(), Vec::new())
+            .solve()
+            .unwrap();
+        let step =
+            sucessive::problem::APDGProblem::new(params.clone(), algo, guess.clone(), Vec::new())
+                .solve()
+                .unwrap();
+
+        for solution in [guess, step] {
+            let groups = solution.constraint_groups().unwrap();
+            assert!(!groups.contains(&ConstraintGroup::Tilt));
+
+            let is_tilt = |v: &ConstraintViolation| v.constraint == "tilt";
+            assert!(!solution
+                .check_constraints(&params, 1e-6)
+                .iter()
+                .any(is_tilt));
+
+            // Without the record the tilt limit is checked, and broken
+            let unrecorded = APDGSolution {
+                constraint_groups: None,
+                ..solution
+            };
+            assert!(unrecorded
+                .check_constraints(&params, 1e-6)
+                .iter()
+                .any(is_tilt));
+        }
+    }
+}
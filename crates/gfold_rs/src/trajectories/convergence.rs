@@ -2,12 +2,26 @@
 
 use std::{io::Write, path::Path};
 
+/// Per-iteration change of the solution, as `log10` of the difference to the
+/// previous iterate.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(non_snake_case)]
 pub struct ConvergenceHistory {
+    /// Position difference
     pub pos: Vec<f64>,
+    /// Velocity difference
     pub vel: Vec<f64>,
+    /// Thrust difference
     pub thrust: Vec<f64>,
+    /// Virtual acceleration difference
     pub aR: Vec<f64>,
+    /// Whether the SC loop met `sc_tolerance` before running out of iterations.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub converged: bool,
+    /// Whether the SC loop was stopped early by a wall-clock budget.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub truncated: bool,
 }
 
 impl ConvergenceHistory {
@@ -16,6 +30,11 @@ impl ConvergenceHistory {
         self.pos.len()
     }
 
+    /// Whether no iteration was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.pos.is_empty()
+    }
+
     /// Get the number of iterations.
     pub fn iterations(&self) -> impl Iterator<Item = usize> + '_ {
         1..=self.len()
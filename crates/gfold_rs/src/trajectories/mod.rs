@@ -4,6 +4,20 @@
 mod apdg;
 mod convergence;
 
-pub use apdg::models::{AlgorithmParams, SimulationParams};
-pub use apdg::{APDGProblemSolver, APDGSolution, APDGSolutionTimeStep, Settings};
+pub use apdg::background::{SolveHandle, SolveProgress};
+pub use apdg::constraints::{ConstraintContext, NodeVariables, TrajectoryConstraint};
+pub use apdg::duals::{ActiveConstraint, BoundaryDuals, ConstraintDuals, ConstraintFamily};
+pub use apdg::mission::{HopMission, Mission};
+pub use apdg::models::{AlgorithmParams, ConstraintGroup, InitialGuess, SimulationParams};
+pub use apdg::objective::CostTerm;
+pub use apdg::phases::{MultiPhaseSolution, Phase, PhaseKind, PhaseSolution};
+pub use apdg::propagation::{Integrator, NodeDefect, PropagationReport};
+pub use apdg::replan::Replanner;
+pub use apdg::robust::{speed_bound, MarginPolicy, NodeMargins, RobustPlan, Uncertainty};
+pub use apdg::screening::FeasibilityReport;
+pub use apdg::target::{LandingTarget, TargetSample};
+pub use apdg::tightness::{NodeTightness, TightnessReport};
+pub use apdg::validation::{FieldError, ValidationErrors};
+pub use apdg::verification::ConstraintViolation;
+pub use apdg::{APDGProblemSolver, APDGSolution, APDGSolutionTimeStep, Error, Settings};
 pub use convergence::ConvergenceHistory;
@@ -0,0 +1,263 @@
+//! Unit-safe access to configuration and solution values.
+//!
+//! Internally every quantity is a raw `f64` in the unit noted on its field,
+//! which keeps the solver formulation simple. The accessors here share the
+//! field name and return a [`uom`] quantity instead, while the `set_*`
+//! methods take one, so a value can no longer be given in the wrong unit:
+//!
+//! ```
+//! use gfold_rs::trajectories::SimulationParams;
+//! use gfold_rs::units::{angle::radian, Angle};
+//!
+//! let mut params = SimulationParams::default();
+//! params.set_theta_max(Angle::new::<radian>(0.2));
+//!
+//! // Stored in degrees, read back in any angle unit
+//! assert!((params.theta_max().get::<radian>() - 0.2).abs() < 1e-12);
+//! ```
+
+#![allow(non_snake_case)]
+
+use nalgebra::Vector3;
+use uom::si::{Quantity, ISQ, SI};
+use uom::typenum::{N3, P1, Z0};
+
+use crate::rocket_config::RocketConfig;
+use crate::trajectories::{APDGSolutionTimeStep, SimulationParams};
+
+pub use uom::si::f64::{
+    Acceleration, Angle, Area, Force, Length, Mass, MassDensity, Pressure, Time, Velocity,
+};
+pub use uom::si::{
+    acceleration, angle, area, force, length, mass, mass_density, pressure, time, velocity,
+};
+
+use uom::si::{
+    acceleration::meter_per_second_squared, angle::degree, area::square_meter, force::newton,
+    length::meter, mass::kilogram, mass_density::kilogram_per_cubic_meter, pressure::pascal,
+    time::second, velocity::meter_per_second,
+};
+
+/// Rate of change of force [N/s].
+///
+/// `uom` has no named quantity for this, the value is always in SI base units.
+pub type ForceRate = Quantity<ISQ<P1, P1, N3, Z0, Z0, Z0, Z0>, SI<f64>, f64>;
+
+/// Create a [`ForceRate`] from a value in newtons per second.
+pub fn newtons_per_second(value: f64) -> ForceRate {
+    Force::new::<newton>(value) / Time::new::<second>(1.0)
+}
+
+/// Typed getters and setters for scalar fields stored in a fixed unit.
+macro_rules! scalar_quantities {
+    ($ty:ty { $($(#[$doc:meta])* $field:ident / $set:ident: $q:ident in $unit:ident;)* }) => {
+        impl $ty {
+            $(
+                $(#[$doc])*
+                pub fn $field(&self) -> $q {
+                    $q::new::<$unit>(self.$field)
+                }
+
+                #[doc = concat!("Set `", stringify!($field), "` from a typed quantity.")]
+                pub fn $set(&mut self, value: $q) {
+                    self.$field = value.get::<$unit>();
+                }
+            )*
+        }
+    };
+}
+
+/// Typed getters and setters for vector fields stored in a fixed unit.
+macro_rules! vector_quantities {
+    ($ty:ty { $($(#[$doc:meta])* $field:ident / $set:ident: $q:ident in $unit:ident;)* }) => {
+        impl $ty {
+            $(
+                $(#[$doc])*
+                pub fn $field(&self) -> Vector3<$q> {
+                    self.$field.map($q::new::<$unit>)
+                }
+
+                #[doc = concat!("Set `", stringify!($field), "` from typed components.")]
+                pub fn $set(&mut self, value: Vector3<$q>) {
+                    self.$field = value.map(|q| q.get::<$unit>());
+                }
+            )*
+        }
+    };
+}
+
+scalar_quantities!(SimulationParams {
+    /// Ambient fluid density
+    rho / set_rho: MassDensity in kilogram_per_cubic_meter;
+    /// Ambient pressure
+    p_amb / set_p_amb: Pressure in pascal;
+    /// Standard gravity
+    g_0 / set_g_0: Acceleration in meter_per_second_squared;
+    /// Dry mass of the vehicle
+    m_dry / set_m_dry: Mass in kilogram;
+    /// Initial total mass
+    m_0 / set_m_0: Mass in kilogram;
+    /// Initial thrust guess
+    gamma_0_vac / set_gamma_0_vac: Force in newton;
+    /// Nozzle exit area
+    a_nozzle / set_a_nozzle: Area in square_meter;
+    /// Specific impulse
+    i_sp / set_i_sp: Time in second;
+    /// Minimum vacuum thrust
+    t_min_vac / set_t_min_vac: Force in newton;
+    /// Maximum vacuum thrust
+    t_max_vac / set_t_max_vac: Force in newton;
+    /// Maximum tilt angle
+    theta_max / set_theta_max: Angle in degree;
+    /// Glide slope angle
+    gamma_gs / set_gamma_gs: Angle in degree;
+    /// Reference area for drag
+    s_d / set_s_d: Area in square_meter;
+});
+
+vector_quantities!(SimulationParams {
+    /// Gravity vector
+    g_vec / set_g_vec: Acceleration in meter_per_second_squared;
+    /// Initial position
+    r0 / set_r0: Length in meter;
+    /// Final position
+    rf / set_rf: Length in meter;
+    /// Initial velocity
+    v0 / set_v0: Velocity in meter_per_second;
+    /// Final velocity
+    vf / set_vf: Velocity in meter_per_second;
+});
+
+impl SimulationParams {
+    /// Minimum thrust derivative
+    pub fn tdot_min(&self) -> ForceRate {
+        newtons_per_second(self.tdot_min)
+    }
+
+    /// Set `tdot_min` from a typed quantity.
+    pub fn set_tdot_min(&mut self, value: ForceRate) {
+        self.tdot_min = value.value;
+    }
+
+    /// Maximum thrust derivative
+    pub fn tdot_max(&self) -> ForceRate {
+        newtons_per_second(self.tdot_max)
+    }
+
+    /// Set `tdot_max` from a typed quantity.
+    pub fn set_tdot_max(&mut self, value: ForceRate) {
+        self.tdot_max = value.value;
+    }
+}
+
+scalar_quantities!(RocketConfig {
+    /// Dry mass
+    m_dry / set_m_dry: Mass in kilogram;
+    /// Propellant mass
+    m_fuel / set_m_fuel: Mass in kilogram;
+    /// Minimum vacuum thrust
+    t_min_vac / set_t_min_vac: Force in newton;
+    /// Maximum vacuum thrust
+    t_max_vac / set_t_max_vac: Force in newton;
+    /// Specific impulse
+    i_sp / set_i_sp: Time in second;
+    /// Nozzle exit area
+    a_nozzle / set_a_nozzle: Area in square_meter;
+    /// Maximum tilt angle
+    theta_max / set_theta_max: Angle in degree;
+    /// Reference area for drag
+    s_d / set_s_d: Area in square_meter;
+});
+
+impl RocketConfig {
+    /// Minimum thrust derivative
+    pub fn tdot_min(&self) -> ForceRate {
+        newtons_per_second(self.tdot_min)
+    }
+
+    /// Set `tdot_min` from a typed quantity.
+    pub fn set_tdot_min(&mut self, value: ForceRate) {
+        self.tdot_min = value.value;
+    }
+
+    /// Maximum thrust derivative
+    pub fn tdot_max(&self) -> ForceRate {
+        newtons_per_second(self.tdot_max)
+    }
+
+    /// Set `tdot_max` from a typed quantity.
+    pub fn set_tdot_max(&mut self, value: ForceRate) {
+        self.tdot_max = value.value;
+    }
+
+    /// Initial total mass (dry mass + propellant), see [`RocketConfig::m_0`]
+    /// for the raw value
+    pub fn m_0_mass(&self) -> Mass {
+        self.m_dry() + self.m_fuel()
+    }
+}
+
+scalar_quantities!(APDGSolutionTimeStep {
+    /// Mass
+    m / set_m: Mass in kilogram;
+    /// Thrust magnitude
+    gamma / set_gamma: Force in newton;
+});
+
+vector_quantities!(APDGSolutionTimeStep {
+    /// Position
+    r / set_r: Length in meter;
+    /// Velocity
+    v / set_v: Velocity in meter_per_second;
+    /// Acceleration
+    a / set_a: Acceleration in meter_per_second_squared;
+    /// Thrust
+    t / set_t: Force in newton;
+    /// Acceleration relaxation term
+    aR / set_aR: Acceleration in meter_per_second_squared;
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::angle::radian;
+
+    #[test]
+    fn test_angles_are_stored_in_degrees() {
+        let mut params = SimulationParams::default();
+        params.set_theta_max(Angle::new::<radian>(std::f64::consts::FRAC_PI_6));
+
+        assert!((params.theta_max - 30.0).abs() < 1e-12);
+        assert!((params.theta_max().get::<degree>() - 30.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_thrust_rate_round_trip() {
+        let mut params = SimulationParams::default();
+        let rate = Force::new::<force::kilonewton>(50.0) / Time::new::<second>(1.0);
+        params.set_tdot_max(rate);
+
+        assert_eq!(params.tdot_max, 50_000.0);
+        assert_eq!(params.tdot_max().value, 50_000.0);
+    }
+
+    #[test]
+    fn test_rocket_initial_mass_matches_raw_value() {
+        let rocket = RocketConfig::builder()
+            .m_dry(1500.0)
+            .m_fuel(500.0)
+            .t_min_vac(5_000.0)
+            .t_max_vac(20_000.0)
+            .tdot_min(-10_000.0)
+            .tdot_max(10_000.0)
+            .i_sp(300.0)
+            .a_nozzle(0.5)
+            .theta_max(30.0)
+            .s_d(10.0)
+            .c_d(0.5)
+            .build();
+
+        assert_eq!(rocket.m_0(), 2000.0);
+        assert_eq!(rocket.m_0_mass().get::<kilogram>(), rocket.m_0());
+    }
+}
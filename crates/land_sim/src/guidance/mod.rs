@@ -123,12 +123,10 @@ fn follow_trajectory(
 fn step_at_time(
     elapsed_time: f32,
     sol: &gfold_rs::trajectories::APDGSolution,
-) -> Option<&APDGSolutionTimeStep> {
-    let dt = sol.dt() as f32;
-    if dt <= 0.0 {
+) -> Option<APDGSolutionTimeStep> {
+    if sol.dt() <= 0.0 {
         return None;
     }
 
-    let step_idx = ((elapsed_time / dt) as usize).min(sol.num_steps() - 1);
-    Some(&sol.steps()[step_idx])
+    sol.sample(elapsed_time as f64)
 }